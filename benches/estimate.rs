@@ -0,0 +1,145 @@
+//! Benchmarks the sampled estimator on representative boards: an empty and a dense 9x9 board, a
+//! 9x9 board of twenty pieces, the board of twelve pieces the estimator tests use, and a large
+//! sparse board. The twelve-piece
+//! board is also estimated on rayon pools of 1, 2, 4 and as many threads as there are cores, to
+//! show how the simulations scale.
+//! Run with `cargo bench --bench estimate`.
+//!
+//! Every estimate runs `PANEL_GAME_BENCH_SIMULATIONS` simulations, [`DEFAULT_SIMULATIONS`] unless
+//! set, so a quick run such as one in CI can use fewer and a local one more.
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use panel_game::estimator::{DEFAULT_SIMULATIONS, EstimateOptions, estimate_with_options};
+use panel_game::types::{Grid, Rectangle};
+use rayon::ThreadPoolBuilder;
+use std::hint::black_box;
+
+/// The environment variable that overrides the number of simulations of each estimate.
+const SIMULATIONS_ENV: &str = "PANEL_GAME_BENCH_SIMULATIONS";
+
+/// Reads the number of simulations from [`SIMULATIONS_ENV`], falling back to
+/// [`DEFAULT_SIMULATIONS`] if it is unset.
+///
+/// # Panics
+///
+/// Panics if the variable is set to anything but a positive integer.
+fn simulations_from_env() -> usize {
+    match std::env::var(SIMULATIONS_ENV) {
+        Ok(value) => value
+            .parse()
+            .ok()
+            .filter(|&count| count > 0)
+            .unwrap_or_else(|| {
+                panic!("{SIMULATIONS_ENV} must be a positive integer, not {value:?}")
+            }),
+        Err(_) => DEFAULT_SIMULATIONS,
+    }
+}
+
+/// Builds the rectangles of the given widths and heights.
+fn pieces(sizes: &[(usize, usize)]) -> Vec<Rectangle> {
+    sizes
+        .iter()
+        .map(|&(width, height)| Rectangle::new(width, height).unwrap())
+        .collect()
+}
+
+/// Builds the board of twelve pieces the estimator tests use.
+fn twelve_pieces() -> (Grid<bool>, Vec<Rectangle>) {
+    let rect_mask = Grid::from(
+        &[
+            ".#.......",
+            "......#..",
+            "...##....",
+            "..#......",
+            "..####..#",
+        ][..],
+    );
+    let rectangles = pieces(&[
+        (2, 1),
+        (2, 1),
+        (2, 1),
+        (2, 1),
+        (2, 1),
+        (2, 1),
+        (3, 1),
+        (3, 1),
+        (3, 1),
+        (3, 1),
+        (4, 1),
+        (4, 1),
+    ]);
+    (rect_mask, rectangles)
+}
+
+/// Builds twenty pieces covering almost half of a 9x9 board.
+fn twenty_pieces() -> Vec<Rectangle> {
+    let mut sizes = vec![(4, 1), (3, 1), (3, 1), (3, 1), (2, 2)];
+    sizes.extend([(2, 1); 6]);
+    sizes.extend([(1, 1); 9]);
+    pieces(&sizes)
+}
+
+/// Returns the options of a seeded estimate of [`simulations_from_env`] simulations.
+fn bench_options() -> EstimateOptions {
+    EstimateOptions {
+        simulations: simulations_from_env(),
+        seed: Some(1),
+        ..Default::default()
+    }
+}
+
+fn bench_boards(c: &mut Criterion) {
+    let options = bench_options();
+    let fleet = pieces(&[(5, 1), (4, 1), (3, 1), (3, 1), (2, 1)]);
+    let (twelve, twelve_rectangles) = twelve_pieces();
+    let boards = [
+        ("empty 9x9", Grid::new(9, 9, false), fleet.clone()),
+        // Half the cells are blocked, leaving the fleet few places to go.
+        ("dense 9x9", Grid::random_mask(9, 9, 0.5, 1), fleet.clone()),
+        ("twenty pieces 9x9", Grid::new(9, 9, false), twenty_pieces()),
+        ("twelve pieces", twelve, twelve_rectangles),
+        ("sparse 16x16", Grid::random_mask(16, 16, 0.1, 1), fleet),
+    ];
+
+    let mut group = c.benchmark_group("estimate");
+    // A single estimate takes up to seconds, so take the fewest samples criterion allows.
+    group.sample_size(10);
+    for (name, rect_mask, rectangles) in &boards {
+        group.bench_with_input(
+            BenchmarkId::new(*name, options.simulations),
+            &(rect_mask, rectangles),
+            |b, (rect_mask, rectangles)| {
+                b.iter(|| estimate_with_options(black_box(rect_mask), rectangles, &options))
+            },
+        );
+    }
+    group.finish();
+}
+
+fn bench_threads(c: &mut Criterion) {
+    let options = bench_options();
+    let (rect_mask, rectangles) = twelve_pieces();
+    let cores = std::thread::available_parallelism().map_or(1, |cores| cores.get());
+    let mut threads = vec![1, 2, 4, cores];
+    threads.sort_unstable();
+    threads.dedup();
+
+    let mut group = c.benchmark_group("threads");
+    group.sample_size(10);
+    for threads in threads {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        group.bench_function(BenchmarkId::new("twelve pieces", threads), |b| {
+            b.iter(|| {
+                pool.install(|| estimate_with_options(black_box(&rect_mask), &rectangles, &options))
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_boards, bench_threads);
+criterion_main!(benches);
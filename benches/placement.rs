@@ -0,0 +1,127 @@
+//! Benchmarks the placement of the sampled estimator: the allocations of the simulations on a
+//! 9x9 board, which reuse their buffers, and a cap on the candidate placements tried per shape,
+//! on a large open board and on a large cluttered one where most candidates do not fit.
+//! Run with `cargo bench --bench placement`.
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use panel_game::estimator::{EstimateOptions, estimate_with_options};
+use panel_game::types::{Grid, Rectangle};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The number of simulations of each estimate.
+const SIMULATIONS: usize = 20_000;
+
+/// The allocator of the benches, counting the allocations it makes.
+struct CountingAllocator;
+
+/// The number of allocations made so far, reallocations included.
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Builds the rectangles of the given widths and heights.
+fn pieces(sizes: &[(usize, usize)]) -> Vec<Rectangle> {
+    sizes
+        .iter()
+        .map(|&(width, height)| Rectangle::new(width, height).unwrap())
+        .collect()
+}
+
+/// Benchmarks the estimate of a board under each cap.
+fn bench_caps(c: &mut Criterion, name: &str, rect_mask: &Grid<bool>, rectangles: &[Rectangle]) {
+    let mut group = c.benchmark_group(name);
+    group.sample_size(10);
+    for max_candidates in [None, Some(256), Some(64), Some(16)] {
+        let options = EstimateOptions {
+            simulations: SIMULATIONS,
+            seed: Some(1),
+            max_candidates,
+            ..Default::default()
+        };
+        let cap = max_candidates.map_or("unbounded".to_string(), |max| max.to_string());
+        group.bench_function(BenchmarkId::new("max_candidates", cap), |b| {
+            b.iter(|| estimate_with_options(black_box(rect_mask), rectangles, &options))
+        });
+    }
+    group.finish();
+}
+
+/// Reports the allocations of estimates of a 9x9 board at growing numbers of simulations, which
+/// stay flat as every worker reuses its buffers across simulations, and benchmarks the largest.
+fn bench_allocations(c: &mut Criterion) {
+    let rect_mask = Grid::random_mask(9, 9, 5.0 / 81.0, 1);
+    let rectangles = pieces(&[
+        (5, 1),
+        (4, 1),
+        (3, 1),
+        (3, 1),
+        (2, 1),
+        (2, 1),
+        (2, 2),
+        (1, 1),
+    ]);
+    let options = |simulations| EstimateOptions {
+        simulations,
+        seed: Some(1),
+        ..Default::default()
+    };
+    // Start the rayon workers before counting, so their setup is left out.
+    estimate_with_options(&rect_mask, &rectangles, &options(1000));
+    for simulations in [1000, 10_000, 100_000] {
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        estimate_with_options(&rect_mask, &rectangles, &options(simulations));
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+        println!("9x9 board: {allocations} allocations for {simulations} simulations");
+    }
+
+    let options = options(100_000);
+    let mut group = c.benchmark_group("reused scratch 9x9");
+    group.sample_size(10);
+    group.bench_function("100000 simulations", |b| {
+        b.iter(|| estimate_with_options(black_box(&rect_mask), &rectangles, &options))
+    });
+    group.finish();
+}
+
+fn bench_max_candidates(c: &mut Criterion) {
+    let open = Grid::new(32, 32, false);
+    let rectangles = pieces(&[(5, 1), (4, 1), (3, 1), (3, 1), (2, 1), (2, 2), (1, 1)]);
+    bench_caps(c, "open 32x32", &open, &rectangles);
+
+    // Two in five cells are blocked at random, leaving mostly short gaps the long pieces rarely
+    // fit.
+    let cluttered = Grid::random_mask(24, 24, 0.4, 1);
+    let rectangles = pieces(&[
+        (4, 1),
+        (4, 1),
+        (4, 1),
+        (3, 1),
+        (3, 1),
+        (3, 1),
+        (2, 1),
+        (2, 1),
+    ]);
+    bench_caps(c, "cluttered 24x24", &cluttered, &rectangles);
+}
+
+criterion_group!(benches, bench_allocations, bench_max_candidates);
+criterion_main!(benches);
@@ -0,0 +1,27 @@
+//! Captures the git commit the crate is built from, for `GET /version` to report.
+
+use std::process::Command;
+
+/// The environment variable the commit is exposed to the crate as, and may be given in when the
+/// build has no git checkout, such as on a deployment server.
+const GIT_SHA_ENV: &str = "PANEL_GAME_GIT_SHA";
+
+fn main() {
+    println!("cargo:rerun-if-env-changed={GIT_SHA_ENV}");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+
+    let sha = std::env::var(GIT_SHA_ENV)
+        .ok()
+        .filter(|sha| !sha.is_empty())
+        .or_else(|| {
+            let output = Command::new("git")
+                .args(["rev-parse", "--short=12", "HEAD"])
+                .output()
+                .ok()?;
+            let sha = String::from_utf8(output.stdout).ok()?;
+            output.status.success().then(|| sha.trim().to_string())
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env={GIT_SHA_ENV}={sha}");
+}
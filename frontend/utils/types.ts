@@ -36,7 +36,8 @@ export interface Grid<T> {
   data: T[][]
 }
 
-export type Color = [number, number, number]
+/** A color as a `#rrggbb` hex string. */
+export type Color = string
 
 export interface InferenceResult {
   probabilities: Grid<[number, Color]>
@@ -0,0 +1,114 @@
+use crate::api::error::{ApiError, ErrorResponse};
+use crate::api::estimate::{RequestParams, ResponseMessage, run_estimate, to_response_message};
+use crate::api::limits::GridLimits;
+use crate::api::pool::EstimatorPool;
+use actix_web::HttpResponse;
+use actix_web::{post, web};
+use rayon::prelude::*;
+use serde::Serialize;
+
+const MAX_BATCH_SIZE: usize = 32;
+
+/// The result of one item of a batch.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum BatchItem {
+    /// The item was estimated.
+    Ok(Box<ResponseMessage>),
+    /// The item was invalid.
+    Err(ErrorResponse),
+}
+
+/// Checks if the number of items in a batch is valid.
+fn validate_batch_size(size: usize) -> bool {
+    size <= MAX_BATCH_SIZE
+}
+
+/// Estimates the probabilities of several boards in parallel.
+///
+/// Responds with one result per item in the order of the request, each either a response message
+/// or the error an invalid item would get from `POST /estimate`. Only a batch with more than
+/// [`MAX_BATCH_SIZE`] items is rejected as a whole, with `422 Unprocessable Entity`.
+#[post("/estimate/batch")]
+pub async fn estimate_batch(
+    params: web::Json<Vec<RequestParams>>,
+    pool: web::Data<EstimatorPool>,
+    limits: web::Data<GridLimits>,
+) -> Result<HttpResponse, ApiError> {
+    if !validate_batch_size(params.len()) {
+        return Err(ApiError::unprocessable(
+            "batch_too_large",
+            format!("a batch must have at most {MAX_BATCH_SIZE} items"),
+        ));
+    }
+
+    let params = params.into_inner();
+    let items: Vec<BatchItem> = pool
+        .run(move || {
+            params
+                .par_iter()
+                .map(|param| match run_estimate(param, &limits) {
+                    Ok(result) => BatchItem::Ok(Box::new(to_response_message(result, param))),
+                    Err(error) => BatchItem::Err(error.into_body()),
+                })
+                .collect()
+        })
+        .await?;
+    Ok(HttpResponse::Ok().json(items))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_app;
+    use actix_web::{http::StatusCode, test};
+    use serde_json::json;
+
+    #[actix_web::test]
+    async fn test_validate_batch_size() {
+        assert!(validate_batch_size(0));
+        assert!(validate_batch_size(MAX_BATCH_SIZE));
+        assert!(!validate_batch_size(MAX_BATCH_SIZE + 1));
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_batch() {
+        let app = test::init_service(test_app().service(estimate_batch)).await;
+        let req = test::TestRequest::post()
+            .uri("/estimate/batch")
+            .set_json(json!([
+                {"mask": {"rows": 1, "cols": 3, "data": [[false, false, false]]},
+                 "rectangles": [{"width": 2, "height": 1}]},
+                {"mask": {"rows": 1, "cols": 3, "data": [[false, false, false]]},
+                 "rectangles": [{"width": 4, "height": 1}]},
+                {"mask": {"rows": 1, "cols": 2, "data": [[false, true]]},
+                 "rectangles": [], "simulations": 0},
+            ]))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let items: Vec<serde_json::Value> = test::read_body_json(resp).await;
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0]["feasibility"], 1.0);
+        assert_eq!(items[0]["probabilities"]["data"][0][1][0], 1.0);
+        assert_eq!(items[1]["code"], "rectangle_too_large");
+        assert_eq!(items[2]["code"], "invalid_simulations");
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_batch_too_large() {
+        let app = test::init_service(test_app().service(estimate_batch)).await;
+        let item = json!({"mask": {"rows": 1, "cols": 1, "data": [[false]]}, "rectangles": []});
+        let req = test::TestRequest::post()
+            .uri("/estimate/batch")
+            .set_json(vec![item; MAX_BATCH_SIZE + 1])
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body: ErrorResponse = test::read_body_json(resp).await;
+        assert_eq!(body.code, "batch_too_large");
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// A bounded cache shared by every worker, evicting the least recently used entry when full.
+///
+/// The entries are scanned linearly, which is simple and fast enough for the few dozen entries a
+/// cache of whole estimates is kept to.
+pub struct LruCache<K, V> {
+    /// The entries from the least to the most recently used.
+    entries: Arc<Mutex<VecDeque<(K, V)>>>,
+    /// The most entries kept.
+    capacity: usize,
+}
+
+impl<K, V> Clone for LruCache<K, V> {
+    fn clone(&self) -> Self {
+        LruCache {
+            entries: self.entries.clone(),
+            capacity: self.capacity,
+        }
+    }
+}
+
+impl<K: PartialEq, V: Clone> LruCache<K, V> {
+    /// Creates an empty [`LruCache`] keeping up to `capacity` entries.
+    pub fn new(capacity: usize) -> LruCache<K, V> {
+        LruCache {
+            entries: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    /// Returns the value cached for a key, if any, marking it as the most recently used.
+    pub fn get(&self, key: &K) -> Option<V> {
+        let mut entries = self
+            .entries
+            .lock()
+            .unwrap_or_else(|error| error.into_inner());
+        let index = entries.iter().position(|(entry, _)| entry == key)?;
+        let entry = entries.remove(index)?;
+        let value = entry.1.clone();
+        entries.push_back(entry);
+        Some(value)
+    }
+
+    /// Caches a value for a key, evicting the least recently used entry if the cache is full.
+    pub fn insert(&self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self
+            .entries
+            .lock()
+            .unwrap_or_else(|error| error.into_inner());
+        entries.retain(|(entry, _)| *entry != key);
+        if entries.len() >= self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back((key, value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_lru_cache_evicts() {
+        let cache = LruCache::new(3);
+        for key in 1..=3 {
+            cache.insert(key, key * 10);
+        }
+        // Using the first entry keeps it, so the second is evicted instead.
+        assert_eq!(cache.get(&1), Some(10));
+        cache.insert(4, 40);
+        assert_eq!(cache.get(&1), Some(10));
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.get(&4), Some(40));
+
+        // Inserting a cached key replaces its value without evicting another.
+        cache.insert(3, 31);
+        assert_eq!(cache.get(&3), Some(31));
+        assert_eq!(cache.get(&1), Some(10));
+        assert_eq!(cache.get(&4), Some(40));
+    }
+
+    #[actix_web::test]
+    async fn test_lru_cache_shared() {
+        let cache = LruCache::new(1);
+        cache.clone().insert("board", 1);
+        assert_eq!(cache.get(&"board"), Some(1));
+        assert_eq!(LruCache::<&str, i32>::new(0).get(&"board"), None);
+    }
+}
@@ -0,0 +1,103 @@
+use actix_cors::Cors;
+use actix_web::http::{Method, header};
+
+/// The origins other than the service's own that browsers may call the API from.
+///
+/// Empty, so only same-origin requests are allowed. Add e.g. `"http://localhost:5173"` to develop
+/// the frontend against a local service, or the CDN origin it is served from.
+pub const ALLOWED_ORIGINS: &[&str] = &[];
+
+/// How long a browser may cache the answer to a preflight request, in seconds.
+const PREFLIGHT_MAX_AGE: usize = 3600;
+
+/// Builds the CORS middleware allowing cross-origin calls from the given origins.
+///
+/// An allowed origin may send `GET` and `POST` requests with a JSON body, so the browser's
+/// preflight for `POST /estimate` succeeds. Preflights from any other origin are rejected. Other
+/// requests are still served, as same-origin posts also carry an `Origin`, but without CORS
+/// headers, so browsers withhold the responses from scripts of other origins.
+///
+/// # Arguments
+///
+/// * `origins` - The allowed origins, such as [`ALLOWED_ORIGINS`], each a scheme, host and
+///   optional port.
+pub fn cors(origins: &[&str]) -> Cors {
+    origins
+        .iter()
+        .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin))
+        .allowed_methods([Method::GET, Method::POST])
+        .allowed_headers([header::CONTENT_TYPE, header::ACCEPT])
+        .max_age(PREFLIGHT_MAX_AGE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::estimate::estimate;
+    use crate::api::test_app;
+    use actix_web::{http::StatusCode, test};
+
+    const ORIGIN: &str = "http://localhost:5173";
+
+    /// Builds the preflight a browser sends from `origin` before `POST /estimate` with JSON.
+    fn preflight(origin: &str) -> test::TestRequest {
+        test::TestRequest::default()
+            .method(Method::OPTIONS)
+            .uri("/estimate")
+            .insert_header((header::ORIGIN, origin))
+            .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "POST"))
+            .insert_header((header::ACCESS_CONTROL_REQUEST_HEADERS, "content-type"))
+    }
+
+    #[actix_web::test]
+    async fn test_cors_preflight() {
+        let app = test::init_service(test_app().wrap(cors(&[ORIGIN])).service(estimate)).await;
+
+        let resp = test::call_service(&app, preflight(ORIGIN).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let headers = resp.headers();
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            ORIGIN
+        );
+        let methods = headers.get(header::ACCESS_CONTROL_ALLOW_METHODS).unwrap();
+        assert!(methods.to_str().unwrap().contains("POST"));
+        let allowed = headers.get(header::ACCESS_CONTROL_ALLOW_HEADERS).unwrap();
+        assert!(allowed.to_str().unwrap().contains("content-type"));
+        assert_eq!(headers.get(header::ACCESS_CONTROL_MAX_AGE).unwrap(), "3600");
+
+        let resp = test::call_service(&app, preflight("https://example.com").to_request()).await;
+        assert!(resp.status().is_client_error());
+        assert!(
+            resp.headers()
+                .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .is_none()
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_cors_same_origin_only() {
+        let app =
+            test::init_service(test_app().wrap(cors(ALLOWED_ORIGINS)).service(estimate)).await;
+
+        let resp = test::call_service(&app, preflight(ORIGIN).to_request()).await;
+        assert!(resp.status().is_client_error());
+        assert!(
+            resp.headers()
+                .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .is_none()
+        );
+
+        // Browsers also send an `Origin` with same-origin posts, which must still be served.
+        let req = test::TestRequest::post()
+            .uri("/estimate")
+            .insert_header((header::ORIGIN, "https://panel-game.example"))
+            .set_json(serde_json::json!({
+                "mask": {"rows": 1, "cols": 2, "data": [[false, false]]},
+                "rectangles": [{"width": 2, "height": 1}],
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+}
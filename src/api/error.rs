@@ -0,0 +1,243 @@
+use crate::error::{BoardGrid, Error};
+use actix_web::error::{JsonPayloadError, QueryPayloadError};
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError, web};
+use serde::{Deserialize, Serialize};
+
+/// The body of an error response.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ErrorResponse {
+    /// A machine-readable identifier of the error, e.g. `grid_too_large`.
+    pub code: String,
+    /// A human-readable description of the error.
+    pub message: String,
+}
+
+/// An error returned by an endpoint, responded with its status and an [`ErrorResponse`] body.
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    body: ErrorResponse,
+}
+
+impl ApiError {
+    /// Creates a `400 Bad Request` error for a request that could not be parsed.
+    pub fn bad_request(code: &str, message: impl Into<String>) -> ApiError {
+        ApiError::new(StatusCode::BAD_REQUEST, code, message)
+    }
+
+    /// Creates a `422 Unprocessable Entity` error for a well-formed request with invalid values.
+    pub fn unprocessable(code: &str, message: impl Into<String>) -> ApiError {
+        ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, code, message)
+    }
+
+    /// Creates a `413 Payload Too Large` error for a body larger than the service accepts.
+    pub fn payload_too_large(code: &str, message: impl Into<String>) -> ApiError {
+        ApiError::new(StatusCode::PAYLOAD_TOO_LARGE, code, message)
+    }
+
+    /// Creates a `404 Not Found` error for a resource that does not exist.
+    pub fn not_found(code: &str, message: impl Into<String>) -> ApiError {
+        ApiError::new(StatusCode::NOT_FOUND, code, message)
+    }
+
+    /// Creates a `500 Internal Server Error` error for a request the service failed to handle.
+    pub fn internal(code: &str, message: impl Into<String>) -> ApiError {
+        ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, code, message)
+    }
+
+    /// Returns the body of the error, for reporting it inside another response.
+    pub fn into_body(self) -> ErrorResponse {
+        self.body
+    }
+
+    fn new(status: StatusCode, code: &str, message: impl Into<String>) -> ApiError {
+        ApiError {
+            status,
+            body: ErrorResponse {
+                code: code.to_string(),
+                message: message.into(),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.body.code, self.body.message)
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status).json(&self.body)
+    }
+}
+
+impl From<Error> for ApiError {
+    /// Maps an error of the estimator to the status and code the endpoints respond with.
+    ///
+    /// A board without any layout gives `404 Not Found` with the code `no_layout`, and every other
+    /// error `422 Unprocessable Entity`, with the code of the matching request check where there
+    /// is one.
+    fn from(error: Error) -> Self {
+        let message = error.to_string();
+        match error {
+            Error::Infeasible => ApiError::not_found("no_layout", message),
+            Error::ZeroDimension => ApiError::unprocessable("grid_empty", message),
+            Error::ZeroMaxCandidates => ApiError::unprocessable("invalid_max_candidates", message),
+            Error::GridDimensionMismatch {
+                grid: BoardGrid::Required,
+                ..
+            } => ApiError::unprocessable("required_mismatch", message),
+            Error::GridDimensionMismatch {
+                grid: BoardGrid::Weights { .. },
+                ..
+            } => ApiError::unprocessable("invalid_weights", message),
+            Error::OversizedRectangle { .. } => {
+                ApiError::unprocessable("rectangle_too_large", message)
+            }
+            Error::InvalidRectangleIndex { .. } => {
+                ApiError::unprocessable("invalid_of_rectangles", message)
+            }
+            Error::Grid(_) => ApiError::unprocessable("invalid_grid", message),
+            Error::Sunk(_) => ApiError::unprocessable("invalid_sunk", message),
+        }
+    }
+}
+
+/// The largest JSON body accepted, in bytes.
+///
+/// A board of the largest grid with a weighted grid for each of a few pieces fits well within it,
+/// while larger bodies are rejected before any of them is deserialized.
+pub const MAX_JSON_PAYLOAD: usize = 1 << 20;
+
+/// Configures JSON bodies to be limited to [`MAX_JSON_PAYLOAD`] bytes and rejected with an
+/// [`ErrorResponse`].
+///
+/// A larger body gives `413 Payload Too Large` with the code `payload_too_large`, syntactically
+/// malformed JSON gives `400 Bad Request` with the code `malformed_json`, a field the request
+/// does not know gives `400 Bad Request` with the code `unknown_field`, and JSON with invalid
+/// values (e.g. a zero-dimension rectangle or a ragged mask) gives `422 Unprocessable Entity` with
+/// the code `invalid_json`.
+pub fn json_config() -> web::JsonConfig {
+    web::JsonConfig::default()
+        .limit(MAX_JSON_PAYLOAD)
+        .error_handler(|error, _| {
+            let message = error.to_string();
+            let api_error = match &error {
+                JsonPayloadError::Overflow { .. }
+                | JsonPayloadError::OverflowKnownLength { .. } => {
+                    ApiError::payload_too_large("payload_too_large", message)
+                }
+                // serde reports no kind for unknown fields, only its message.
+                JsonPayloadError::Deserialize(error)
+                    if error.is_data() && error.to_string().starts_with("unknown field") =>
+                {
+                    ApiError::bad_request("unknown_field", message)
+                }
+                JsonPayloadError::Deserialize(error) if error.is_data() => {
+                    ApiError::unprocessable("invalid_json", message)
+                }
+                _ => ApiError::bad_request("malformed_json", message),
+            };
+            api_error.into()
+        })
+}
+
+/// Configures query strings to be rejected with a `400 Bad Request` [`ErrorResponse`].
+pub fn query_config() -> web::QueryConfig {
+    web::QueryConfig::default().error_handler(|error: QueryPayloadError, _| {
+        ApiError::bad_request("malformed_query", error.to_string()).into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::body::to_bytes;
+
+    #[actix_web::test]
+    async fn test_api_error_response() {
+        let error = ApiError::unprocessable("grid_too_large", "grid must be at most 9x9");
+        assert_eq!(error.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let body = to_bytes(error.error_response().into_body()).await.unwrap();
+        let body: ErrorResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body.code, "grid_too_large");
+        assert_eq!(body.message, "grid must be at most 9x9");
+    }
+
+    #[test]
+    fn test_api_error_from_error() {
+        use crate::battleship::SunkError;
+        use crate::types::GridError;
+
+        let cases = [
+            (Error::Infeasible, StatusCode::NOT_FOUND, "no_layout"),
+            (
+                Error::ZeroDimension,
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "grid_empty",
+            ),
+            (
+                Error::ZeroMaxCandidates,
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "invalid_max_candidates",
+            ),
+            (
+                Error::GridDimensionMismatch {
+                    grid: BoardGrid::Required,
+                    expected: (2, 2),
+                    found: (1, 2),
+                },
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "required_mismatch",
+            ),
+            (
+                Error::GridDimensionMismatch {
+                    grid: BoardGrid::Weights { rectangle: 0 },
+                    expected: (2, 2),
+                    found: (1, 2),
+                },
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "invalid_weights",
+            ),
+            (
+                Error::OversizedRectangle { rectangle: 0 },
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "rectangle_too_large",
+            ),
+            (
+                Error::InvalidRectangleIndex {
+                    index: 3,
+                    rectangles: 2,
+                },
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "invalid_of_rectangles",
+            ),
+            (
+                Error::Grid(GridError::Empty),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "invalid_grid",
+            ),
+            (
+                Error::Sunk(SunkError::OutOfBounds { ship: 0 }),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "invalid_sunk",
+            ),
+        ];
+        for (error, status, code) in cases {
+            let message = error.to_string();
+            let error = ApiError::from(error);
+            assert_eq!(error.status_code(), status, "{code}");
+            let body = error.into_body();
+            assert_eq!(body.code, code);
+            assert_eq!(body.message, message);
+        }
+    }
+}
@@ -1,90 +1,1763 @@
-use crate::colors::{Color, ColorMap};
+use crate::api::cache::LruCache;
+use crate::api::error::ApiError;
+use crate::api::limits::{GridLimits, MAX_GRID_SIDE};
+use crate::api::pool::EstimatorPool;
+use crate::battleship::{SunkShip, estimate_battleship};
+use crate::colors::{Color, ColorMap, to_legend};
 use crate::estimator::to_entropy;
-use crate::types::Rectangle;
-use crate::{estimator::estimate_probabilities, types::Grid};
-use actix_web::HttpResponse;
-use actix_web::{Responder, post, web};
-use serde::{Deserialize, Serialize};
+use crate::notation::{parse_mask, parse_rectangles};
+use crate::types::{CellState, Position, Rectangle};
+use crate::{
+    estimator::{
+        DEFAULT_NODE_BUDGET, DEFAULT_SIMULATIONS, Estimate, EstimateOptions, check_rectangles,
+        estimate_exact_with, estimate_with_options, to_checked_shapes,
+    },
+    types::Grid,
+};
+use actix_web::http::header::Accept;
+use actix_web::{HttpResponse, mime};
+use actix_web::{get, post, web};
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize, de::Error};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::Instrument;
 
-const MAX_GRID_ROWS: usize = 9;
-const MAX_GRID_COLS: usize = 9;
+const MIN_SIMULATIONS: usize = 1;
+const MAX_SIMULATIONS: usize = 5_000_000;
+const EXACT_MAX_GRID_AREA: usize = 16;
+const EXACT_MAX_RECTANGLES: usize = 4;
+/// The most rectangles a request may ask for, as more can never fit the largest grid.
+const MAX_RECTANGLE_COUNT: usize = MAX_GRID_SIDE * MAX_GRID_SIDE;
+/// The longest a request may sample for, after which the estimate so far is returned, so a
+/// pathological board cannot hold a worker for its full number of simulations.
+const REQUEST_DEADLINE: Duration = Duration::from_secs(10);
+/// The version of the format of the response message, raised whenever a field is renamed or
+/// removed or changes its meaning.
+const API_VERSION: u32 = 1;
+/// The number of stops of each color map in the legend of a response, unless requested otherwise.
+const DEFAULT_LEGEND_STOPS: usize = 11;
+const MIN_LEGEND_STOPS: usize = 2;
+const MAX_LEGEND_STOPS: usize = 256;
+/// The most estimates kept by an [`EstimateCache`].
+const ESTIMATE_CACHE_CAPACITY: usize = 64;
+/// The least a probability must change from the baseline to be returned, unless requested
+/// otherwise.
+const DEFAULT_DELTA_THRESHOLD: f64 = 1e-3;
 
 /// The request parameters.
-#[derive(Serialize, Deserialize)]
-struct RequestParams {
-    /// A mask of the grid.
-    mask: Grid<bool>,
+///
+/// The wire names of the fields are their snake_case Rust names. A field the request does not
+/// know is rejected rather than ignored, so a misspelled parameter is not silently left at its
+/// default.
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub(super) struct RequestParams {
+    /// The state of each cell of the grid.
+    ///
+    /// Also accepted as a plain grid mask, where `true` marks a cell revealed to be empty and
+    /// `false` a cell not revealed yet, or as a grid mask whose polarity is named, either
+    /// `{"blocked": mask}` with the same meaning or `{"free": mask}` where `true` marks a cell
+    /// that may still be covered.
+    #[serde(deserialize_with = "deserialize_mask")]
+    #[schemars(with = "MaskRepr")]
+    pub(super) mask: Grid<CellState>,
     /// A list of rectangles to be placed.
-    rectangles: Vec<Rectangle>,
+    ///
+    /// A rectangle may be given a `count` to stand for that many identical rectangles, each with
+    /// its own index in the occupants.
+    #[serde(deserialize_with = "expand_rectangles")]
+    #[schemars(with = "Vec<RectangleSpec>")]
+    pub(super) rectangles: Vec<Rectangle>,
+    /// The number of simulations to run. Defaults to [`DEFAULT_SIMULATIONS`].
+    pub(super) simulations: Option<usize>,
+    /// The seed of the random number generator, for reproducible estimates.
+    pub(super) seed: Option<u64>,
+    /// Stops once no probability changes by more than this between two checkpoints, if given.
+    pub(super) tolerance: Option<f64>,
+    /// A mask of the cells known to be covered by some rectangle, in addition to the occupied
+    /// cells of `mask`.
+    ///
+    /// Independent of `mask`: a cell that is both empty and required can never be covered.
+    pub(super) required: Option<Grid<bool>>,
+    /// The color map of the probabilities. Defaults to [`ColorMap::Viridis`].
+    pub(super) probability_cmap: Option<ColorMap>,
+    /// The color map of the entropies. Defaults to [`ColorMap::Magma`].
+    pub(super) entropy_cmap: Option<ColorMap>,
+    /// Whether to pair each value with a color. Defaults to `true`.
+    pub(super) colorize: Option<bool>,
+    /// Whether to color the entropies against their own range rather than `[0, 1]`, so the most
+    /// uncertain cell gets the end of the color map. The entropy values are unchanged. Defaults to
+    /// `false`.
+    pub(super) normalize_entropy: Option<bool>,
+    /// Whether to color the probabilities against the largest one rather than `[0, 1]`, so the
+    /// most likely cell gets the end of the color map even on a board where every probability is
+    /// small. The probability values are unchanged. Defaults to `false`.
+    pub(super) rescale_probabilities: Option<bool>,
+    /// Colors every probability below this like 0, so the rare flukes of a few simulations do not
+    /// show on the heatmap, if given. The probability values are unchanged. Must be at least 0 and
+    /// less than 1.
+    pub(super) probability_threshold: Option<f64>,
+    /// The number of stops of each color map in the legend of a colorized response. Defaults to
+    /// [`DEFAULT_LEGEND_STOPS`].
+    pub(super) legend_stops: Option<usize>,
+    /// The rectangles whose coverage the probabilities count, by their 1-based index like the
+    /// occupants, if any. Every rectangle counts if empty or not given.
+    ///
+    /// The entropies follow the probabilities, while the occupants and the expected coverage still
+    /// count every rectangle. Filtered boards are always sampled rather than enumerated exactly.
+    pub(super) of_rectangles: Option<Vec<usize>>,
+    /// The ships revealed to be sunk, for battleship rules, if any.
+    ///
+    /// Each takes a rectangle that may lie like it out of `rectangles` and masks its footprint, so
+    /// only the ships still afloat are estimated and the occupied cells of its footprint need no
+    /// covering.
+    pub(super) sunk: Option<Vec<SunkShip>>,
+    /// The probabilities of an earlier estimate of the board, if any, to respond with only the
+    /// cells whose probability changed since.
+    ///
+    /// A baseline of another size than the mask, e.g. an empty grid, returns every cell. Only
+    /// `POST /estimate` responds with the changes; the other endpoints ignore the baseline.
+    pub(super) baseline: Option<Grid<f64>>,
+    /// The most a probability may change from the baseline and not be returned. Defaults to
+    /// [`DEFAULT_DELTA_THRESHOLD`].
+    pub(super) delta_threshold: Option<f64>,
+    /// Whether to pair each simulation with its antithetic one, for a lower variance at the same
+    /// number of simulations, as by [`EstimateOptions::antithetic`]. Defaults to `false`.
+    pub(super) antithetic: Option<bool>,
+}
+
+/// The accepted representations of the mask of a request.
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum MaskRepr {
+    /// A grid mask, where `true` marks a blocked cell.
+    Blocked(Grid<bool>),
+    /// The state of each cell.
+    States(Grid<CellState>),
+    /// A grid mask of either polarity.
+    Polarized(PolarizedMask),
+}
+
+/// A grid mask whose polarity is named by its key.
+#[derive(Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+enum PolarizedMask {
+    /// `true` marks a blocked cell, revealed to be empty, like a plain grid mask.
+    Blocked(Grid<bool>),
+    /// `true` marks a free cell, not revealed yet.
+    Free(Grid<bool>),
+}
+
+/// Deserializes the mask of a request from any of its [`MaskRepr`]s.
+fn deserialize_mask<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Grid<CellState>, D::Error> {
+    Ok(match MaskRepr::deserialize(deserializer)? {
+        MaskRepr::Blocked(mask) | MaskRepr::Polarized(PolarizedMask::Blocked(mask)) => {
+            Grid::from(&mask)
+        }
+        MaskRepr::Polarized(PolarizedMask::Free(free)) => Grid::from(&free.map(|&free| !free)),
+        MaskRepr::States(states) => states,
+    })
+}
+
+impl RequestParams {
+    /// Pairs each probability with its color in a color map, against the largest probability if
+    /// the request asks to rescale them, and with the color of 0 if it is below the probability
+    /// threshold of the request.
+    pub(super) fn color_probabilities(
+        &self,
+        probabilities: &Grid<f64>,
+        cmap: &ColorMap,
+    ) -> Grid<(f64, Color)> {
+        let floored = self
+            .probability_threshold
+            .map(|threshold| probabilities.map(|&p| if p < threshold { 0.0 } else { p }));
+        let colored = floored.as_ref().unwrap_or(probabilities);
+        let mut pairs = if self.rescale_probabilities.unwrap_or(false) {
+            colored.to_rescaled_color_pairs(cmap)
+        } else {
+            colored.to_value_color_pairs(cmap)
+        };
+        if floored.is_some() {
+            for (pos, (value, _)) in pairs.iter_positions_mut() {
+                *value = probabilities[&pos];
+            }
+        }
+        pairs
+    }
+
+    /// Samples the legend of the color maps the probabilities and entropies are colored with.
+    ///
+    /// The stops span the values colored with the ends of each color map: `[0, 1]`, or the range
+    /// rescaled or normalized to if the request asks for it.
+    fn legend(
+        &self,
+        probabilities: &Grid<f64>,
+        entropy: &Grid<f64>,
+        probability_cmap: &ColorMap,
+        entropy_cmap: &ColorMap,
+    ) -> Legend {
+        let stops = self.legend_stops.unwrap_or(DEFAULT_LEGEND_STOPS);
+        let (_, max) = value_range(probabilities);
+        let probability_range = if self.rescale_probabilities.unwrap_or(false) && max > 0.0 {
+            (0.0, max)
+        } else {
+            (0.0, 1.0)
+        };
+        let (min, max) = value_range(entropy);
+        let entropy_range = if self.normalize_entropy.unwrap_or(false) && max - min > 0.0 {
+            (min, max)
+        } else {
+            (0.0, 1.0)
+        };
+        Legend {
+            probabilities: to_legend(probability_cmap, probability_range, stops),
+            entropy: to_legend(entropy_cmap, entropy_range, stops),
+        }
+    }
+
+    /// Splits the cell states into the grid mask of the empty cells and the mask of the cells that
+    /// must be covered, the occupied cells together with any `required` cells.
+    ///
+    /// A `required` grid of the wrong size is returned unchanged, for validation to reject.
+    pub(super) fn to_masks(&self) -> (Grid<bool>, Option<Grid<bool>>) {
+        let mask = self.mask.to_mask();
+        let occupied = self.mask.to_required();
+        let required = match &self.required {
+            Some(required) if validate_required(&mask, Some(required)) => {
+                let mut required = required.clone();
+                for (pos, cell) in required.iter_positions_mut() {
+                    *cell |= occupied[&pos];
+                }
+                Some(required)
+            }
+            Some(required) => Some(required.clone()),
+            None if occupied.iter_positions().any(|(_, &cell)| cell) => Some(occupied),
+            None => None,
+        };
+        (mask, required)
+    }
 }
 
-/// The response message.
+/// A rectangle of a request with the number of identical copies to place.
+#[derive(Deserialize, JsonSchema)]
+struct RectangleSpec {
+    #[serde(flatten)]
+    rect: Rectangle,
+    /// The number of copies. Defaults to 1.
+    #[serde(default = "default_count")]
+    count: usize,
+}
+
+/// Rectangles are placed once unless stated otherwise.
+fn default_count() -> usize {
+    1
+}
+
+/// Deserializes a list of [`RectangleSpec`]s into one [`Rectangle`] per copy.
+fn expand_rectangles<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Rectangle>, D::Error> {
+    let mut rectangles = Vec::new();
+    for spec in Vec::<RectangleSpec>::deserialize(deserializer)? {
+        if !(1..=MAX_RECTANGLE_COUNT).contains(&spec.count) {
+            return Err(D::Error::custom(format!(
+                "rectangle count must be between 1 and {MAX_RECTANGLE_COUNT}"
+            )));
+        }
+        // Checked before expanding the counts, so a long list cannot allocate without bound.
+        if rectangles.len() + spec.count > MAX_RECTANGLE_COUNT {
+            return Err(D::Error::custom(format!(
+                "at most {MAX_RECTANGLE_COUNT} rectangles may be placed"
+            )));
+        }
+        rectangles.extend(std::iter::repeat_n(spec.rect, spec.count));
+    }
+    Ok(rectangles)
+}
+
+/// The response message, with snake_case wire names like the request parameters.
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub(super) struct ResponseMessage {
+    /// The version of the format of the response, [`API_VERSION`].
+    version: u32,
+    /// The probabilities and entropies, tagged by their `format`.
+    #[serde(flatten)]
+    heatmaps: Heatmaps,
+    /// The standard error of each probability.
+    std_error: Grid<f64>,
+    /// The most likely rectangle (1-based index, or 0 for none) to cover each cell and its probability.
+    occupants: Grid<(usize, f64)>,
+    /// The expected number of rectangles covering each cell.
+    expected_coverage: Grid<f64>,
+    /// The fraction of simulations in which every rectangle was placed.
+    feasibility: f64,
+    /// Whether any layout of every rectangle consistent with the board was found.
+    ///
+    /// If not, every probability is 0 because nothing was counted, not because no rectangle covers
+    /// any cell: the board may be over-constrained, or need more simulations to find a layout.
+    feasible: bool,
+    /// Stops of the color maps of a colorized response, mapping colors back to values.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    legend: Option<Legend>,
+    /// How the estimate was computed.
+    meta: ResponseMeta,
+}
+
+/// The response message of a request with a baseline, only the probabilities that changed.
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+struct DeltaMessage {
+    /// The version of the format of the response, [`API_VERSION`].
+    version: u32,
+    /// The number of rows of the grid.
+    rows: usize,
+    /// The number of columns of the grid.
+    cols: usize,
+    /// Each cell whose probability changed by more than the threshold from the baseline, with its
+    /// new probability, in row-major order.
+    changes: Vec<(Position, f64)>,
+    /// The fraction of simulations in which every rectangle was placed.
+    feasibility: f64,
+    /// Whether any layout of every rectangle consistent with the board was found.
+    feasible: bool,
+    /// How the estimate was computed.
+    meta: ResponseMeta,
+}
+
+/// Evenly spaced `(value, color)` stops of the color maps of a response, from the start to the end
+/// of each color map.
+#[derive(Serialize, Deserialize, JsonSchema)]
+struct Legend {
+    probabilities: Vec<(f64, Color)>,
+    entropy: Vec<(f64, Color)>,
+}
+
+/// The metadata of a response.
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+struct ResponseMeta {
+    /// The number of simulations run, or 0 for an exact estimate.
+    simulations_run: usize,
+    /// The time the estimate took, in milliseconds.
+    elapsed_ms: u64,
+    /// The number of simulations in which every rectangle was placed, or the number of layouts of
+    /// an exact estimate.
+    feasible_samples: usize,
+    /// Whether the estimate was served from the cache of an identical earlier request, in which
+    /// case `elapsed_ms` is the time the earlier request took.
+    cached: bool,
+}
+
+/// The probability and entropy grids of a response.
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "format", rename_all = "kebab-case")]
+enum Heatmaps {
+    /// Each value is paired with its color in the requested color map.
+    Colorized {
+        probabilities: Grid<(f64, Color)>,
+        entropy: Grid<(f64, Color)>,
+    },
+    /// The bare values, for clients that do their own coloring.
+    Raw {
+        probabilities: Grid<f64>,
+        entropy: Grid<f64>,
+    },
+}
+
+/// Checks if the grid size is within the limits of the service.
+fn validate_grid_size(grid: &Grid<bool>, limits: &GridLimits) -> bool {
+    grid.rows() <= limits.rows()
+        && grid.cols() <= limits.cols()
+        && grid.rows() * grid.cols() <= limits.cells()
+}
+
+/// Checks if the number of simulations is valid.
+fn validate_simulations(simulations: usize) -> bool {
+    (MIN_SIMULATIONS..=MAX_SIMULATIONS).contains(&simulations)
+}
+
+/// Checks if the convergence tolerance is valid.
+fn validate_tolerance(tolerance: Option<f64>) -> bool {
+    tolerance.is_none_or(|tolerance| tolerance > 0.0 && tolerance <= 1.0)
+}
+
+/// Checks if there are at most as many rectangles as cells, as each rectangle covers one or more.
+fn validate_rectangle_count(grid: &Grid<bool>, rectangles: &[Rectangle]) -> bool {
+    rectangles.len() <= grid.rows() * grid.cols()
+}
+
+/// Checks if the number of legend stops, if given, is valid.
+fn validate_legend_stops(stops: Option<usize>) -> bool {
+    stops.is_none_or(|stops| (MIN_LEGEND_STOPS..=MAX_LEGEND_STOPS).contains(&stops))
+}
+
+/// Checks if every rectangle to count, if any, is a 1-based index of the rectangles.
+fn validate_of_rectangles(rectangles: &[Rectangle], of_rectangles: Option<&[usize]>) -> bool {
+    of_rectangles.is_none_or(|ids| ids.iter().all(|&id| (1..=rectangles.len()).contains(&id)))
+}
+
+/// Checks if the probability threshold, if given, is valid.
+fn validate_probability_threshold(threshold: Option<f64>) -> bool {
+    threshold.is_none_or(|threshold| (0.0..1.0).contains(&threshold))
+}
+
+/// Checks if the threshold of the changes from a baseline, if given, is valid.
+fn validate_delta_threshold(threshold: Option<f64>) -> bool {
+    threshold.is_none_or(|threshold| (0.0..=1.0).contains(&threshold))
+}
+
+/// Checks if the required mask, if any, has the same size as the grid.
+fn validate_required(grid: &Grid<bool>, required: Option<&Grid<bool>>) -> bool {
+    required.is_none_or(|required| required.rows() == grid.rows() && required.cols() == grid.cols())
+}
+
+/// Checks if the board is small enough to attempt an exact enumeration before sampling.
+fn prefers_exact(grid: &Grid<bool>, rectangles: &[Rectangle]) -> bool {
+    grid.rows() * grid.cols() <= EXACT_MAX_GRID_AREA && rectangles.len() <= EXACT_MAX_RECTANGLES
+}
+
+/// Validates the request parameters and estimates the probabilities they describe.
+///
+/// Small boards are enumerated exactly, falling back to sampling when the enumeration gives up.
+/// Boards with sunk ships are sampled over the ships still afloat.
+///
+/// # Returns
+///
+/// The estimate, or the error of the first invalid parameter. An infeasible board is an estimate
+/// whose feasibility is 0 rather than an error.
+pub(super) fn run_estimate(
+    param: &RequestParams,
+    limits: &GridLimits,
+) -> Result<Estimate, ApiError> {
+    let (mask, options) = validate_params(param, limits)?;
+    if let Some(sunk) = param.sunk.as_deref().filter(|sunk| !sunk.is_empty()) {
+        return estimate_battleship(&mask, &param.rectangles, sunk, &options)
+            .map_err(|error| crate::error::Error::from(error).into());
+    }
+    Ok(try_exact(&mask, &param.rectangles, &options)
+        .unwrap_or_else(|| estimate_with_options(&mask, &param.rectangles, &options)))
+}
+
+/// Validates the request parameters.
+///
+/// # Returns
+///
+/// The grid mask and the options to sample the estimate with, whose required cells include the
+/// occupied cells of the mask and whose deadline is [`REQUEST_DEADLINE`], or the error of the
+/// first invalid parameter.
+pub(super) fn validate_params(
+    param: &RequestParams,
+    limits: &GridLimits,
+) -> Result<(Grid<bool>, EstimateOptions), ApiError> {
+    let (mask, required) = param.to_masks();
+    if let Some(error) = find_invalid_params(param, &mask, required.as_ref(), limits)
+        .into_iter()
+        .next()
+    {
+        return Err(error);
+    }
+    Ok((mask, to_options(param, required)))
+}
+
+/// Builds the options to sample the estimate of valid request parameters with.
+fn to_options(param: &RequestParams, required: Option<Grid<bool>>) -> EstimateOptions {
+    EstimateOptions {
+        simulations: param.simulations.unwrap_or(DEFAULT_SIMULATIONS),
+        seed: param.seed,
+        tolerance: param.tolerance,
+        deadline: Some(REQUEST_DEADLINE),
+        required,
+        max_candidates: None,
+        counted: param
+            .of_rectangles
+            .as_ref()
+            .filter(|ids| !ids.is_empty())
+            .map(|ids| ids.iter().map(|id| id - 1).collect()),
+        antithetic: param.antithetic.unwrap_or(false),
+    }
+}
+
+/// Checks every request parameter, rather than stopping at the first invalid one.
+///
+/// # Arguments
+///
+/// * `param` - The request parameters.
+/// * `mask` - The grid mask of the request, as returned by [`RequestParams::to_masks`].
+/// * `required` - The required cells of the request, as returned by [`RequestParams::to_masks`].
+/// * `limits` - The grid limits of the service.
+///
+/// # Returns
+///
+/// A `422 Unprocessable Entity` error for each invalid parameter, in the order they are checked:
+/// the limits of the service, then the first problem [`to_checked_shapes`] finds with the board. An empty grid is the only error reported then, as every other check would fail
+/// along with it.
+pub(super) fn find_invalid_params(
+    param: &RequestParams,
+    mask: &Grid<bool>,
+    required: Option<&Grid<bool>>,
+    limits: &GridLimits,
+) -> Vec<ApiError> {
+    let fits = check_rectangles(mask, &param.rectangles);
+    if let Err(error @ crate::error::Error::ZeroDimension) = fits {
+        return vec![error.into()];
+    }
+    let mut errors = vec![];
+    let within_limits =
+        validate_grid_size(mask, limits) && validate_rectangle_count(mask, &param.rectangles);
+    if !validate_grid_size(mask, limits) {
+        errors.push(ApiError::unprocessable(
+            "grid_too_large",
+            format!(
+                "grid must be at most {}x{} with at most {} cells",
+                limits.rows(),
+                limits.cols(),
+                limits.cells()
+            ),
+        ));
+    }
+    if !validate_simulations(param.simulations.unwrap_or(DEFAULT_SIMULATIONS)) {
+        errors.push(ApiError::unprocessable(
+            "invalid_simulations",
+            format!("simulations must be between {MIN_SIMULATIONS} and {MAX_SIMULATIONS}"),
+        ));
+    }
+    if !validate_tolerance(param.tolerance) {
+        errors.push(ApiError::unprocessable(
+            "invalid_tolerance",
+            "tolerance must be greater than 0 and at most 1",
+        ));
+    }
+    if !validate_legend_stops(param.legend_stops) {
+        errors.push(ApiError::unprocessable(
+            "invalid_legend_stops",
+            format!("legend_stops must be between {MIN_LEGEND_STOPS} and {MAX_LEGEND_STOPS}"),
+        ));
+    }
+    if !validate_probability_threshold(param.probability_threshold) {
+        errors.push(ApiError::unprocessable(
+            "invalid_probability_threshold",
+            "probability_threshold must be at least 0 and less than 1",
+        ));
+    }
+    if !validate_delta_threshold(param.delta_threshold) {
+        errors.push(ApiError::unprocessable(
+            "invalid_delta_threshold",
+            "delta_threshold must be between 0 and 1",
+        ));
+    }
+    if !validate_rectangle_count(mask, &param.rectangles) {
+        errors.push(ApiError::unprocessable(
+            "too_many_rectangles",
+            "there must be at most as many rectangles as cells",
+        ));
+    }
+    if !validate_of_rectangles(&param.rectangles, param.of_rectangles.as_deref()) {
+        errors.push(ApiError::unprocessable(
+            "invalid_of_rectangles",
+            "of_rectangles must be 1-based indices of the rectangles",
+        ));
+    }
+    // The cells of the shapes are only listed once the rectangles and the grid are known to be
+    // small enough.
+    let board = fits.and_then(|()| {
+        if !within_limits {
+            return Ok(());
+        }
+        // The rectangles to count are 1-based in a request, so they are checked above instead.
+        let options = EstimateOptions {
+            required: required.cloned(),
+            ..Default::default()
+        };
+        to_checked_shapes(mask, &param.rectangles, &options).map(drop)
+    });
+    errors.extend(board.err().map(ApiError::from));
+    errors
+}
+
+/// Enumerates the layouts of a small board exactly.
+///
+/// # Returns
+///
+/// The exact estimate, or `None` if the board is too large, only some rectangles are counted, or
+/// the enumeration gave up.
+pub(super) fn try_exact(
+    mask: &Grid<bool>,
+    rectangles: &[Rectangle],
+    options: &EstimateOptions,
+) -> Option<Estimate> {
+    if !prefers_exact(mask, rectangles) || options.counted.is_some() {
+        return None;
+    }
+    estimate_exact_with(
+        mask,
+        rectangles,
+        options.required.as_ref(),
+        DEFAULT_NODE_BUDGET,
+    )
+}
+
+/// The query parameters of the compact `GET /estimate` variant.
 #[derive(Serialize, Deserialize)]
-struct ResponseMessage {
-    probabilities: Grid<(f64, Color)>,
-    entropy: Grid<(f64, Color)>,
+pub(super) struct QueryParams {
+    /// The rows of the mask separated by commas, with `.` for a free cell and `#` for a masked cell.
+    mask: String,
+    /// The rectangles separated by commas, each written as `<width>x<height>`.
+    rectangles: String,
+    /// The number of simulations to run. Defaults to [`DEFAULT_SIMULATIONS`].
+    simulations: Option<usize>,
+    /// The seed of the random number generator, for reproducible estimates.
+    seed: Option<u64>,
+    /// Stops once no probability changes by more than this between two checkpoints, if given.
+    tolerance: Option<f64>,
+    /// The cells known to be covered by some rectangle, encoded like `mask` with `#` for a required cell.
+    required: Option<String>,
+    /// The color map of the probabilities. Defaults to [`ColorMap::Viridis`].
+    probability_cmap: Option<ColorMap>,
+    /// The color map of the entropies. Defaults to [`ColorMap::Magma`].
+    entropy_cmap: Option<ColorMap>,
+    /// Whether to pair each value with a color. Defaults to `true`.
+    colorize: Option<bool>,
+    /// Whether to color the entropies against their own range. Defaults to `false`.
+    normalize_entropy: Option<bool>,
+    /// Whether to color the probabilities against the largest one. Defaults to `false`.
+    rescale_probabilities: Option<bool>,
+    /// Colors every probability below this like 0, if given.
+    probability_threshold: Option<f64>,
+    /// The number of stops of each color map in the legend. Defaults to [`DEFAULT_LEGEND_STOPS`].
+    legend_stops: Option<usize>,
+    /// Whether to pair each simulation with its antithetic one. Defaults to `false`.
+    antithetic: Option<bool>,
 }
 
-/// Checks if the grid size is valid.
-fn validate_grid_size(grid: &Grid<bool>) -> bool {
-    grid.rows() <= MAX_GRID_ROWS && grid.cols() <= MAX_GRID_COLS
+impl TryFrom<&QueryParams> for RequestParams {
+    type Error = String;
+
+    fn try_from(query: &QueryParams) -> Result<Self, Self::Error> {
+        Ok(RequestParams {
+            mask: Grid::from(&parse_mask(&query.mask)?),
+            rectangles: parse_rectangles(&query.rectangles)?,
+            simulations: query.simulations,
+            seed: query.seed,
+            tolerance: query.tolerance,
+            required: query.required.as_deref().map(parse_mask).transpose()?,
+            probability_cmap: query.probability_cmap.clone(),
+            entropy_cmap: query.entropy_cmap.clone(),
+            colorize: query.colorize,
+            normalize_entropy: query.normalize_entropy,
+            rescale_probabilities: query.rescale_probabilities,
+            probability_threshold: query.probability_threshold,
+            legend_stops: query.legend_stops,
+            of_rectangles: None,
+            sunk: None,
+            baseline: None,
+            delta_threshold: None,
+            antithetic: query.antithetic,
+        })
+    }
 }
 
-#[post("/estimate")]
-pub async fn estimate(param: web::Json<RequestParams>) -> impl Responder {
-    if !validate_grid_size(&param.mask) {
-        return HttpResponse::BadRequest().finish();
+/// Builds the response message of an estimate, colored with the color maps of the request
+/// parameters unless they opt out of coloring.
+pub(super) fn to_response_message(result: Estimate, param: &RequestParams) -> ResponseMessage {
+    let entropy = to_entropy(&result.probabilities);
+    let (heatmaps, legend) = if param.colorize.unwrap_or(true) {
+        let entropy_cmap = param.entropy_cmap.as_ref().unwrap_or(&ColorMap::Magma);
+        let probability_cmap = param
+            .probability_cmap
+            .as_ref()
+            .unwrap_or(&ColorMap::Viridis);
+        let legend = param.legend(
+            &result.probabilities,
+            &entropy,
+            probability_cmap,
+            entropy_cmap,
+        );
+        let heatmaps = Heatmaps::Colorized {
+            probabilities: param.color_probabilities(&result.probabilities, probability_cmap),
+            entropy: if param.normalize_entropy.unwrap_or(false) {
+                entropy.to_normalized_color_pairs(entropy_cmap)
+            } else {
+                entropy.to_value_color_pairs(entropy_cmap)
+            },
+        };
+        (heatmaps, Some(legend))
+    } else {
+        let heatmaps = Heatmaps::Raw {
+            probabilities: result.probabilities,
+            entropy,
+        };
+        (heatmaps, None)
+    };
+
+    ResponseMessage {
+        version: API_VERSION,
+        heatmaps,
+        std_error: result.std_error,
+        occupants: result.occupants,
+        expected_coverage: result.expected_coverage,
+        feasibility: result.feasibility,
+        feasible: result.stats.feasible_samples > 0,
+        legend,
+        meta: ResponseMeta {
+            simulations_run: result.stats.simulations_run,
+            elapsed_ms: result.stats.elapsed.as_millis() as u64,
+            feasible_samples: result.stats.feasible_samples,
+            cached: false,
+        },
+    }
+}
+
+/// Builds the response message of an estimate against the probabilities of an earlier one.
+///
+/// # Arguments
+///
+/// * `result` - The estimate.
+/// * `baseline` - The probabilities of the earlier estimate. If its size differs from the
+///   estimate, every cell is returned.
+/// * `threshold` - The most a probability may change and not be returned.
+fn to_delta_message(result: Estimate, baseline: &Grid<f64>, threshold: f64) -> DeltaMessage {
+    let probabilities = &result.probabilities;
+    let same_size =
+        baseline.rows() == probabilities.rows() && baseline.cols() == probabilities.cols();
+    let changes = probabilities
+        .iter_positions()
+        .filter(|(pos, p)| !same_size || (*p - baseline[pos]).abs() > threshold)
+        .map(|(pos, &p)| (pos, p))
+        .collect();
+    DeltaMessage {
+        version: API_VERSION,
+        rows: probabilities.rows(),
+        cols: probabilities.cols(),
+        changes,
+        feasibility: result.feasibility,
+        feasible: result.stats.feasible_samples > 0,
+        meta: ResponseMeta {
+            simulations_run: result.stats.simulations_run,
+            elapsed_ms: result.stats.elapsed.as_millis() as u64,
+            feasible_samples: result.stats.feasible_samples,
+            cached: false,
+        },
     }
+}
 
-    let probabilities = estimate_probabilities(&param.mask, &param.rectangles);
-    let entropy = to_entropy(&probabilities).to_value_color_pairs(&ColorMap::Magma);
-    let probabilities = probabilities.to_value_color_pairs(&ColorMap::Viridis);
+/// Returns the smallest and the largest element of a grid.
+fn value_range(grid: &Grid<f64>) -> (f64, f64) {
+    grid.iter_positions().fold(
+        (f64::INFINITY, f64::NEG_INFINITY),
+        |(min, max), (_, &value)| (min.min(value), max.max(value)),
+    )
+}
 
-    HttpResponse::Ok().json(ResponseMessage {
-        probabilities,
-        entropy,
-    })
+/// What identifies an estimate to the cache, every request parameter the estimate depends on.
+///
+/// The rectangles are kept in the order of the request rather than sorted, as the occupants
+/// refer to them by their index.
+#[derive(PartialEq)]
+struct EstimateKey {
+    mask: Grid<CellState>,
+    rectangles: Vec<Rectangle>,
+    simulations: Option<usize>,
+    seed: Option<u64>,
+    /// The bits of the tolerance, so a key compares equal to itself even for a NaN tolerance.
+    tolerance: Option<u64>,
+    required: Option<Grid<bool>>,
+    of_rectangles: Option<Vec<usize>>,
+    sunk: Option<Vec<SunkShip>>,
+    antithetic: Option<bool>,
+}
+
+impl EstimateKey {
+    /// Creates the key of the estimate of a request, ignoring the parameters that only affect how
+    /// it is presented.
+    fn new(param: &RequestParams) -> EstimateKey {
+        EstimateKey {
+            mask: param.mask.clone(),
+            rectangles: param.rectangles.clone(),
+            simulations: param.simulations,
+            seed: param.seed,
+            tolerance: param.tolerance.map(f64::to_bits),
+            required: param.required.clone(),
+            of_rectangles: param.of_rectangles.clone(),
+            sunk: param.sunk.clone(),
+            antithetic: param.antithetic,
+        }
+    }
+}
+
+/// The estimates of the most recent requests, shared by every worker.
+///
+/// Holds up to [`ESTIMATE_CACHE_CAPACITY`] estimates, evicting the least recently used one.
+#[derive(Clone)]
+pub struct EstimateCache(LruCache<EstimateKey, Estimate>);
+
+impl Default for EstimateCache {
+    fn default() -> Self {
+        EstimateCache(LruCache::new(ESTIMATE_CACHE_CAPACITY))
+    }
+}
+
+/// Estimates a request, or returns the estimate of an identical earlier request if one is cached.
+///
+/// # Returns
+///
+/// The estimate and whether it was cached, or a `422 Unprocessable Entity` error describing the
+/// first invalid parameter. Errors are not cached.
+async fn run_cached_estimate(
+    param: &Arc<RequestParams>,
+    pool: &EstimatorPool,
+    limits: &web::Data<GridLimits>,
+    cache: Option<&EstimateCache>,
+) -> Result<(Estimate, bool), ApiError> {
+    let span = tracing::info_span!(
+        "estimate",
+        rows = param.mask.rows(),
+        cols = param.mask.cols(),
+        rectangles = param.rectangles.len(),
+    );
+    let run = || {
+        let (param, limits) = (Arc::clone(param), limits.clone());
+        pool.run(move || run_estimate(&param, &limits))
+    };
+    async {
+        let (result, cached) = match cache {
+            Some(EstimateCache(cache)) => {
+                let key = EstimateKey::new(param);
+                match cache.get(&key) {
+                    Some(result) => (result, true),
+                    None => {
+                        let result = run().await??;
+                        cache.insert(key, result.clone());
+                        (result, false)
+                    }
+                }
+            }
+            None => (run().await??, false),
+        };
+        tracing::info!(
+            simulations_run = result.stats.simulations_run,
+            feasible_samples = result.stats.feasible_samples,
+            feasibility = result.feasibility,
+            elapsed_ms = result.stats.elapsed.as_millis() as u64,
+            cached,
+            "estimated",
+        );
+        Ok((result, cached))
+    }
+    .instrument(span)
+    .await
+}
+
+/// Builds the response to an estimate, the compact binary probabilities of [`to_binary`] if the
+/// client prefers `application/octet-stream`, the JSON [`DeltaMessage`] if the request has a
+/// baseline, or the JSON [`ResponseMessage`] otherwise.
+fn respond(
+    result: Estimate,
+    cached: bool,
+    param: &RequestParams,
+    accept: Option<&Accept>,
+) -> HttpResponse {
+    if accept.is_some_and(|accept| accept.preference() == mime::APPLICATION_OCTET_STREAM) {
+        return HttpResponse::Ok()
+            .content_type(mime::APPLICATION_OCTET_STREAM)
+            .body(to_binary(&result.probabilities));
+    }
+    if let Some(baseline) = &param.baseline {
+        let threshold = param.delta_threshold.unwrap_or(DEFAULT_DELTA_THRESHOLD);
+        let mut message = to_delta_message(result, baseline, threshold);
+        message.meta.cached = cached;
+        return HttpResponse::Ok().json(message);
+    }
+    let mut message = to_response_message(result, param);
+    message.meta.cached = cached;
+    HttpResponse::Ok().json(message)
+}
+
+/// Encodes the probabilities of an estimate compactly, for clients that need no colors or
+/// statistics.
+///
+/// The layout, with every number little-endian:
+///
+/// | Bytes             | Type  | Content                                  |
+/// | ----------------- | ----- | ---------------------------------------- |
+/// | `0..4`            | `u32` | The number of rows.                      |
+/// | `4..8`            | `u32` | The number of columns.                   |
+/// | `8..8 + 4 * rows * cols` | `f32` | The probabilities, row by row from the top-left cell. |
+///
+/// The grid limits keep both dimensions far below `u32::MAX`.
+fn to_binary(probabilities: &Grid<f64>) -> Vec<u8> {
+    let mut body = Vec::with_capacity(8 + 4 * probabilities.rows() * probabilities.cols());
+    body.extend_from_slice(&(probabilities.rows() as u32).to_le_bytes());
+    body.extend_from_slice(&(probabilities.cols() as u32).to_le_bytes());
+    for y in 0..probabilities.rows() {
+        for &p in probabilities.row(y).unwrap_or_default() {
+            body.extend_from_slice(&(p as f32).to_le_bytes());
+        }
+    }
+    body
+}
+
+/// Estimates the probabilities of a board.
+///
+/// Identical requests are served from the [`EstimateCache`], if one is registered, whatever their
+/// baseline. Responds with the bare probabilities in the binary layout of [`to_binary`] if the
+/// client prefers `Accept: application/octet-stream`, with only the cells that changed from the
+/// baseline if the request has one, and with the full JSON response otherwise.
+#[post("/estimate")]
+pub async fn estimate(
+    param: web::Json<RequestParams>,
+    pool: web::Data<EstimatorPool>,
+    limits: web::Data<GridLimits>,
+    cache: Option<web::Data<EstimateCache>>,
+    accept: Option<web::Header<Accept>>,
+) -> Result<HttpResponse, ApiError> {
+    let param = Arc::new(param.into_inner());
+    let (result, cached) = run_cached_estimate(
+        &param,
+        &pool,
+        &limits,
+        cache.as_ref().map(web::Data::get_ref),
+    )
+    .await?;
+    Ok(respond(
+        result,
+        cached,
+        &param,
+        accept.as_ref().map(|accept| &accept.0),
+    ))
+}
+
+/// Estimates the probabilities of a board encoded compactly in the query string.
+///
+/// Responds with `400 Bad Request` and the parse error if the mask or the rectangles are malformed.
+/// Identical requests are served from the [`EstimateCache`], if one is registered, and the
+/// response format is negotiated like for `POST /estimate`.
+#[get("/estimate")]
+pub async fn estimate_query(
+    query: web::Query<QueryParams>,
+    pool: web::Data<EstimatorPool>,
+    limits: web::Data<GridLimits>,
+    cache: Option<web::Data<EstimateCache>>,
+    accept: Option<web::Header<Accept>>,
+) -> Result<HttpResponse, ApiError> {
+    let param = RequestParams::try_from(&*query)
+        .map_err(|error| ApiError::bad_request("malformed_query", error))?;
+    let param = Arc::new(param);
+    let (result, cached) = run_cached_estimate(
+        &param,
+        &pool,
+        &limits,
+        cache.as_ref().map(web::Data::get_ref),
+    )
+    .await?;
+    Ok(respond(
+        result,
+        cached,
+        &param,
+        accept.as_ref().map(|accept| &accept.0),
+    ))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::Position;
-    use actix_web::{App, http::StatusCode, test};
+    use crate::api::error::{ErrorResponse, json_config, query_config};
+    use crate::api::limits::{DEFAULT_MAX_GRID_COLS, DEFAULT_MAX_GRID_ROWS};
+    use crate::api::test_app;
+    use crate::colors::to_rgb;
+    use actix_web::{dev::ServiceResponse, http::StatusCode, test};
     use serde_json;
 
     #[actix_web::test]
     async fn test_validate_grid_size() {
-        let valid_grid = Grid::new(MAX_GRID_ROWS, MAX_GRID_COLS, false);
-        let invalid_grid = Grid::new(MAX_GRID_ROWS + 1, MAX_GRID_COLS + 1, false);
+        let limits = GridLimits::new(12, 10).unwrap();
+        assert!(validate_grid_size(&Grid::new(12, 10, false), &limits));
+        assert!(!validate_grid_size(&Grid::new(13, 10, false), &limits));
+        assert!(!validate_grid_size(&Grid::new(12, 11, false), &limits));
+
+        // A long narrow grid passes the number of cells even past the sides of a square limit of
+        // that area, while a grid within the sides may still have too many cells.
+        let limits = GridLimits::new(24, 24)
+            .unwrap()
+            .with_max_cells(100)
+            .unwrap();
+        assert!(validate_grid_size(&Grid::new(6, 12, false), &limits));
+        assert!(validate_grid_size(&Grid::new(4, 24, false), &limits));
+        assert!(!validate_grid_size(&Grid::new(12, 12, false), &limits));
+        assert!(!validate_grid_size(&Grid::new(5, 25, false), &limits));
+    }
+
+    #[actix_web::test]
+    async fn test_validate_simulations() {
+        assert!(validate_simulations(MIN_SIMULATIONS));
+        assert!(validate_simulations(MAX_SIMULATIONS));
+        assert!(!validate_simulations(0));
+        assert!(!validate_simulations(MAX_SIMULATIONS + 1));
+    }
+
+    #[actix_web::test]
+    async fn test_prefers_exact() {
+        let rectangles = vec![Rectangle::new(2, 1).unwrap(); EXACT_MAX_RECTANGLES];
+        assert!(prefers_exact(&Grid::new(4, 4, false), &rectangles));
+        assert!(!prefers_exact(&Grid::new(5, 9, false), &rectangles));
 
-        assert!(validate_grid_size(&valid_grid));
-        assert!(!validate_grid_size(&invalid_grid));
+        let rectangles = vec![Rectangle::new(1, 1).unwrap(); EXACT_MAX_RECTANGLES + 1];
+        assert!(!prefers_exact(&Grid::new(4, 4, false), &rectangles));
+    }
+
+    #[actix_web::test]
+    async fn test_validate_tolerance() {
+        assert!(validate_tolerance(None));
+        assert!(validate_tolerance(Some(0.01)));
+        assert!(validate_tolerance(Some(1.0)));
+        assert!(!validate_tolerance(Some(0.0)));
+        assert!(!validate_tolerance(Some(-0.1)));
+        assert!(!validate_tolerance(Some(f64::NAN)));
+    }
+
+    #[actix_web::test]
+    async fn test_validate_rectangle_count() {
+        let grid = Grid::new(2, 2, false);
+        let rectangles = vec![Rectangle::new(1, 1).unwrap(); 4];
+        assert!(validate_rectangle_count(&grid, &rectangles));
+        assert!(!validate_rectangle_count(
+            &grid,
+            &vec![rectangles[0].clone(); 5]
+        ));
+    }
+
+    /// A grid of values paired with their colors.
+    type ColorGrid = Grid<(f64, Color)>;
+
+    /// Returns the colorized probabilities and entropies of a response, failing if they are raw.
+    fn colorized(message: &ResponseMessage) -> (&ColorGrid, &ColorGrid) {
+        match &message.heatmaps {
+            Heatmaps::Colorized {
+                probabilities,
+                entropy,
+            } => (probabilities, entropy),
+            Heatmaps::Raw { .. } => panic!("expected a colorized response"),
+        }
+    }
+
+    /// Checks that a response is an error with the given status and code.
+    async fn assert_error(resp: ServiceResponse, status: StatusCode, code: &str) {
+        assert_eq!(resp.status(), status);
+        let body: ErrorResponse = test::read_body_json(resp).await;
+        assert_eq!(body.code, code);
+        assert!(!body.message.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_grid_limits() {
+        let request = || {
+            test::TestRequest::post()
+                .uri("/estimate")
+                .set_json(serde_json::json!({
+                    "mask": Grid::new(12, 12, false),
+                    "rectangles": [{"width": 4, "height": 3, "count": 6}],
+                    "simulations": 200,
+                    "seed": 1,
+                    "colorize": false,
+                }))
+                .to_request()
+        };
+
+        let app = test::init_service(test_app().service(estimate)).await;
+        let resp = test::call_service(&app, request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert!(body["feasibility"].as_f64().unwrap() > 0.0);
+        let probabilities: Grid<f64> =
+            serde_json::from_value(body["probabilities"].clone()).unwrap();
+        assert!(probabilities.iter_positions().any(|(_, &p)| p > 0.0));
+
+        let app = test::init_service(
+            test_app()
+                .app_data(web::Data::new(GridLimits::new(9, 9).unwrap()))
+                .service(estimate),
+        )
+        .await;
+        let resp = test::call_service(&app, request()).await;
+        assert_error(resp, StatusCode::UNPROCESSABLE_ENTITY, "grid_too_large").await;
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_grid_cells_limit() {
+        let app = test::init_service(test_app().service(estimate)).await;
+        let request = |rows, cols| {
+            test::TestRequest::post()
+                .uri("/estimate")
+                .set_json(serde_json::json!({
+                    "mask": Grid::new(rows, cols, false),
+                    "rectangles": [{"width": 2, "height": 1}],
+                    "simulations": 100,
+                    "colorize": false,
+                }))
+                .to_request()
+        };
+
+        // Longer than the former default of 16x16, but with as many cells.
+        let resp = test::call_service(&app, request(8, 32)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        // Within both sides, but with more cells.
+        let resp = test::call_service(&app, request(17, 17)).await;
+        assert_error(resp, StatusCode::UNPROCESSABLE_ENTITY, "grid_too_large").await;
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_oversized_rectangle() {
+        let app = test::init_service(test_app().service(estimate)).await;
+        // A rectangle far too large to list the cells of is rejected by its dimensions alone.
+        for width in [3, 1usize << 40] {
+            let req = test::TestRequest::post()
+                .uri("/estimate")
+                .set_json(serde_json::json!({
+                    "mask": Grid::new(2, 2, false),
+                    "rectangles": [{"width": width, "height": 1}],
+                }))
+                .to_request();
+
+            let resp = test::call_service(&app, req).await;
+            assert_error(
+                resp,
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "rectangle_too_large",
+            )
+            .await;
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_validate_required() {
+        let grid = Grid::new(3, 4, false);
+        assert!(validate_required(&grid, None));
+        assert!(validate_required(&grid, Some(&Grid::new(3, 4, false))));
+        assert!(!validate_required(&grid, Some(&Grid::new(4, 3, false))));
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_weights_mismatch() {
+        let app = test::init_service(test_app().service(estimate)).await;
+        let req = test::TestRequest::post()
+            .uri("/estimate")
+            .set_json(serde_json::json!({
+                "mask": {"rows": 1, "cols": 3, "data": [[false, false, false]]},
+                "rectangles": [{
+                    "width": 1,
+                    "height": 1,
+                    "weights": {"rows": 1, "cols": 2, "data": [[1.0, 2.0]]},
+                }],
+            }))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body: ErrorResponse = test::read_body_json(resp).await;
+        assert_eq!(body.code, "invalid_weights");
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_sunk() {
+        let app = test::init_service(test_app().service(estimate)).await;
+        let request = |position: serde_json::Value| {
+            test::TestRequest::post()
+                .uri("/estimate")
+                .set_json(serde_json::json!({
+                    "mask": {"rows": 3, "cols": 3, "data": [
+                        ["occupied", "occupied", "unknown"],
+                        ["unknown", "unknown", "unknown"],
+                        ["unknown", "unknown", "unknown"],
+                    ]},
+                    "rectangles": [{"width": 2, "height": 1}, {"width": 3, "height": 1}],
+                    "sunk": [{"position": position, "rectangle": {"width": 2, "height": 1}}],
+                    "seed": 1,
+                    "colorize": false,
+                }))
+                .to_request()
+        };
+
+        let resp = test::call_service(&app, request(serde_json::json!({"x": 0, "y": 0}))).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["feasibility"], 1.0);
+        let probabilities: Grid<f64> =
+            serde_json::from_value(body["probabilities"].clone()).unwrap();
+        assert_eq!(probabilities[&Position::new(0, 0)], 0.0);
+        assert!(probabilities[&Position::new(2, 1)] > 0.5);
+
+        let resp = test::call_service(&app, request(serde_json::json!({"x": 2, "y": 0}))).await;
+        assert_error(resp, StatusCode::UNPROCESSABLE_ENTITY, "invalid_sunk").await;
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_required() {
+        let app = test::init_service(test_app().service(estimate)).await;
+        let mut required = Grid::new(1, 3, false);
+        required[&Position::new(0, 0)] = true;
+        let req = test::TestRequest::post()
+            .uri("/estimate")
+            .set_json(&RequestParams {
+                mask: Grid::new(1, 3, CellState::Unknown),
+                rectangles: vec![Rectangle::new(1, 1).unwrap()],
+                simulations: None,
+                seed: None,
+                tolerance: None,
+                required: Some(required),
+                probability_cmap: None,
+                entropy_cmap: None,
+                colorize: None,
+                normalize_entropy: None,
+                rescale_probabilities: None,
+                probability_threshold: None,
+                legend_stops: None,
+                of_rectangles: None,
+                sunk: None,
+                baseline: None,
+                delta_threshold: None,
+                antithetic: None,
+            })
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let message: ResponseMessage = test::read_body_json(resp).await;
+        let (probabilities, _) = colorized(&message);
+        assert_eq!(probabilities[&Position::new(0, 0)].0, 1.0);
+        assert_eq!(probabilities[&Position::new(1, 0)].0, 0.0);
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_mask_polarity() {
+        let app = test::init_service(test_app().service(estimate)).await;
+        let blocked = Grid::from(&["#...", "..#.", "...."][..]);
+        let free = blocked.map(|&blocked| !blocked);
+        let mut bodies = vec![];
+        for mask in [
+            serde_json::to_value(&blocked).unwrap(),
+            serde_json::json!({"blocked": blocked}),
+            serde_json::json!({"free": free}),
+        ] {
+            let req = test::TestRequest::post()
+                .uri("/estimate")
+                .set_json(serde_json::json!({
+                    "mask": mask,
+                    "rectangles": [{"width": 2, "height": 1}, {"width": 3, "height": 1}],
+                    "simulations": 1000,
+                    "seed": 1,
+                    "colorize": false,
+                }))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            bodies.push(body["probabilities"].clone());
+        }
+        // Either polarity describes the same board, whose blocked cells are never covered.
+        assert_eq!(bodies[1], bodies[0]);
+        assert_eq!(bodies[2], bodies[0]);
+        assert_eq!(bodies[0]["data"][0][0], 0.0);
+        assert_eq!(bodies[0]["data"][1][2], 0.0);
+        assert_ne!(bodies[0]["data"][2][0], 0.0);
+
+        // The inverted mask under the other polarity describes the inverted board.
+        let req = test::TestRequest::post()
+            .uri("/estimate")
+            .set_json(serde_json::json!({
+                "mask": {"free": blocked},
+                "rectangles": [{"width": 1, "height": 1}],
+            }))
+            .to_request();
+        let message: ResponseMessage = test::call_and_read_body_json(&app, req).await;
+        let probabilities = match message.heatmaps {
+            Heatmaps::Colorized { probabilities, .. } => probabilities.map(|&(p, _)| p),
+            Heatmaps::Raw { probabilities, .. } => probabilities,
+        };
+        assert_eq!(probabilities[&Position::new(0, 0)], 0.5);
+        assert_eq!(probabilities[&Position::new(1, 0)], 0.0);
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_revealed_states() {
+        let app = test::init_service(test_app().service(estimate)).await;
+        let mut mask = Grid::new(1, 5, CellState::Unknown);
+        mask[&Position::new(0, 0)] = CellState::Occupied;
+        mask[&Position::new(3, 0)] = CellState::Empty;
+        let req = test::TestRequest::post()
+            .uri("/estimate")
+            .set_json(serde_json::json!({
+                "mask": mask,
+                "rectangles": [{"width": 2, "height": 1}],
+                "colorize": false,
+            }))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // Only the layout covering the occupied corner is consistent with the revealed cells.
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(
+            body["probabilities"]["data"],
+            serde_json::json!([[1.0, 1.0, 0.0, 0.0, 0.0]])
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_validate_revealed_states() {
+        let mut mask = Grid::new(2, 3, CellState::Unknown);
+        mask[&Position::new(0, 0)] = CellState::Occupied;
+        let mut required = Grid::new(2, 3, false);
+        required[&Position::new(2, 1)] = true;
+        let param = RequestParams {
+            mask,
+            rectangles: vec![Rectangle::new(1, 1).unwrap(); 2],
+            simulations: None,
+            seed: None,
+            tolerance: None,
+            required: Some(required),
+            probability_cmap: None,
+            entropy_cmap: None,
+            colorize: None,
+            normalize_entropy: None,
+            rescale_probabilities: None,
+            probability_threshold: None,
+            legend_stops: None,
+            of_rectangles: None,
+            sunk: None,
+            baseline: None,
+            delta_threshold: None,
+            antithetic: None,
+        };
+
+        let (mask, options) = validate_params(&param, &GridLimits::default()).unwrap();
+        assert_eq!(mask.to_string(), "...\n...");
+        assert_eq!(options.required.unwrap().to_string(), "#..\n..#");
+        assert_eq!(options.deadline, Some(REQUEST_DEADLINE));
     }
 
     #[actix_web::test]
     async fn test_estimate_invalid_grid_size() {
-        let app = test::init_service(App::new().service(estimate)).await;
+        let app = test::init_service(test_app().service(estimate)).await;
         let req = test::TestRequest::post()
             .uri("/estimate")
             .set_json(&RequestParams {
-                mask: Grid::new(MAX_GRID_ROWS + 1, MAX_GRID_COLS + 1, false),
-                rectangles: vec![Rectangle::new(1, 1)],
+                mask: Grid::new(
+                    DEFAULT_MAX_GRID_ROWS + 1,
+                    DEFAULT_MAX_GRID_COLS + 1,
+                    CellState::Unknown,
+                ),
+                rectangles: vec![Rectangle::new(1, 1).unwrap()],
+                simulations: None,
+                seed: None,
+                tolerance: None,
+                required: None,
+                probability_cmap: None,
+                entropy_cmap: None,
+                colorize: None,
+                normalize_entropy: None,
+                rescale_probabilities: None,
+                probability_threshold: None,
+                legend_stops: None,
+                of_rectangles: None,
+                sunk: None,
+                baseline: None,
+                delta_threshold: None,
+                antithetic: None,
             })
             .to_request();
 
         let resp = test::call_service(&app, req).await;
-        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_error(resp, StatusCode::UNPROCESSABLE_ENTITY, "grid_too_large").await;
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_empty_grid() {
+        let app = test::init_service(test_app().service(estimate)).await;
+        for (rows, cols) in [(0, 3), (3, 0), (0, 0)] {
+            let req = test::TestRequest::post()
+                .uri("/estimate")
+                .set_json(serde_json::json!({
+                    "mask": Grid::new(rows, cols, false),
+                    "rectangles": [],
+                }))
+                .to_request();
+
+            let resp = test::call_service(&app, req).await;
+            assert_error(resp, StatusCode::UNPROCESSABLE_ENTITY, "grid_empty").await;
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_invalid_simulations() {
+        let app = test::init_service(test_app().service(estimate)).await;
+        for simulations in [0, MAX_SIMULATIONS + 1] {
+            let req = test::TestRequest::post()
+                .uri("/estimate")
+                .set_json(&RequestParams {
+                    mask: Grid::new(3, 3, CellState::Unknown),
+                    rectangles: vec![Rectangle::new(1, 1).unwrap()],
+                    simulations: Some(simulations),
+                    seed: None,
+                    tolerance: None,
+                    required: None,
+                    probability_cmap: None,
+                    entropy_cmap: None,
+                    colorize: None,
+                    normalize_entropy: None,
+                    rescale_probabilities: None,
+                    probability_threshold: None,
+                    legend_stops: None,
+                    of_rectangles: None,
+                    sunk: None,
+                    baseline: None,
+                    delta_threshold: None,
+                    antithetic: None,
+                })
+                .to_request();
+
+            let resp = test::call_service(&app, req).await;
+            assert_error(
+                resp,
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "invalid_simulations",
+            )
+            .await;
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_custom_simulations() {
+        let app = test::init_service(test_app().service(estimate)).await;
+        let req = test::TestRequest::post()
+            .uri("/estimate")
+            .set_json(&RequestParams {
+                mask: Grid::new(3, 3, CellState::Unknown),
+                rectangles: vec![Rectangle::new(3, 3).unwrap()],
+                simulations: Some(10),
+                seed: None,
+                tolerance: None,
+                required: None,
+                probability_cmap: None,
+                entropy_cmap: None,
+                colorize: None,
+                normalize_entropy: None,
+                rescale_probabilities: None,
+                probability_threshold: None,
+                legend_stops: None,
+                of_rectangles: None,
+                sunk: None,
+                baseline: None,
+                delta_threshold: None,
+                antithetic: None,
+            })
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let message: ResponseMessage = test::read_body_json(resp).await;
+        let (probabilities, _) = colorized(&message);
+        assert_eq!(message.feasibility, 1.0);
+        for y in 0..3 {
+            for x in 0..3 {
+                assert_eq!(probabilities[&Position::new(x, y)].0, 1.0);
+            }
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_meta() {
+        let app = test::init_service(test_app().service(estimate)).await;
+        let request = |mask, simulations| RequestParams {
+            mask,
+            rectangles: vec![Rectangle::new(2, 1).unwrap()],
+            simulations: Some(simulations),
+            seed: None,
+            tolerance: None,
+            required: None,
+            probability_cmap: None,
+            entropy_cmap: None,
+            colorize: None,
+            normalize_entropy: None,
+            rescale_probabilities: None,
+            probability_threshold: None,
+            legend_stops: None,
+            of_rectangles: None,
+            sunk: None,
+            baseline: None,
+            delta_threshold: None,
+            antithetic: None,
+        };
+
+        let req = test::TestRequest::post()
+            .uri("/estimate")
+            .set_json(request(Grid::new(4, 5, CellState::Unknown), 500))
+            .to_request();
+        let message: ResponseMessage = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(message.meta.simulations_run, 500);
+        assert_eq!(message.meta.feasible_samples, 500);
+        assert!(message.meta.elapsed_ms < 60_000);
+
+        // A small board is enumerated exactly, counting its layouts instead of simulations.
+        let req = test::TestRequest::post()
+            .uri("/estimate")
+            .set_json(request(Grid::new(2, 2, CellState::Unknown), 500))
+            .to_request();
+        let message: ResponseMessage = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(message.meta.simulations_run, 0);
+        assert_eq!(message.meta.feasible_samples, 4);
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_seeded() {
+        let app = test::init_service(test_app().service(estimate)).await;
+        let mut bodies = vec![];
+        for _ in 0..2 {
+            let req = test::TestRequest::post()
+                .uri("/estimate")
+                .set_json(&RequestParams {
+                    mask: Grid::new(3, 3, CellState::Unknown),
+                    rectangles: vec![Rectangle::new(2, 1).unwrap()],
+                    simulations: Some(1000),
+                    seed: Some(3),
+                    tolerance: None,
+                    required: None,
+                    probability_cmap: None,
+                    entropy_cmap: None,
+                    colorize: None,
+                    normalize_entropy: None,
+                    rescale_probabilities: None,
+                    probability_threshold: None,
+                    legend_stops: None,
+                    of_rectangles: None,
+                    sunk: None,
+                    baseline: None,
+                    delta_threshold: None,
+                    antithetic: None,
+                })
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            bodies.push(test::read_body(resp).await);
+        }
+        assert_eq!(bodies[0], bodies[1]);
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_cached() {
+        let app = test::init_service(
+            test_app()
+                .app_data(web::Data::new(EstimateCache::default()))
+                .service(estimate),
+        )
+        .await;
+        let request = |colorize| {
+            test::TestRequest::post()
+                .uri("/estimate")
+                .set_json(serde_json::json!({
+                    "mask": {"rows": 4, "cols": 5, "data": vec![vec![false; 5]; 4]},
+                    "rectangles": [{"width": 2, "height": 1}],
+                    "simulations": 1000,
+                    "colorize": colorize,
+                }))
+                .to_request()
+        };
+
+        let mut bodies = vec![];
+        for _ in 0..2 {
+            let body: serde_json::Value = test::call_and_read_body_json(&app, request(true)).await;
+            bodies.push(body);
+        }
+        assert_eq!(bodies[0]["meta"]["cached"], false);
+        assert_eq!(bodies[1]["meta"]["cached"], true);
+        // Without a seed, only the cache makes the second estimate identical to the first.
+        bodies[1]["meta"]["cached"] = false.into();
+        assert_eq!(
+            serde_json::to_vec(&bodies[0]).unwrap(),
+            serde_json::to_vec(&bodies[1]).unwrap()
+        );
+
+        // How the estimate is presented does not affect it, so it is still served from the cache.
+        let body: serde_json::Value = test::call_and_read_body_json(&app, request(false)).await;
+        assert_eq!(body["meta"]["cached"], true);
+        assert_eq!(body["std_error"], bodies[0]["std_error"]);
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_antithetic() {
+        let app = test::init_service(
+            test_app()
+                .app_data(web::Data::new(EstimateCache::default()))
+                .service(estimate),
+        )
+        .await;
+        // Too large to be enumerated exactly, so it is sampled.
+        let request = |antithetic: Option<bool>| {
+            test::TestRequest::post()
+                .uri("/estimate")
+                .set_json(serde_json::json!({
+                    "mask": Grid::new(1, 20, false),
+                    "rectangles": [{"width": 3, "height": 1}],
+                    "simulations": 1000,
+                    "seed": 1,
+                    "colorize": false,
+                    "antithetic": antithetic,
+                }))
+                .to_request()
+        };
+
+        let plain: serde_json::Value = test::call_and_read_body_json(&app, request(None)).await;
+        let paired: serde_json::Value =
+            test::call_and_read_body_json(&app, request(Some(true))).await;
+        // Pairing the simulations changes the estimate, so it is not served from the cache.
+        assert_eq!(paired["meta"]["cached"], false);
+        assert_eq!(paired["meta"]["simulations_run"], 1000);
+        assert_ne!(paired["probabilities"], plain["probabilities"]);
+
+        let default: serde_json::Value =
+            test::call_and_read_body_json(&app, request(Some(false))).await;
+        assert_eq!(default["probabilities"], plain["probabilities"]);
+    }
+
+    /// A log shared between the test and the subscriber writing to it.
+    #[derive(Clone, Default)]
+    struct CapturedLog(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for CapturedLog {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_traced() {
+        let log = CapturedLog::default();
+        let writer = log.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let app = test::init_service(test_app().service(estimate)).await;
+        let req = test::TestRequest::post()
+            .uri("/estimate")
+            .set_json(serde_json::json!({
+                "mask": Grid::new(4, 5, false),
+                "rectangles": [{"width": 2, "height": 1}],
+                "simulations": 500,
+            }))
+            .to_request();
+        let message: ResponseMessage = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(message.meta.simulations_run, 500);
+        assert_eq!(message.feasibility, 1.0);
+
+        let log = String::from_utf8(log.0.lock().unwrap().clone()).unwrap();
+        let line = log.lines().find(|line| line.contains("estimated")).unwrap();
+        for field in [
+            "rows=4",
+            "cols=5",
+            "rectangles=1",
+            "simulations_run=500",
+            "feasibility=1",
+            "elapsed_ms=",
+            "cached=false",
+        ] {
+            assert!(line.contains(field), "{field} missing from {line}");
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_infeasible() {
+        let app = test::init_service(test_app().service(estimate)).await;
+        let req = test::TestRequest::post()
+            .uri("/estimate")
+            .set_json(&RequestParams {
+                mask: Grid::new(3, 3, CellState::Empty),
+                rectangles: vec![Rectangle::new(1, 1).unwrap()],
+                simulations: Some(100),
+                seed: None,
+                tolerance: None,
+                required: None,
+                probability_cmap: None,
+                entropy_cmap: None,
+                colorize: None,
+                normalize_entropy: None,
+                rescale_probabilities: None,
+                probability_threshold: None,
+                legend_stops: None,
+                of_rectangles: None,
+                sunk: None,
+                baseline: None,
+                delta_threshold: None,
+                antithetic: None,
+            })
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let message: ResponseMessage = test::read_body_json(resp).await;
+        assert_eq!(message.feasibility, 0.0);
+        assert!(!message.feasible);
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_unsatisfiable() {
+        let app = test::init_service(test_app().service(estimate)).await;
+        // The domino fits the free cells, but not the three cells that must all be covered.
+        let req = test::TestRequest::post()
+            .uri("/estimate")
+            .set_json(serde_json::json!({
+                "mask": {"rows": 1, "cols": 4, "data": [["occupied", "unknown", "occupied", "unknown"]]},
+                "rectangles": [{"width": 2, "height": 1}],
+                "simulations": 100,
+                "colorize": false,
+            }))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["feasible"], false);
+        assert_eq!(body["meta"]["feasible_samples"], 0);
+        assert_eq!(body["probabilities"]["data"][0][1], 0.0);
     }
 
     #[actix_web::test]
     async fn test_estimate_no_rectangles() {
-        let app = test::init_service(App::new().service(estimate)).await;
+        let app = test::init_service(test_app().service(estimate)).await;
         let req = test::TestRequest::post()
             .uri("/estimate")
             .set_json(&RequestParams {
-                mask: Grid::new(3, 3, false),
+                mask: Grid::new(3, 3, CellState::Unknown),
                 rectangles: vec![],
+                simulations: None,
+                seed: None,
+                tolerance: None,
+                required: None,
+                probability_cmap: None,
+                entropy_cmap: None,
+                colorize: None,
+                normalize_entropy: None,
+                rescale_probabilities: None,
+                probability_threshold: None,
+                legend_stops: None,
+                of_rectangles: None,
+                sunk: None,
+                baseline: None,
+                delta_threshold: None,
+                antithetic: None,
             })
             .to_request();
 
@@ -92,23 +1765,41 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::OK);
 
         let response_body: ResponseMessage = test::read_body_json(resp).await;
+        assert_eq!(response_body.feasibility, 1.0);
+        let (probabilities, entropy) = colorized(&response_body);
         for y in 0..3 {
             for x in 0..3 {
                 let pos = Position::new(x, y);
-                assert_eq!(response_body.probabilities[&pos].0, 0.0);
-                assert_eq!(response_body.entropy[&pos].0, 0.0);
+                assert_eq!(probabilities[&pos].0, 0.0);
+                assert_eq!(entropy[&pos].0, 0.0);
             }
         }
     }
 
     #[actix_web::test]
     async fn test_estimate() {
-        let app = test::init_service(App::new().service(estimate)).await;
+        let app = test::init_service(test_app().service(estimate)).await;
         let req = test::TestRequest::post()
             .uri("/estimate")
             .set_json(&RequestParams {
-                mask: Grid::new(3, 3, false),
-                rectangles: vec![Rectangle::new(1, 1), Rectangle::new(2, 1)],
+                mask: Grid::new(3, 3, CellState::Unknown),
+                rectangles: vec![Rectangle::new(1, 1).unwrap(), Rectangle::new(2, 1).unwrap()],
+                simulations: None,
+                seed: None,
+                tolerance: None,
+                required: None,
+                probability_cmap: None,
+                entropy_cmap: None,
+                colorize: None,
+                normalize_entropy: None,
+                rescale_probabilities: None,
+                probability_threshold: None,
+                legend_stops: None,
+                of_rectangles: None,
+                sunk: None,
+                baseline: None,
+                delta_threshold: None,
+                antithetic: None,
             })
             .to_request();
         let resp = test::call_service(&app, req).await;
@@ -116,19 +1807,690 @@ mod tests {
 
         let body = test::read_body(resp).await;
         let message: ResponseMessage = serde_json::from_slice(&body).unwrap();
-        assert_eq!(message.probabilities.rows(), 3);
-        assert_eq!(message.probabilities.cols(), 3);
-        assert_eq!(message.entropy.rows(), 3);
-        assert_eq!(message.entropy.cols(), 3);
+        let (probabilities, entropy) = colorized(&message);
+        assert_eq!(probabilities.rows(), 3);
+        assert_eq!(probabilities.cols(), 3);
+        assert_eq!(entropy.rows(), 3);
+        assert_eq!(entropy.cols(), 3);
+        assert_eq!(message.std_error.rows(), 3);
+        assert_eq!(message.std_error.cols(), 3);
+        assert_eq!(message.occupants.rows(), 3);
+        assert_eq!(message.occupants.cols(), 3);
+        assert_eq!(message.expected_coverage.rows(), 3);
+        assert_eq!(message.expected_coverage.cols(), 3);
+        assert!(message.feasible);
 
-        for y in 0..message.probabilities.rows() {
-            for x in 0..message.probabilities.cols() {
+        for y in 0..probabilities.rows() {
+            for x in 0..probabilities.cols() {
                 let pos = Position::new(x, y);
-                assert!(
-                    message.probabilities[&pos].0 >= 0.0 && message.probabilities[&pos].0 <= 1.0
-                );
-                assert!(message.entropy[&pos].0 >= 0.0 && message.entropy[&pos].0 <= 1.0);
+                assert!(probabilities[&pos].0 >= 0.0 && probabilities[&pos].0 <= 1.0);
+                assert!(entropy[&pos].0 >= 0.0 && entropy[&pos].0 <= 1.0);
+                assert!(message.std_error[&pos] >= 0.0 && message.std_error[&pos] <= 0.5);
             }
         }
     }
+
+    #[actix_web::test]
+    async fn test_estimate_query() {
+        let app = test::init_service(test_app().service(estimate_query)).await;
+        let req = test::TestRequest::get()
+            .uri("/estimate?mask=...,..%23&rectangles=1x1&seed=1")
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let message: ResponseMessage = test::read_body_json(resp).await;
+        let (probabilities, _) = colorized(&message);
+        assert_eq!(probabilities.rows(), 2);
+        assert_eq!(probabilities.cols(), 3);
+        assert_eq!(probabilities[&Position::new(2, 1)].0, 0.0);
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_query_malformed() {
+        let app =
+            test::init_service(test_app().app_data(query_config()).service(estimate_query)).await;
+        let req = test::TestRequest::get()
+            .uri("/estimate?mask=..x&rectangles=1x1")
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body: ErrorResponse = test::read_body_json(resp).await;
+        assert_eq!(body.code, "malformed_query");
+        assert_eq!(body.message, "invalid mask cell 'x' in row 0");
+
+        let req = test::TestRequest::get()
+            .uri("/estimate?rectangles=1x1")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_error(resp, StatusCode::BAD_REQUEST, "malformed_query").await;
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_color_maps() {
+        let app = test::init_service(test_app().service(estimate)).await;
+        let req = test::TestRequest::post()
+            .uri("/estimate")
+            .set_json(serde_json::json!({
+                "mask": Grid::new(1, 2, true),
+                "rectangles": [],
+                "probability_cmap": "magma",
+                "entropy_cmap": "viridis",
+            }))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let message: ResponseMessage = test::read_body_json(resp).await;
+        let (probabilities, entropy) = colorized(&message);
+        let origin = Position::new(0, 0);
+        assert_eq!(probabilities[&origin].1, to_rgb(0.0, &ColorMap::Magma));
+        assert_eq!(entropy[&origin].1, to_rgb(0.0, &ColorMap::Viridis));
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_rescale_probabilities() {
+        let app = test::init_service(test_app().service(estimate)).await;
+        // A domino covers the middle of a 3x3 board in 4 of its 12 placements, more than any other
+        // cell, and a corner in only 2.
+        let req = test::TestRequest::post()
+            .uri("/estimate")
+            .set_json(serde_json::json!({
+                "mask": Grid::new(3, 3, false),
+                "rectangles": [{"width": 2, "height": 1}],
+                "probability_cmap": "grayscale",
+                "rescale_probabilities": true,
+            }))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let message: ResponseMessage = test::read_body_json(resp).await;
+        let (probabilities, _) = colorized(&message);
+        let (max_pos, max) = probabilities
+            .iter_positions()
+            .max_by(|(_, a), (_, b)| a.0.total_cmp(&b.0))
+            .unwrap();
+        // The most likely cell keeps its true probability, but gets the brightest color.
+        assert_eq!(max_pos, Position::new(1, 1));
+        assert!((max.0 - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(max.1, Color(255, 255, 255));
+        let corner = &probabilities[&Position::new(0, 0)];
+        assert!((corner.0 - 1.0 / 6.0).abs() < 1e-9);
+        assert_eq!(corner.1, to_rgb(0.5, &ColorMap::Grayscale));
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_legend() {
+        let app = test::init_service(test_app().service(estimate)).await;
+        let request = |body: serde_json::Value| {
+            test::TestRequest::post()
+                .uri("/estimate")
+                .set_json(body)
+                .to_request()
+        };
+
+        let req = request(serde_json::json!({
+            "mask": Grid::new(3, 3, false),
+            "rectangles": [{"width": 2, "height": 1}],
+            "entropy_cmap": "cividis",
+        }));
+        let message: ResponseMessage = test::call_and_read_body_json(&app, req).await;
+        let legend = message.legend.unwrap();
+        assert_eq!(legend.probabilities.len(), DEFAULT_LEGEND_STOPS);
+        assert_eq!(
+            legend.probabilities.first(),
+            Some(&(0.0, to_rgb(0.0, &ColorMap::Viridis)))
+        );
+        assert_eq!(
+            legend.probabilities.last(),
+            Some(&(1.0, to_rgb(1.0, &ColorMap::Viridis)))
+        );
+        assert_eq!(
+            legend.entropy.first(),
+            Some(&(0.0, to_rgb(0.0, &ColorMap::Cividis)))
+        );
+        assert_eq!(
+            legend.entropy.last(),
+            Some(&(1.0, to_rgb(1.0, &ColorMap::Cividis)))
+        );
+
+        // Rescaled probabilities reach the end of the color map at the largest one, 1/3.
+        let req = request(serde_json::json!({
+            "mask": Grid::new(3, 3, false),
+            "rectangles": [{"width": 2, "height": 1}],
+            "rescale_probabilities": true,
+            "legend_stops": 3,
+        }));
+        let message: ResponseMessage = test::call_and_read_body_json(&app, req).await;
+        let legend = message.legend.unwrap();
+        assert_eq!(legend.probabilities.len(), 3);
+        let (max, color) = &legend.probabilities[2];
+        assert!((max - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(*color, to_rgb(1.0, &ColorMap::Viridis));
+
+        let req = request(serde_json::json!({
+            "mask": Grid::new(3, 3, false),
+            "rectangles": [{"width": 2, "height": 1}],
+            "colorize": false,
+        }));
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert!(body.get("legend").is_none());
+
+        let req = request(serde_json::json!({
+            "mask": Grid::new(3, 3, false),
+            "rectangles": [{"width": 2, "height": 1}],
+            "legend_stops": 1,
+        }));
+        let resp = test::call_service(&app, req).await;
+        assert_error(
+            resp,
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "invalid_legend_stops",
+        )
+        .await;
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_of_rectangles() {
+        let app = test::init_service(test_app().service(estimate)).await;
+        let request = |of_rectangles: serde_json::Value| {
+            test::TestRequest::post()
+                .uri("/estimate")
+                .set_json(serde_json::json!({
+                    "mask": Grid::new(3, 3, false),
+                    "rectangles": [{"width": 3, "height": 1}, {"width": 1, "height": 1}],
+                    "simulations": 5_000,
+                    "seed": 1,
+                    "colorize": false,
+                    "of_rectangles": of_rectangles,
+                }))
+                .to_request()
+        };
+
+        let all: serde_json::Value =
+            test::call_and_read_body_json(&app, request(serde_json::json!([]))).await;
+        let long: serde_json::Value =
+            test::call_and_read_body_json(&app, request(serde_json::json!([1]))).await;
+        assert_ne!(long["probabilities"], all["probabilities"]);
+        let probabilities: Grid<f64> =
+            serde_json::from_value(long["probabilities"].clone()).unwrap();
+        assert!((probabilities.sum() - 3.0).abs() < 1e-9);
+        // A filtered board is sampled, while the whole small board is enumerated exactly.
+        assert_eq!(long["meta"]["simulations_run"], 5_000);
+        assert_eq!(all["meta"]["simulations_run"], 0);
+
+        let resp = test::call_service(&app, request(serde_json::json!([3]))).await;
+        assert_error(
+            resp,
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "invalid_of_rectangles",
+        )
+        .await;
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_binary() {
+        let app = test::init_service(test_app().service(estimate)).await;
+        // A domino on a 2x3 board covers a cell of the middle column in 3 of its 7 placements and
+        // each corner in 2.
+        let request = |accept: &str| {
+            test::TestRequest::post()
+                .uri("/estimate")
+                .insert_header(("accept", accept))
+                .set_json(serde_json::json!({
+                    "mask": Grid::new(2, 3, false),
+                    "rectangles": [{"width": 2, "height": 1}],
+                }))
+                .to_request()
+        };
+
+        let resp = test::call_service(&app, request("application/octet-stream")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get("content-type").unwrap(),
+            "application/octet-stream"
+        );
+        let body = test::read_body(resp).await;
+        assert_eq!(body.len(), 8 + 4 * 6);
+        let word = |i: usize| <[u8; 4]>::try_from(&body[i..i + 4]).unwrap();
+        assert_eq!(u32::from_le_bytes(word(0)), 2);
+        assert_eq!(u32::from_le_bytes(word(4)), 3);
+        let values: Vec<f32> = (0..6)
+            .map(|i| f32::from_le_bytes(word(8 + 4 * i)))
+            .collect();
+        let (edge, middle) = (2.0 / 7.0, 3.0 / 7.0);
+        for (value, expected) in values.iter().zip([edge, middle, edge, edge, middle, edge]) {
+            assert!((value - expected).abs() < 1e-6, "{values:?}");
+        }
+
+        // JSON stays the default, and wins when the client prefers it.
+        for accept in ["*/*", "application/json, application/octet-stream;q=0.5"] {
+            let resp = test::call_service(&app, request(accept)).await;
+            assert_eq!(
+                resp.headers().get("content-type").unwrap(),
+                "application/json"
+            );
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_probability_threshold() {
+        let app = test::init_service(test_app().service(estimate)).await;
+        // A domino on a 2x3 board covers each corner with probability 2/7 and each cell of the
+        // middle column with 3/7, enumerated exactly.
+        let request = |threshold: f64| {
+            test::TestRequest::post()
+                .uri("/estimate")
+                .set_json(serde_json::json!({
+                    "mask": Grid::new(2, 3, false),
+                    "rectangles": [{"width": 2, "height": 1}],
+                    "probability_threshold": threshold,
+                }))
+                .to_request()
+        };
+
+        let body: serde_json::Value = test::call_and_read_body_json(&app, request(0.3)).await;
+        let color = |p: f64| serde_json::to_value(to_rgb(p, &ColorMap::Viridis)).unwrap();
+        let corner = &body["probabilities"]["data"][0][0];
+        assert!((corner[0].as_f64().unwrap() - 2.0 / 7.0).abs() < 1e-12);
+        assert_eq!(corner[1], color(0.0));
+        let middle = &body["probabilities"]["data"][0][1];
+        assert!((middle[0].as_f64().unwrap() - 3.0 / 7.0).abs() < 1e-12);
+        assert_eq!(middle[1], color(3.0 / 7.0));
+
+        let resp = test::call_service(&app, request(1.0)).await;
+        assert_error(
+            resp,
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "invalid_probability_threshold",
+        )
+        .await;
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_baseline() {
+        let app = test::init_service(test_app().service(estimate)).await;
+        // A domino on a 4x4 board, enumerated exactly, so only the reveal changes the estimate.
+        let request = |mask: Grid<bool>, extra: serde_json::Value| {
+            let mut body = serde_json::json!({
+                "mask": mask,
+                "rectangles": [{"width": 2, "height": 1}],
+                "colorize": false,
+            });
+            body.as_object_mut()
+                .unwrap()
+                .extend(extra.as_object().unwrap().clone());
+            test::TestRequest::post()
+                .uri("/estimate")
+                .set_json(body)
+                .to_request()
+        };
+
+        let before: serde_json::Value = test::call_and_read_body_json(
+            &app,
+            request(Grid::new(4, 4, false), serde_json::json!({})),
+        )
+        .await;
+        let baseline: Grid<f64> = serde_json::from_value(before["probabilities"].clone()).unwrap();
+
+        // Revealing a corner to be empty takes the domino's placements through it from the corner
+        // and its two neighbors, while every other cell only changes slightly.
+        let mut revealed = Grid::new(4, 4, false);
+        revealed[&Position::new(0, 0)] = true;
+        let body: serde_json::Value = test::call_and_read_body_json(
+            &app,
+            request(
+                revealed.clone(),
+                serde_json::json!({"baseline": baseline, "delta_threshold": 0.02}),
+            ),
+        )
+        .await;
+        assert_eq!(body["rows"], 4);
+        assert_eq!(body["cols"], 4);
+        assert!(body.get("probabilities").is_none());
+        let changes: Vec<(Position, f64)> =
+            serde_json::from_value(body["changes"].clone()).unwrap();
+        let positions: Vec<Position> = changes.iter().map(|(pos, _)| pos.clone()).collect();
+        assert_eq!(
+            positions,
+            [
+                Position::new(0, 0),
+                Position::new(1, 0),
+                Position::new(0, 1)
+            ]
+        );
+        assert_eq!(changes[0].1, 0.0);
+        assert!((changes[1].1 - 2.0 / 22.0).abs() < 1e-9);
+
+        // An empty baseline has no cell to compare with, so every cell is returned.
+        let body: serde_json::Value = test::call_and_read_body_json(
+            &app,
+            request(
+                revealed.clone(),
+                serde_json::json!({"baseline": {"rows": 0, "cols": 0, "data": []}}),
+            ),
+        )
+        .await;
+        assert_eq!(body["changes"].as_array().unwrap().len(), 16);
+
+        let resp = test::call_service(
+            &app,
+            request(
+                revealed,
+                serde_json::json!({"baseline": baseline, "delta_threshold": 2.0}),
+            ),
+        )
+        .await;
+        assert_error(
+            resp,
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "invalid_delta_threshold",
+        )
+        .await;
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_normalize_entropy() {
+        let app = test::init_service(test_app().service(estimate)).await;
+        // The ends of the strip are covered in 4 of the 6 layouts and the middle in 5, so no cell
+        // reaches an entropy of 1.
+        let req = test::TestRequest::post()
+            .uri("/estimate")
+            .set_json(serde_json::json!({
+                "mask": Grid::new(1, 4, false),
+                "rectangles": [{"width": 2, "height": 1}, {"width": 1, "height": 1}],
+                "entropy_cmap": "grayscale",
+                "normalize_entropy": true,
+            }))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let message: ResponseMessage = test::read_body_json(resp).await;
+        let (_, entropy) = colorized(&message);
+        let (end, middle) = (
+            &entropy[&Position::new(0, 0)],
+            &entropy[&Position::new(1, 0)],
+        );
+        assert!(end.0 > middle.0 && end.0 < 1.0);
+        assert_eq!(end.1, Color(255, 255, 255));
+        assert_eq!(middle.1, Color(0, 0, 0));
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_unknown_field() {
+        let app = test::init_service(test_app().app_data(json_config()).service(estimate)).await;
+        let req = test::TestRequest::post()
+            .uri("/estimate")
+            .set_json(serde_json::json!({
+                "mask": Grid::new(3, 3, false),
+                "rectangles": [{"width": 2, "height": 1}],
+                "simulation": 100,
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body: ErrorResponse = test::read_body_json(resp).await;
+        assert_eq!(body.code, "unknown_field");
+        assert!(body.message.contains("simulation"), "{}", body.message);
+    }
+
+    #[actix_web::test]
+    async fn test_wire_names() {
+        let param = RequestParams {
+            mask: Grid::new(1, 2, CellState::Unknown),
+            rectangles: vec![Rectangle::new(1, 1).unwrap()],
+            simulations: Some(100),
+            seed: Some(1),
+            tolerance: Some(0.5),
+            required: Some(Grid::new(1, 2, false)),
+            probability_cmap: Some(ColorMap::Plasma),
+            entropy_cmap: Some(ColorMap::Cividis),
+            colorize: Some(false),
+            normalize_entropy: Some(true),
+            rescale_probabilities: Some(true),
+            probability_threshold: Some(0.01),
+            legend_stops: Some(5),
+            of_rectangles: Some(vec![1]),
+            sunk: Some(vec![]),
+            baseline: Some(Grid::new(1, 2, 0.0)),
+            delta_threshold: Some(0.01),
+            antithetic: Some(true),
+        };
+        let value = serde_json::to_value(&param).unwrap();
+        let mut names: Vec<&str> = value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(|k| k.as_str())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "antithetic",
+                "baseline",
+                "colorize",
+                "delta_threshold",
+                "entropy_cmap",
+                "legend_stops",
+                "mask",
+                "normalize_entropy",
+                "of_rectangles",
+                "probability_cmap",
+                "probability_threshold",
+                "rectangles",
+                "required",
+                "rescale_probabilities",
+                "seed",
+                "simulations",
+                "sunk",
+                "tolerance",
+            ]
+        );
+        let again: RequestParams = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&again).unwrap(), value);
+
+        let message = to_response_message(
+            estimate_with_options(
+                &Grid::new(1, 2, false),
+                &param.rectangles,
+                &EstimateOptions::default(),
+            ),
+            &param,
+        );
+        let value = serde_json::to_value(&message).unwrap();
+        assert_eq!(value["version"], API_VERSION);
+        for name in [
+            "format",
+            "probabilities",
+            "entropy",
+            "std_error",
+            "occupants",
+            "expected_coverage",
+            "feasibility",
+            "feasible",
+            "meta",
+        ] {
+            assert!(value.get(name).is_some(), "{name} missing");
+        }
+        for name in [
+            "simulations_run",
+            "elapsed_ms",
+            "feasible_samples",
+            "cached",
+        ] {
+            assert!(value["meta"].get(name).is_some(), "meta.{name} missing");
+        }
+        let again: ResponseMessage = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&again).unwrap(), value);
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_malformed_json() {
+        let app = test::init_service(test_app().app_data(json_config()).service(estimate)).await;
+        let req = test::TestRequest::post()
+            .uri("/estimate")
+            .insert_header(("content-type", "application/json"))
+            .set_payload("{\"mask\": ")
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_error(resp, StatusCode::BAD_REQUEST, "malformed_json").await;
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_invalid_json() {
+        let app = test::init_service(test_app().app_data(json_config()).service(estimate)).await;
+        let bodies = [
+            // A zero-dimension rectangle.
+            serde_json::json!({
+                "mask": Grid::new(2, 2, false),
+                "rectangles": [{"width": 0, "height": 1}],
+            }),
+            // A ragged mask.
+            serde_json::json!({
+                "mask": {"rows": 2, "cols": 2, "data": [[false, false], [false]]},
+                "rectangles": [],
+            }),
+            // A rectangle placed zero times.
+            serde_json::json!({
+                "mask": Grid::new(2, 2, false),
+                "rectangles": [{"width": 1, "height": 1, "count": 0}],
+            }),
+        ];
+        for body in bodies {
+            let req = test::TestRequest::post()
+                .uri("/estimate")
+                .set_json(body)
+                .to_request();
+
+            let resp = test::call_service(&app, req).await;
+            assert_error(resp, StatusCode::UNPROCESSABLE_ENTITY, "invalid_json").await;
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_rectangle_count() {
+        let app = test::init_service(test_app().service(estimate)).await;
+        let mut responses = vec![];
+        for rectangles in [
+            serde_json::json!([
+                {"width": 2, "height": 1},
+                {"width": 1, "height": 1, "count": 5},
+            ]),
+            serde_json::json!([
+                {"width": 2, "height": 1},
+                {"width": 1, "height": 1},
+                {"width": 1, "height": 1},
+                {"width": 1, "height": 1},
+                {"width": 1, "height": 1},
+                {"width": 1, "height": 1},
+            ]),
+        ] {
+            let req = test::TestRequest::post()
+                .uri("/estimate")
+                .set_json(serde_json::json!({
+                    "mask": Grid::new(3, 3, false),
+                    "rectangles": rectangles,
+                    "simulations": 2000,
+                    "seed": 11,
+                }))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let mut message: serde_json::Value = test::read_body_json(resp).await;
+            message["meta"]["elapsed_ms"] = serde_json::Value::Null;
+            responses.push(message);
+        }
+        assert_eq!(responses[0], responses[1]);
+        assert_eq!(responses[0]["meta"]["simulations_run"], 2000);
+        assert_eq!(responses[0]["feasibility"], 1.0);
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_too_many_rectangles() {
+        let app = test::init_service(test_app().app_data(json_config()).service(estimate)).await;
+        // More rectangles than cells can never be placed, so they are rejected before sampling.
+        let req = test::TestRequest::post()
+            .uri("/estimate")
+            .set_json(serde_json::json!({
+                "mask": Grid::new(2, 2, false),
+                "rectangles": vec![serde_json::json!({"width": 1, "height": 1}); 5],
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_error(
+            resp,
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "too_many_rectangles",
+        )
+        .await;
+
+        // More rectangles than the largest grid has cells are rejected while deserializing.
+        let req = test::TestRequest::post()
+            .uri("/estimate")
+            .set_json(serde_json::json!({
+                "mask": Grid::new(2, 2, false),
+                "rectangles": [
+                    {"width": 1, "height": 1, "count": MAX_RECTANGLE_COUNT},
+                    {"width": 1, "height": 1},
+                ],
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_error(resp, StatusCode::UNPROCESSABLE_ENTITY, "invalid_json").await;
+
+        // A body past the payload limit is rejected before it is deserialized at all.
+        let rectangles = vec![serde_json::json!({"width": 1, "height": 1}); 100_000];
+        let req = test::TestRequest::post()
+            .uri("/estimate")
+            .set_json(serde_json::json!({
+                "mask": Grid::new(2, 2, false),
+                "rectangles": rectangles,
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_error(resp, StatusCode::PAYLOAD_TOO_LARGE, "payload_too_large").await;
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_raw() {
+        let app = test::init_service(test_app().service(estimate)).await;
+        let req = test::TestRequest::post()
+            .uri("/estimate")
+            .set_json(serde_json::json!({
+                "mask": Grid::new(1, 2, false),
+                "rectangles": [{"width": 1, "height": 1}],
+                "colorize": false,
+            }))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["format"], "raw");
+        assert_eq!(
+            body["probabilities"]["data"],
+            serde_json::json!([[0.5, 0.5]])
+        );
+
+        let message: ResponseMessage = serde_json::from_value(body).unwrap();
+        let Heatmaps::Raw {
+            probabilities,
+            entropy,
+        } = message.heatmaps
+        else {
+            panic!("expected a raw response");
+        };
+        assert_eq!(probabilities[&Position::new(1, 0)], 0.5);
+        assert!((entropy[&Position::new(1, 0)] - 1.0).abs() < 1e-9);
+    }
 }
@@ -0,0 +1,155 @@
+use crate::api::error::ApiError;
+use crate::api::estimate::{RequestParams, run_estimate};
+use crate::api::limits::GridLimits;
+use crate::api::pool::EstimatorPool;
+use crate::estimator::to_entropy;
+use crate::types::Grid;
+use actix_web::HttpResponse;
+use actix_web::{post, web};
+use serde::{Deserialize, Serialize};
+
+/// The query parameters of `POST /estimate/csv`.
+#[derive(Serialize, Deserialize)]
+struct CsvQuery {
+    /// Whether to append the entropy block below the probabilities. Defaults to `false`.
+    entropy: Option<bool>,
+}
+
+/// Escapes a CSV field, quoting it if it contains a comma, a quote or a line break.
+fn escape_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Appends a grid to a CSV document as a block of rows.
+///
+/// The block starts with a header row of `label` followed by the column indices, and each row
+/// starts with its row index.
+fn write_block(csv: &mut String, label: &str, grid: &Grid<f64>) {
+    let header =
+        std::iter::once(escape_field(label)).chain((0..grid.cols()).map(|x| x.to_string()));
+    push_row(csv, header);
+    for y in 0..grid.rows() {
+        let values = grid.row(y).unwrap_or_default().iter().map(f64::to_string);
+        push_row(csv, std::iter::once(y.to_string()).chain(values));
+    }
+}
+
+/// Appends a row of already escaped fields to a CSV document.
+fn push_row(csv: &mut String, fields: impl Iterator<Item = String>) {
+    csv.push_str(&fields.collect::<Vec<_>>().join(","));
+    csv.push('\n');
+}
+
+/// Lays out the probabilities, and optionally the entropies below them, as a CSV document.
+fn to_csv(probabilities: &Grid<f64>, entropy: Option<&Grid<f64>>) -> String {
+    let mut csv = String::new();
+    write_block(&mut csv, "probability", probabilities);
+    if let Some(entropy) = entropy {
+        csv.push('\n');
+        write_block(&mut csv, "entropy", entropy);
+    }
+    csv
+}
+
+/// Estimates the probabilities of a board and exports them as CSV.
+///
+/// The probabilities are laid out row by row under a header row of column indices, followed by a
+/// blank line and an entropy block in the same layout if `entropy=true` is given.
+#[post("/estimate/csv")]
+pub async fn estimate_csv(
+    param: web::Json<RequestParams>,
+    query: web::Query<CsvQuery>,
+    pool: web::Data<EstimatorPool>,
+    limits: web::Data<GridLimits>,
+) -> Result<HttpResponse, ApiError> {
+    let param = param.into_inner();
+    let with_entropy = query.entropy.unwrap_or(false);
+    let csv = pool
+        .run(move || {
+            let result = run_estimate(&param, &limits)?;
+            let entropy = with_entropy.then(|| to_entropy(&result.probabilities));
+            Ok::<_, ApiError>(to_csv(&result.probabilities, entropy.as_ref()))
+        })
+        .await??;
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .body(csv))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_app;
+    use crate::types::Position;
+    use actix_web::{http::StatusCode, test};
+    use serde_json::json;
+
+    /// Splits a CSV document without quoted fields into blocks of rows of fields.
+    fn parse_blocks(csv: &str) -> Vec<Vec<Vec<String>>> {
+        csv.split("\n\n")
+            .map(|block| {
+                block
+                    .lines()
+                    .map(|line| line.split(',').map(str::to_string).collect())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn test_escape_field() {
+        assert_eq!(escape_field("0.5"), "0.5");
+        assert_eq!(escape_field("a,b"), "\"a,b\"");
+        assert_eq!(escape_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[actix_web::test]
+    async fn test_to_csv() {
+        let mut probabilities = Grid::new(1, 2, 0.0);
+        probabilities[&Position::new(1, 0)] = 0.25;
+        assert_eq!(to_csv(&probabilities, None), "probability,0,1\n0,0,0.25\n");
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_csv() {
+        let app = test::init_service(test_app().service(estimate_csv)).await;
+        let req = test::TestRequest::post()
+            .uri("/estimate/csv?entropy=true")
+            .set_json(json!({
+                "mask": {"rows": 2, "cols": 3, "data": [[false, false, false], [false, false, true]]},
+                "rectangles": [{"width": 2, "height": 1}],
+            }))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get("content-type").unwrap(),
+            "text/csv; charset=utf-8"
+        );
+
+        let body = test::read_body(resp).await;
+        let csv = std::str::from_utf8(&body).unwrap();
+        assert!(csv.ends_with('\n') && !csv.ends_with("\n\n"));
+
+        let blocks = parse_blocks(csv);
+        assert_eq!(blocks.len(), 2);
+        for (block, label) in blocks.iter().zip(["probability", "entropy"]) {
+            assert_eq!(block.len(), 3);
+            assert_eq!(block[0], vec![label, "0", "1", "2"]);
+            for (y, row) in block[1..].iter().enumerate() {
+                assert_eq!(row.len(), 4);
+                assert_eq!(row[0], y.to_string());
+                for value in &row[1..] {
+                    let value: f64 = value.parse().unwrap();
+                    assert!((0.0..=1.0).contains(&value));
+                }
+            }
+        }
+        assert_eq!(blocks[0][2][3], "0");
+    }
+}
@@ -0,0 +1,43 @@
+use crate::api::pool::EstimatorPool;
+use actix_web::{HttpResponse, Responder, get, web};
+use serde::{Deserialize, Serialize};
+
+/// The response message.
+#[derive(Serialize, Deserialize)]
+struct ResponseMessage {
+    status: String,
+    /// The number of threads estimates are simulated on.
+    estimator_threads: usize,
+}
+
+/// Reports that the service is up, without touching the filesystem or running an estimate.
+#[get("/healthz")]
+pub async fn healthz(pool: web::Data<EstimatorPool>) -> impl Responder {
+    HttpResponse::Ok().json(ResponseMessage {
+        status: "ok".to_string(),
+        estimator_threads: pool.threads(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{App, http::StatusCode, test};
+
+    #[actix_web::test]
+    async fn test_healthz() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(EstimatorPool::new(3).unwrap()))
+                .service(healthz),
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/healthz").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let message: ResponseMessage = test::read_body_json(resp).await;
+        assert_eq!(message.status, "ok");
+        assert_eq!(message.estimator_threads, 3);
+    }
+}
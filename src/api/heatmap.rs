@@ -0,0 +1,200 @@
+use crate::api::error::ApiError;
+use crate::api::estimate::{RequestParams, run_estimate};
+use crate::api::limits::GridLimits;
+use crate::api::pool::EstimatorPool;
+use crate::colors::{Color, ColorMap};
+use crate::types::{Grid, Position};
+use actix_web::HttpResponse;
+use actix_web::{post, web};
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_CELL_PX: u32 = 32;
+const MIN_CELL_PX: u32 = 1;
+const MAX_CELL_PX: u32 = 64;
+
+/// The query parameters of `POST /estimate/png`.
+#[derive(Serialize, Deserialize)]
+struct PngQuery {
+    /// The side of the square drawn for each cell, in pixels. Defaults to [`DEFAULT_CELL_PX`].
+    cell_px: Option<u32>,
+}
+
+/// Checks if the size of a cell is valid.
+fn validate_cell_px(cell_px: u32) -> bool {
+    (MIN_CELL_PX..=MAX_CELL_PX).contains(&cell_px)
+}
+
+/// Encodes a grid of colors as a PNG image with a square block of pixels per cell.
+///
+/// # Arguments
+///
+/// * `colors` - The color of each cell. Must have at least one row and one column.
+/// * `cell_px` - The side of the block of each cell, in pixels.
+fn encode_png(colors: &Grid<Color>, cell_px: u32) -> Vec<u8> {
+    let cell = cell_px as usize;
+    let (width, height) = (colors.cols() * cell, colors.rows() * cell);
+
+    let mut pixels = Vec::with_capacity(width * height * 3);
+    for py in 0..height {
+        for px in 0..width {
+            let Color(r, g, b) = colors[&Position::new(px / cell, py / cell)];
+            pixels.extend([r, g, b]);
+        }
+    }
+
+    let mut image = Vec::new();
+    let mut encoder = png::Encoder::new(&mut image, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    // Writing to a vector cannot fail, and the pixels match the header by construction.
+    let mut writer = encoder
+        .write_header()
+        .expect("the PNG header is written to memory");
+    writer
+        .write_image_data(&pixels)
+        .expect("the pixels match the image size");
+    writer.finish().expect("the PNG is written to memory");
+    image
+}
+
+/// Estimates the probabilities of a board and renders them as a PNG heatmap.
+///
+/// Each cell is a `cell_px` square colored with the probability color map of the request. Responds
+/// with `422 Unprocessable Entity` if `cell_px` is not between [`MIN_CELL_PX`] and
+/// [`MAX_CELL_PX`].
+#[post("/estimate/png")]
+pub async fn estimate_png(
+    param: web::Json<RequestParams>,
+    query: web::Query<PngQuery>,
+    pool: web::Data<EstimatorPool>,
+    limits: web::Data<GridLimits>,
+) -> Result<HttpResponse, ApiError> {
+    let cell_px = query.cell_px.unwrap_or(DEFAULT_CELL_PX);
+    if !validate_cell_px(cell_px) {
+        return Err(ApiError::unprocessable(
+            "invalid_cell_px",
+            format!("cell_px must be between {MIN_CELL_PX} and {MAX_CELL_PX}"),
+        ));
+    }
+
+    let param = param.into_inner();
+    let png = pool
+        .run(move || {
+            let result = run_estimate(&param, &limits)?;
+            let cmap = param
+                .probability_cmap
+                .as_ref()
+                .unwrap_or(&ColorMap::Viridis);
+            let colors = param
+                .color_probabilities(&result.probabilities, cmap)
+                .map(|(_, color)| color.clone());
+            Ok::<_, ApiError>(encode_png(&colors, cell_px))
+        })
+        .await??;
+    Ok(HttpResponse::Ok().content_type("image/png").body(png))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::error::ErrorResponse;
+    use crate::api::test_app;
+    use crate::colors::to_rgb;
+    use actix_web::{http::StatusCode, test};
+    use serde_json::json;
+
+    /// Decodes a PNG image into its width, height and RGB pixels.
+    fn decode_png(image: &[u8]) -> (u32, u32, Vec<u8>) {
+        let mut reader = png::Decoder::new(image).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        pixels.truncate(info.buffer_size());
+        (info.width, info.height, pixels)
+    }
+
+    #[actix_web::test]
+    async fn test_validate_cell_px() {
+        assert!(validate_cell_px(MIN_CELL_PX));
+        assert!(validate_cell_px(MAX_CELL_PX));
+        assert!(!validate_cell_px(0));
+        assert!(!validate_cell_px(MAX_CELL_PX + 1));
+    }
+
+    #[actix_web::test]
+    async fn test_encode_png() {
+        let mut colors = Grid::new(1, 2, Color(0, 0, 0));
+        colors[&Position::new(1, 0)] = Color(255, 128, 0);
+
+        let (width, height, pixels) = decode_png(&encode_png(&colors, 2));
+        assert_eq!((width, height), (4, 2));
+        assert_eq!(&pixels[..6], &[0, 0, 0, 0, 0, 0]);
+        assert_eq!(&pixels[6..12], &[255, 128, 0, 255, 128, 0]);
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_png() {
+        let app = test::init_service(test_app().service(estimate_png)).await;
+        let req = test::TestRequest::post()
+            .uri("/estimate/png?cell_px=4")
+            .set_json(json!({
+                "mask": {"rows": 2, "cols": 3, "data": [[false, false, false], [true, true, true]]},
+                "rectangles": [{"width": 3, "height": 1}],
+            }))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get("content-type").unwrap(), "image/png");
+
+        let (width, height, pixels) = decode_png(&test::read_body(resp).await);
+        assert_eq!((width, height), (12, 8));
+        let Color(r, g, b) = to_rgb(1.0, &ColorMap::Viridis);
+        assert_eq!(&pixels[..3], &[r, g, b]);
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_png_all_zero() {
+        // An infeasible board has all-zero probabilities and renders in the lowest color.
+        let app = test::init_service(test_app().service(estimate_png)).await;
+        let req = test::TestRequest::post()
+            .uri("/estimate/png?cell_px=1")
+            .set_json(json!({
+                "mask": {"rows": 1, "cols": 2, "data": [[false, true]]},
+                "rectangles": [{"width": 2, "height": 1}],
+                "probability_cmap": "grayscale",
+            }))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let (width, height, pixels) = decode_png(&test::read_body(resp).await);
+        assert_eq!((width, height), (2, 1));
+        assert_eq!(pixels, vec![0; 6]);
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_png_empty() {
+        let app = test::init_service(test_app().service(estimate_png)).await;
+        let req = test::TestRequest::post()
+            .uri("/estimate/png")
+            .set_json(json!({"mask": {"rows": 0, "cols": 0, "data": []}, "rectangles": []}))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body: ErrorResponse = test::read_body_json(resp).await;
+        assert_eq!(body.code, "grid_empty");
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_png_invalid_cell_px() {
+        let app = test::init_service(test_app().service(estimate_png)).await;
+        let req = test::TestRequest::post()
+            .uri("/estimate/png?cell_px=0")
+            .set_json(json!({"mask": {"rows": 1, "cols": 1, "data": [[false]]}, "rectangles": []}))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+}
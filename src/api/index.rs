@@ -1,19 +1,150 @@
-use actix_files::NamedFile;
-use actix_web::{Responder, get};
+use crate::api::error::ApiError;
+use actix_files::{Files, NamedFile};
+use actix_web::{HttpResponse, get, web};
+use std::path::{Path, PathBuf};
 
+/// The directory of the built frontend, relative to the working directory.
+pub const DEFAULT_STATIC_ROOT: &str = "frontend/.output/public";
+/// The environment variable that overrides [`DEFAULT_STATIC_ROOT`].
+pub const STATIC_ROOT_ENV: &str = "PANEL_GAME_STATIC_ROOT";
+/// The page served at `/` when the frontend has not been built.
+const FALLBACK_PAGE: &str = "<!doctype html>
+<html lang=\"en\">
+<head><meta charset=\"utf-8\"><title>panel-game</title></head>
+<body>
+<h1>panel-game</h1>
+<p>The frontend is not built, but the API is up: try <code>POST /estimate</code>, or see
+<a href=\"/schema\">/schema</a> for the request format.</p>
+</body>
+</html>
+";
+
+/// The directory the frontend entry point and its assets are served from.
+#[derive(Clone, Debug)]
+pub struct StaticRoot(PathBuf);
+
+impl StaticRoot {
+    /// Creates a [`StaticRoot`] at the given directory.
+    pub fn new(path: impl Into<PathBuf>) -> StaticRoot {
+        StaticRoot(path.into())
+    }
+
+    /// Reads the static root from [`STATIC_ROOT_ENV`], falling back to [`DEFAULT_STATIC_ROOT`].
+    pub fn from_env() -> StaticRoot {
+        StaticRoot::new(
+            std::env::var_os(STATIC_ROOT_ENV).unwrap_or_else(|| DEFAULT_STATIC_ROOT.into()),
+        )
+    }
+
+    /// Returns the directory.
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Checks if the directory holds a built frontend, an `index.html`.
+    pub fn is_built(&self) -> bool {
+        self.0.join("index.html").is_file()
+    }
+}
+
+/// Registers the frontend: [`index`] and the files of the [`StaticRoot`] if it is built, or only
+/// [`FALLBACK_PAGE`] at `/` if it is not, so the API works without building the frontend.
+///
+/// Serves every path under `/`, so it must be registered after every other route.
+pub fn configure_frontend(cfg: &mut web::ServiceConfig, root: &StaticRoot) {
+    if root.is_built() {
+        cfg.service(index).service(Files::new("/", root.path()));
+    } else {
+        cfg.service(fallback_index);
+    }
+}
+
+/// Serves [`FALLBACK_PAGE`] in place of the frontend entry point.
+#[get("/")]
+async fn fallback_index() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(FALLBACK_PAGE)
+}
+
+/// Serves the frontend entry point, `index.html` in the [`StaticRoot`].
+///
+/// Responds with `404 Not Found` and the code `frontend_missing` if it does not exist.
 #[get("/")]
-pub async fn index() -> impl Responder {
-    NamedFile::open_async("frontend/.output/public/index.html").await
+pub async fn index(root: web::Data<StaticRoot>) -> Result<NamedFile, ApiError> {
+    let path = root.path().join("index.html");
+    NamedFile::open_async(&path).await.map_err(|error| {
+        ApiError::not_found(
+            "frontend_missing",
+            format!("cannot open {}: {error}", path.display()),
+        )
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::api::error::ErrorResponse;
+    use crate::api::estimate::estimate;
+    use crate::api::test_app;
     use actix_web::{App, http::StatusCode, test};
 
     #[actix_web::test]
     async fn test_index() {
-        let app = test::init_service(App::new().service(index)).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(StaticRoot::new(DEFAULT_STATIC_ROOT)))
+                .service(index),
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get("content-type").unwrap(),
+            "text/html; charset=utf-8"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_index_missing() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(StaticRoot::new("does/not/exist")))
+                .service(index),
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let body: ErrorResponse = test::read_body_json(resp).await;
+        assert_eq!(body.code, "frontend_missing");
+        assert!(body.message.contains("does/not/exist/index.html"));
+    }
+
+    #[actix_web::test]
+    async fn test_configure_frontend() {
+        let root = StaticRoot::new(DEFAULT_STATIC_ROOT);
+        assert!(root.is_built());
+        let app =
+            test::init_service(App::new().configure(|cfg| configure_frontend(cfg, &root))).await;
+        let req = test::TestRequest::get().uri("/").to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        assert_ne!(body, FALLBACK_PAGE);
+    }
+
+    #[actix_web::test]
+    async fn test_configure_frontend_missing() {
+        let root = StaticRoot::new("does/not/exist");
+        assert!(!root.is_built());
+        let app = test::init_service(
+            test_app()
+                .service(estimate)
+                .configure(|cfg| configure_frontend(cfg, &root)),
+        )
+        .await;
+
         let req = test::TestRequest::get().uri("/").to_request();
         let resp = test::call_service(&app, req).await;
         assert_eq!(resp.status(), StatusCode::OK);
@@ -21,5 +152,20 @@ mod tests {
             resp.headers().get("content-type").unwrap(),
             "text/html; charset=utf-8"
         );
+        assert_eq!(test::read_body(resp).await, FALLBACK_PAGE);
+        let req = test::TestRequest::get().uri("/app.js").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        // The API works all the same.
+        let req = test::TestRequest::post()
+            .uri("/estimate")
+            .set_json(serde_json::json!({
+                "mask": {"rows": 1, "cols": 2, "data": [[false, false]]},
+                "rectangles": [{"width": 2, "height": 1}],
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
     }
 }
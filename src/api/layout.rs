@@ -0,0 +1,133 @@
+use crate::api::error::ApiError;
+use crate::api::estimate::{RequestParams, validate_params};
+use crate::api::limits::GridLimits;
+use crate::api::pool::EstimatorPool;
+use crate::battleship::remove_sunk;
+use crate::error::Error;
+use crate::estimator::{DEFAULT_LAYOUT_ATTEMPTS, sample_layout};
+use crate::types::Grid;
+use actix_web::HttpResponse;
+use actix_web::{post, web};
+use serde::{Deserialize, Serialize};
+
+/// The response message.
+#[derive(Serialize, Deserialize)]
+struct ResponseMessage {
+    /// The rectangle covering each cell, identified by its 1-based index in the request, or 0 for
+    /// none.
+    layout: Grid<usize>,
+    /// The seed the layout was sampled with, to sample it again.
+    seed: u64,
+}
+
+/// Samples one layout of the rectangles consistent with the board, as an example solution.
+///
+/// The layout is drawn like the simulations of `POST /estimate` draw theirs, and the same request
+/// with the same `seed` always gives the same layout. With sunk ships, only the ships still afloat
+/// are placed, so the footprints of the sunk ships are 0. Responds with `404 Not Found` if no
+/// layout was found within [`DEFAULT_LAYOUT_ATTEMPTS`] simulations.
+#[post("/estimate/sample-layout")]
+pub async fn estimate_sample_layout(
+    param: web::Json<RequestParams>,
+    pool: web::Data<EstimatorPool>,
+    limits: web::Data<GridLimits>,
+) -> Result<HttpResponse, ApiError> {
+    let (mask, options) = validate_params(&param, &limits)?;
+    let sunk = param.sunk.as_deref().unwrap_or_default();
+    let afloat = remove_sunk(&mask, &param.rectangles, sunk, options.required.as_ref())
+        .map_err(Error::from)?;
+    let seed = options.seed.unwrap_or_else(rand::random);
+
+    let layout = pool
+        .run(move || {
+            let layout = sample_layout(
+                &afloat.rect_mask,
+                &afloat.rectangles,
+                afloat.required.as_ref(),
+                seed,
+                DEFAULT_LAYOUT_ATTEMPTS,
+            )?;
+            Some(layout.map(|&id| match id {
+                0 => 0,
+                id => afloat.ids[id - 1] + 1,
+            }))
+        })
+        .await?
+        .ok_or(Error::Infeasible)?;
+    Ok(HttpResponse::Ok().json(ResponseMessage { layout, seed }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_app;
+    use crate::types::Position;
+    use actix_web::{http::StatusCode, test};
+    use serde_json::json;
+
+    #[actix_web::test]
+    async fn test_sample_layout() {
+        let app = test::init_service(test_app().service(estimate_sample_layout)).await;
+        let rectangles = [(2, 1), (3, 1), (1, 1)];
+        let body = json!({
+            "mask": {"rows": 3, "cols": 3, "data": [
+                ["unknown", "unknown", "empty"],
+                ["unknown", "occupied", "unknown"],
+                ["unknown", "unknown", "unknown"],
+            ]},
+            "rectangles": rectangles.map(|(width, height)| json!({"width": width, "height": height})),
+            "seed": 5,
+        });
+        let req = test::TestRequest::post()
+            .uri("/estimate/sample-layout")
+            .set_json(&body)
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let message: ResponseMessage = test::read_body_json(resp).await;
+        assert_eq!(message.seed, 5);
+        let layout = message.layout;
+        assert_eq!(layout[&Position::new(2, 0)], 0);
+        assert_ne!(layout[&Position::new(1, 1)], 0);
+        for (pos, &id) in layout.iter_positions() {
+            if id == 0 {
+                continue;
+            }
+            // Every covered cell lies in a footprint of the size of its rectangle.
+            let (width, height) = rectangles[id - 1];
+            let cells = layout.count(|&cell| cell == id);
+            assert_eq!(cells, width * height);
+            let across = (0..layout.cols())
+                .filter(|&x| layout[&Position::new(x, pos.y())] == id)
+                .count();
+            let down = (0..layout.rows())
+                .filter(|&y| layout[&Position::new(pos.x(), y)] == id)
+                .count();
+            assert!((across, down) == (width, height) || (across, down) == (height, width));
+        }
+
+        // The same seed samples the same layout.
+        let req = test::TestRequest::post()
+            .uri("/estimate/sample-layout")
+            .set_json(&body)
+            .to_request();
+        let again: ResponseMessage = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(again.layout, layout);
+    }
+
+    #[actix_web::test]
+    async fn test_sample_layout_not_found() {
+        let app = test::init_service(test_app().service(estimate_sample_layout)).await;
+        let req = test::TestRequest::post()
+            .uri("/estimate/sample-layout")
+            .set_json(json!({
+                "mask": {"rows": 1, "cols": 3, "data": [[false, true, false]]},
+                "rectangles": [{"width": 2, "height": 1}],
+            }))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+}
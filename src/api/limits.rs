@@ -0,0 +1,182 @@
+use std::fmt;
+
+/// The environment variable the grid limits are read from, as `ROWSxCOLS` or a single side.
+pub const MAX_GRID_ENV: &str = "PANEL_GAME_MAX_GRID";
+/// The environment variable the maximum number of cells of a grid is read from.
+pub const MAX_GRID_CELLS_ENV: &str = "PANEL_GAME_MAX_GRID_CELLS";
+/// The largest side a grid may be configured to, keeping a single estimate within reason.
+pub const MAX_GRID_SIDE: usize = 32;
+/// The default maximum number of rows of a grid.
+pub const DEFAULT_MAX_GRID_ROWS: usize = MAX_GRID_SIDE;
+/// The default maximum number of columns of a grid.
+pub const DEFAULT_MAX_GRID_COLS: usize = MAX_GRID_SIDE;
+/// The default maximum number of cells of a grid, that of a 16x16 grid.
+///
+/// Every grid within the former default of 16x16 is still accepted, and so are long narrow grids
+/// of the same area, such as 8x32.
+pub const DEFAULT_MAX_GRID_CELLS: usize = 256;
+
+/// The largest grid the service accepts, set at startup.
+///
+/// A grid must be within both the maximum number of rows and columns and the maximum number of
+/// cells, so the sides can be generous while the cost of an estimate stays bounded by its area.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct GridLimits {
+    /// The maximum number of rows.
+    rows: usize,
+    /// The maximum number of columns.
+    cols: usize,
+    /// The maximum number of cells.
+    cells: usize,
+}
+
+/// An invalid grid limit configuration.
+#[derive(PartialEq, Debug)]
+pub enum InvalidGridLimits {
+    /// The value of [`MAX_GRID_ENV`] is not a valid size.
+    Size(String),
+    /// The value of [`MAX_GRID_CELLS_ENV`] is not a valid number of cells.
+    Cells(String),
+}
+
+impl fmt::Display for InvalidGridLimits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidGridLimits::Size(text) => write!(
+                f,
+                "invalid {MAX_GRID_ENV} {text:?}: expected ROWSxCOLS or a single side, each between 1 and {MAX_GRID_SIDE}",
+            ),
+            InvalidGridLimits::Cells(text) => write!(
+                f,
+                "invalid {MAX_GRID_CELLS_ENV} {text:?}: expected a number of cells between 1 and the rows times the columns",
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InvalidGridLimits {}
+
+impl Default for GridLimits {
+    fn default() -> Self {
+        GridLimits {
+            rows: DEFAULT_MAX_GRID_ROWS,
+            cols: DEFAULT_MAX_GRID_COLS,
+            cells: DEFAULT_MAX_GRID_CELLS,
+        }
+    }
+}
+
+impl GridLimits {
+    /// Creates [`GridLimits`] of the given maximum size, accepting every grid within it.
+    ///
+    /// Returns `None` if a side is zero or larger than [`MAX_GRID_SIDE`].
+    pub fn new(rows: usize, cols: usize) -> Option<GridLimits> {
+        let valid = |side| (1..=MAX_GRID_SIDE).contains(&side);
+        (valid(rows) && valid(cols)).then_some(GridLimits {
+            rows,
+            cols,
+            cells: rows * cols,
+        })
+    }
+
+    /// Returns these [`GridLimits`] with the given maximum number of cells.
+    ///
+    /// Returns `None` if `cells` is zero or more than the rows times the columns, which could
+    /// never be reached.
+    pub fn with_max_cells(self, cells: usize) -> Option<GridLimits> {
+        (1..=self.rows * self.cols)
+            .contains(&cells)
+            .then_some(GridLimits { cells, ..self })
+    }
+
+    /// Parses [`GridLimits`] from `ROWSxCOLS`, e.g. `12x16`, or a single side for a square limit.
+    pub fn parse(text: &str) -> Result<GridLimits, InvalidGridLimits> {
+        let invalid = || InvalidGridLimits::Size(text.to_string());
+        let side = |side: &str| side.trim().parse::<usize>().map_err(|_| invalid());
+        let (rows, cols) = match text.split_once(['x', 'X']) {
+            Some((rows, cols)) => (side(rows)?, side(cols)?),
+            None => (side(text)?, side(text)?),
+        };
+        GridLimits::new(rows, cols).ok_or_else(invalid)
+    }
+
+    /// Reads the limits from [`MAX_GRID_ENV`] and [`MAX_GRID_CELLS_ENV`].
+    ///
+    /// Falls back to the default if neither is set. If only the size is set, every grid within it
+    /// is accepted, as before the number of cells could be limited.
+    pub fn from_env() -> Result<GridLimits, InvalidGridLimits> {
+        let limits = match std::env::var(MAX_GRID_ENV) {
+            Ok(text) => GridLimits::parse(&text)?,
+            Err(_) => GridLimits::default(),
+        };
+        match std::env::var(MAX_GRID_CELLS_ENV) {
+            Ok(text) => text
+                .trim()
+                .parse()
+                .ok()
+                .and_then(|cells| limits.with_max_cells(cells))
+                .ok_or(InvalidGridLimits::Cells(text)),
+            Err(_) => Ok(limits),
+        }
+    }
+
+    /// Returns the maximum number of rows.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Returns the maximum number of columns.
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// Returns the maximum number of cells.
+    pub fn cells(&self) -> usize {
+        self.cells
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_grid_limits_new() {
+        assert_eq!(
+            GridLimits::new(12, 9).map(|l| (l.rows(), l.cols())),
+            Some((12, 9))
+        );
+        assert_eq!(GridLimits::new(0, 9), None);
+        assert_eq!(GridLimits::new(9, MAX_GRID_SIDE + 1), None);
+        assert_eq!(GridLimits::new(12, 9).unwrap().cells(), 108);
+    }
+
+    #[actix_web::test]
+    async fn test_grid_limits_with_max_cells() {
+        let limits = GridLimits::new(12, 9).unwrap();
+        assert_eq!(limits.with_max_cells(50).map(|l| l.cells()), Some(50));
+        assert_eq!(limits.with_max_cells(108).map(|l| l.cells()), Some(108));
+        assert_eq!(limits.with_max_cells(0), None);
+        assert_eq!(limits.with_max_cells(109), None);
+
+        // The default accepts every grid the former default of 16x16 did.
+        let limits = GridLimits::default();
+        assert!(limits.rows() >= 16 && limits.cols() >= 16 && limits.cells() >= 16 * 16);
+    }
+
+    #[actix_web::test]
+    async fn test_grid_limits_parse() {
+        assert_eq!(
+            GridLimits::parse("12x16"),
+            Ok(GridLimits::new(12, 16).unwrap())
+        );
+        assert_eq!(
+            GridLimits::parse("10"),
+            Ok(GridLimits::new(10, 10).unwrap())
+        );
+        assert!(GridLimits::parse("0x5").is_err());
+        assert!(GridLimits::parse("12x").is_err());
+        assert!(GridLimits::parse("large").is_err());
+        assert!(GridLimits::parse("64").is_err());
+    }
+}
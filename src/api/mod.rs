@@ -1,2 +1,39 @@
+pub mod batch;
+pub mod cache;
+pub mod cors;
+pub mod error;
 pub mod estimate;
+pub mod export;
+pub mod healthz;
+pub mod heatmap;
 pub mod index;
+pub mod layout;
+pub mod limits;
+pub mod pool;
+pub mod reveal;
+pub mod schema;
+pub mod stream;
+pub mod suggest;
+pub mod validate;
+pub mod version;
+
+/// Builds an app with what every estimating endpoint needs, a pool of 2 estimator threads and
+/// the default [`GridLimits`](limits::GridLimits), to register the endpoints under test on.
+///
+/// App data registered on it afterwards replaces these, e.g. tighter grid limits.
+#[cfg(test)]
+pub(crate) fn test_app() -> actix_web::App<
+    impl actix_web::dev::ServiceFactory<
+        actix_web::dev::ServiceRequest,
+        Config = (),
+        Response = actix_web::dev::ServiceResponse,
+        Error = actix_web::Error,
+        InitError = (),
+    >,
+> {
+    use actix_web::web::Data;
+
+    actix_web::App::new()
+        .app_data(Data::new(pool::EstimatorPool::new(2).unwrap()))
+        .app_data(Data::new(limits::GridLimits::default()))
+}
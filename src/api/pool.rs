@@ -0,0 +1,82 @@
+use crate::api::error::ApiError;
+use actix_web::web;
+use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
+use std::sync::Arc;
+
+/// The dedicated thread pool the estimates are simulated on.
+///
+/// Keeps the CPU-heavy simulations off the cores left to the actix workers, so concurrent requests
+/// are still handled promptly while an estimate runs.
+#[derive(Clone, Debug)]
+pub struct EstimatorPool(Arc<ThreadPool>);
+
+impl EstimatorPool {
+    /// Builds a pool of the given number of threads.
+    ///
+    /// # Arguments
+    ///
+    /// * `threads` - The number of threads, or `0` for one per core.
+    ///
+    /// # Returns
+    ///
+    /// The pool, or the error if its threads could not be spawned.
+    pub fn new(threads: usize) -> Result<EstimatorPool, ThreadPoolBuildError> {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|index| format!("estimator-{index}"))
+            .build()?;
+        Ok(EstimatorPool(Arc::new(pool)))
+    }
+
+    /// Returns the number of threads of the pool.
+    pub fn threads(&self) -> usize {
+        self.0.current_num_threads()
+    }
+
+    /// Runs `op` on the pool, so any parallel iterator inside it only uses the pool's threads.
+    ///
+    /// Blocks the calling thread until `op` returns, so async handlers use [`EstimatorPool::run`]
+    /// instead.
+    pub fn install<R: Send>(&self, op: impl FnOnce() -> R + Send) -> R {
+        self.0.install(op)
+    }
+
+    /// Runs `op` on the pool from an async handler, without blocking the actix worker.
+    ///
+    /// Like [`EstimatorPool::install`], but a blocking thread of the runtime waits for the pool
+    /// instead, so the worker keeps serving other requests in the meantime. `op` runs within the
+    /// tracing span of the caller.
+    ///
+    /// # Returns
+    ///
+    /// What `op` returned, or a `500 Internal Server Error` if it panicked.
+    pub async fn run<R: Send + 'static>(
+        &self,
+        op: impl FnOnce() -> R + Send + 'static,
+    ) -> Result<R, ApiError> {
+        let pool = self.clone();
+        let span = tracing::Span::current();
+        web::block(move || span.in_scope(|| pool.install(op)))
+            .await
+            .map_err(|_| ApiError::internal("estimator_failed", "the estimate failed to complete"))
+    }
+}
+
+/// Returns the default number of estimator threads, every core but one for the actix workers.
+pub fn default_threads() -> usize {
+    std::thread::available_parallelism().map_or(1, |cores| cores.get().saturating_sub(1).max(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_estimator_pool() {
+        let pool = EstimatorPool::new(2).unwrap();
+        assert_eq!(pool.threads(), 2);
+        assert_eq!(pool.install(rayon::current_num_threads), 2);
+        assert_eq!(pool.run(rayon::current_num_threads).await.unwrap(), 2);
+        assert!(default_threads() >= 1);
+    }
+}
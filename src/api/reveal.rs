@@ -0,0 +1,297 @@
+use crate::api::cache::LruCache;
+use crate::api::error::ApiError;
+use crate::api::estimate::{RequestParams, ResponseMessage, to_response_message, validate_params};
+use crate::api::limits::GridLimits;
+use crate::api::pool::EstimatorPool;
+use crate::estimator::{EstimateOptions, LayoutSample, sample_layouts};
+use crate::types::{CellState, Grid, Position, Rectangle};
+use actix_web::HttpResponse;
+use actix_web::{post, web};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// The most boards whose layouts are kept at once.
+const CACHE_CAPACITY: usize = 8;
+/// The most simulations run, and so layouts kept, for a board.
+///
+/// Together with [`CACHE_CAPACITY`], this bounds the memory of the cache to two bytes per cell of
+/// `CACHE_CAPACITY * MAX_REVEAL_SIMULATIONS` layouts, 80 MiB for grids of 16x16.
+const MAX_REVEAL_SIMULATIONS: usize = 20_000;
+/// The fewest layouts a reveal may leave for them to be reused instead of simulating anew.
+const MIN_REUSED_LAYOUTS: usize = 1000;
+
+/// The request parameters of `POST /estimate/reveal`.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RevealParams {
+    /// The board before the reveal.
+    board: RequestParams,
+    /// The newly revealed cell.
+    reveal: Reveal,
+}
+
+/// A newly revealed cell.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Reveal {
+    /// The position of the cell, which must be unknown on the board.
+    position: Position,
+    /// What the cell was revealed to be, `empty` or `occupied`.
+    state: CellState,
+}
+
+/// The response message.
+#[derive(Serialize, Deserialize)]
+struct RevealResponse {
+    /// The estimate of the board after the reveal.
+    #[serde(flatten)]
+    estimate: ResponseMessage,
+    /// Whether the estimate reused the layouts kept for the board before the reveal.
+    incremental: bool,
+}
+
+/// What identifies a board to the cache, the cells to avoid and cover and the pieces to place.
+#[derive(Hash, PartialEq, Eq, Clone)]
+struct BoardKey {
+    mask: Grid<bool>,
+    required: Option<Grid<bool>>,
+    rectangles: Vec<Rectangle>,
+}
+
+impl BoardKey {
+    /// Creates the key of a board, ignoring a required mask without any required cell.
+    fn new(mask: Grid<bool>, required: Option<Grid<bool>>, rectangles: &[Rectangle]) -> BoardKey {
+        BoardKey {
+            mask,
+            required: required.filter(|required| required.count(|&cell| cell) > 0),
+            rectangles: rectangles.to_vec(),
+        }
+    }
+
+    /// Returns the key of the board after revealing an unknown cell to be empty or occupied.
+    fn reveal(&self, pos: &Position, occupied: bool) -> BoardKey {
+        let mut next = self.clone();
+        if occupied {
+            let required = next
+                .required
+                .get_or_insert_with(|| Grid::new(self.mask.rows(), self.mask.cols(), false));
+            required[pos] = true;
+        } else {
+            next.mask[pos] = true;
+        }
+        next
+    }
+}
+
+/// The layouts sampled for the most recently estimated boards, shared by every worker.
+///
+/// Holds up to [`CACHE_CAPACITY`] boards, evicting the least recently used one.
+#[derive(Clone)]
+pub struct LayoutCache(LruCache<BoardKey, Arc<LayoutSample>>);
+
+impl Default for LayoutCache {
+    fn default() -> Self {
+        LayoutCache(LruCache::new(CACHE_CAPACITY))
+    }
+}
+
+impl LayoutCache {
+    /// Returns the layouts kept for a board, if any, marking them as recently used.
+    fn get(&self, key: &BoardKey) -> Option<Arc<LayoutSample>> {
+        self.0.get(key)
+    }
+
+    /// Keeps the layouts of a board, evicting the least recently used board if the cache is full.
+    fn insert(&self, key: BoardKey, sample: Arc<LayoutSample>) {
+        self.0.insert(key, sample);
+    }
+}
+
+/// Re-estimates a board after one more cell is revealed, reusing the layouts of the board before.
+///
+/// The layouts sampled for each board are kept in a [`LayoutCache`]. If the board before the
+/// reveal is cached, its layouts consistent with the reveal are the sample of the revealed board,
+/// so nothing is simulated, as long as at least [`MIN_REUSED_LAYOUTS`] are left. Otherwise the
+/// revealed board is simulated anew, with at most [`MAX_REVEAL_SIMULATIONS`] simulations. Either
+/// way its layouts are cached for the next reveal.
+///
+/// Unlike `POST /estimate`, small boards are not enumerated exactly and the counts are not
+/// averaged over symmetries. Responds with `422 Unprocessable Entity` if the revealed cell is not
+/// an unknown cell of the board or is revealed as unknown, or if the board has sunk ships or
+/// counts only some rectangles.
+#[post("/estimate/reveal")]
+pub async fn estimate_reveal(
+    param: web::Json<RevealParams>,
+    pool: web::Data<EstimatorPool>,
+    limits: web::Data<GridLimits>,
+    cache: web::Data<LayoutCache>,
+) -> Result<HttpResponse, ApiError> {
+    let board = &param.board;
+    if board.sunk.as_ref().is_some_and(|sunk| !sunk.is_empty()) {
+        return Err(ApiError::unprocessable(
+            "sunk_unsupported",
+            "boards with sunk ships cannot be re-estimated incrementally",
+        ));
+    }
+    if board
+        .of_rectangles
+        .as_ref()
+        .is_some_and(|ids| !ids.is_empty())
+    {
+        return Err(ApiError::unprocessable(
+            "of_rectangles_unsupported",
+            "boards counting only some rectangles cannot be re-estimated incrementally",
+        ));
+    }
+    let (mask, options) = validate_params(board, &limits)?;
+    let Reveal { position, state } = &param.reveal;
+    if board.mask.get(position) != Some(&CellState::Unknown) || *state == CellState::Unknown {
+        return Err(ApiError::unprocessable(
+            "invalid_reveal",
+            "reveal must be an unknown cell of the grid revealed to be empty or occupied",
+        ));
+    }
+
+    let occupied = *state == CellState::Occupied;
+    let previous = BoardKey::new(mask, options.required.clone(), &board.rectangles);
+    let next = previous.reveal(position, occupied);
+    let reused = cache
+        .get(&previous)
+        .map(|sample| sample.reveal(position, occupied))
+        .filter(|sample| sample.len() >= MIN_REUSED_LAYOUTS);
+    let incremental = reused.is_some();
+    let (next, sample, estimate) = pool
+        .run(move || {
+            let sample = reused.unwrap_or_else(|| {
+                let options = EstimateOptions {
+                    simulations: options.simulations.min(MAX_REVEAL_SIMULATIONS),
+                    required: next.required.clone(),
+                    ..options
+                };
+                sample_layouts(&next.mask, &next.rectangles, &options)
+            });
+            let estimate = sample.to_estimate();
+            (next, sample, estimate)
+        })
+        .await?;
+    cache.insert(next, Arc::new(sample));
+
+    Ok(HttpResponse::Ok().json(RevealResponse {
+        estimate: to_response_message(estimate, board),
+        incremental,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_app;
+    use actix_web::{http::StatusCode, test};
+    use serde_json::{Value, json};
+
+    /// Builds a request revealing `position` as `state` on a board of a domino and a tromino, with
+    /// `#` for an empty cell, `o` for an occupied cell and `.` for an unknown cell.
+    fn request(cells: &[&str], position: (usize, usize), state: &str) -> Value {
+        let data: Vec<Vec<&str>> = cells
+            .iter()
+            .map(|row| {
+                row.chars()
+                    .map(|cell| match cell {
+                        '#' => "empty",
+                        'o' => "occupied",
+                        _ => "unknown",
+                    })
+                    .collect()
+            })
+            .collect();
+        json!({
+            "board": {
+                "mask": {"rows": cells.len(), "cols": cells[0].len(), "data": data},
+                "rectangles": [{"width": 2, "height": 1}, {"width": 3, "height": 1}],
+                "simulations": 20_000,
+                "seed": 1,
+                "colorize": false,
+            },
+            "reveal": {"position": {"x": position.0, "y": position.1}, "state": state},
+        })
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_reveal() {
+        let app = test::init_service(
+            test_app()
+                .app_data(web::Data::new(LayoutCache::default()))
+                .service(estimate_reveal),
+        )
+        .await;
+
+        // Nothing is cached for the first board, so the revealed board is simulated anew.
+        let req = test::TestRequest::post()
+            .uri("/estimate/reveal")
+            .set_json(request(
+                &["....", "....", "....", "...."],
+                (1, 0),
+                "occupied",
+            ))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["incremental"], false);
+        assert_eq!(body["probabilities"]["data"][0][1], 1.0);
+        assert_eq!(body["meta"]["simulations_run"], 20_000);
+
+        // The next reveal filters the layouts kept for the board it was revealed on.
+        let req = test::TestRequest::post()
+            .uri("/estimate/reveal")
+            .set_json(request(&[".o..", "....", "....", "...."], (2, 0), "empty"))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["incremental"], true);
+        assert_eq!(body["probabilities"]["data"][0][2], 0.0);
+        assert_eq!(body["probabilities"]["data"][0][1], 1.0);
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_reveal_invalid() {
+        let app = test::init_service(
+            test_app()
+                .app_data(web::Data::new(LayoutCache::default()))
+                .service(estimate_reveal),
+        )
+        .await;
+        for body in [
+            request(&["#...", "....", "....", "...."], (0, 0), "occupied"),
+            request(&["....", "....", "....", "...."], (4, 0), "empty"),
+            request(&["....", "....", "....", "...."], (0, 0), "unknown"),
+        ] {
+            let req = test::TestRequest::post()
+                .uri("/estimate/reveal")
+                .set_json(body)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_layout_cache_evicts() {
+        let cache = LayoutCache::default();
+        let sample = Arc::new(sample_layouts(
+            &Grid::new(1, 1, false),
+            &[Rectangle::new(1, 1).unwrap()],
+            &EstimateOptions {
+                simulations: 10,
+                ..Default::default()
+            },
+        ));
+        let key = |cols| BoardKey::new(Grid::new(1, cols, false), None, &[]);
+        for cols in 1..=CACHE_CAPACITY {
+            cache.insert(key(cols), sample.clone());
+        }
+        // Using the first board keeps it, so the second is evicted instead.
+        assert!(cache.get(&key(1)).is_some());
+        cache.insert(key(CACHE_CAPACITY + 1), sample);
+        assert!(cache.get(&key(1)).is_some());
+        assert!(cache.get(&key(2)).is_none());
+        assert!(cache.get(&key(CACHE_CAPACITY + 1)).is_some());
+    }
+}
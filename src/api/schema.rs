@@ -0,0 +1,81 @@
+use crate::api::estimate::{RequestParams, ResponseMessage};
+use actix_web::{HttpResponse, Responder, get};
+use schemars::generate::SchemaSettings;
+use schemars::{JsonSchema, Schema};
+use serde::{Deserialize, Serialize};
+
+/// The JSON Schemas of the body of `POST /estimate` and of its response.
+#[derive(Serialize, Deserialize)]
+struct ApiSchema {
+    /// The schema of the request body, as it is deserialized.
+    request: Schema,
+    /// The schema of the response body, as it is serialized.
+    response: Schema,
+}
+
+impl ApiSchema {
+    /// Derives the schemas from the request and response types, so they never fall out of sync.
+    fn generate() -> ApiSchema {
+        ApiSchema {
+            request: root_schema::<RequestParams>(SchemaSettings::default().for_deserialize()),
+            response: root_schema::<ResponseMessage>(SchemaSettings::default().for_serialize()),
+        }
+    }
+}
+
+/// Generates the root schema of a type with the given settings.
+fn root_schema<T: JsonSchema>(settings: SchemaSettings) -> Schema {
+    settings.into_generator().into_root_schema_for::<T>()
+}
+
+/// Describes the estimate API as JSON Schemas of its request and response, for client codegen.
+///
+/// The other endpoints taking a body accept the same request, and `GET /estimate` its fields
+/// encoded in the query string.
+#[get("/schema")]
+pub async fn schema() -> impl Responder {
+    HttpResponse::Ok().json(ApiSchema::generate())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{App, http::StatusCode, test};
+    use serde_json::Value;
+
+    /// Collects the names of the properties declared anywhere in a schema.
+    fn property_names(value: &Value, names: &mut Vec<String>) {
+        match value {
+            Value::Object(object) => {
+                if let Some(Value::Object(properties)) = object.get("properties") {
+                    names.extend(properties.keys().cloned());
+                }
+                object
+                    .values()
+                    .for_each(|value| property_names(value, names));
+            }
+            Value::Array(values) => values.iter().for_each(|value| property_names(value, names)),
+            _ => {}
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_schema() {
+        let app = test::init_service(App::new().service(schema)).await;
+        let req = test::TestRequest::get().uri("/schema").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let body: Value = test::read_body_json(resp).await;
+        let mut request = vec![];
+        property_names(&body["request"], &mut request);
+        for field in ["mask", "rectangles", "width", "count", "seed"] {
+            assert!(request.iter().any(|name| name == field), "{field}");
+        }
+        let mut response = vec![];
+        property_names(&body["response"], &mut response);
+        for field in ["probabilities", "entropy", "feasibility", "simulations_run"] {
+            assert!(response.iter().any(|name| name == field), "{field}");
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use crate::api::error::ApiError;
+use crate::api::estimate::{
+    QueryParams, RequestParams, ResponseMessage, to_response_message, try_exact, validate_params,
+};
+use crate::api::limits::GridLimits;
+use crate::api::pool::EstimatorPool;
+use crate::estimator::estimate_with_progress;
+use actix_web::rt::task;
+use actix_web::web::Bytes;
+use actix_web::{HttpResponse, get, web};
+use futures_util::stream;
+use serde::Serialize;
+use std::convert::Infallible;
+use std::ops::ControlFlow;
+use tokio::sync::mpsc;
+
+/// The number of events buffered before the simulation waits for the client to catch up.
+const EVENT_BUFFER: usize = 4;
+
+/// The data of a `progress` event.
+#[derive(Serialize)]
+struct ProgressEvent {
+    /// The fraction of the requested simulations run so far.
+    progress: f64,
+    /// The estimate so far.
+    #[serde(flatten)]
+    estimate: ResponseMessage,
+}
+
+/// Formats a Server-Sent Event with a JSON payload.
+fn to_event(name: &str, data: &impl Serialize) -> Bytes {
+    let data = serde_json::to_string(data).expect("response messages serialize to JSON");
+    Bytes::from(format!("event: {name}\ndata: {data}\n\n"))
+}
+
+/// Estimates the probabilities of a board encoded like `GET /estimate`, streaming the estimate as
+/// Server-Sent Events.
+///
+/// Emits a `progress` event with the estimate so far after every batch of simulations, then a
+/// `done` event with the final estimate. Exactly enumerated boards only emit the `done` event.
+/// The simulation stops as soon as the client disconnects.
+#[get("/estimate/stream")]
+pub async fn estimate_stream(
+    query: web::Query<QueryParams>,
+    pool: web::Data<EstimatorPool>,
+    limits: web::Data<GridLimits>,
+) -> Result<HttpResponse, ApiError> {
+    let param = RequestParams::try_from(&*query)
+        .map_err(|error| ApiError::bad_request("malformed_query", error))?;
+    let (mask, options) = validate_params(&param, &limits)?;
+
+    let (sender, receiver) = mpsc::channel(EVENT_BUFFER);
+    task::spawn_blocking(move || {
+        let result = pool.install(|| {
+            try_exact(&mask, &param.rectangles, &options).unwrap_or_else(|| {
+                estimate_with_progress(&mask, &param.rectangles, &options, |snapshot| {
+                    let event = ProgressEvent {
+                        progress: snapshot.stats.simulations_run as f64
+                            / options.simulations as f64,
+                        estimate: to_response_message(snapshot.clone(), &param),
+                    };
+                    // The receiver is dropped with the response once the client disconnects.
+                    match sender.blocking_send(to_event("progress", &event)) {
+                        Ok(()) => ControlFlow::Continue(()),
+                        Err(_) => ControlFlow::Break(()),
+                    }
+                })
+            })
+        });
+        let _ = sender.blocking_send(to_event("done", &to_response_message(result, &param)));
+    });
+
+    let events = stream::unfold(receiver, |mut receiver| async move {
+        let event = receiver.recv().await?;
+        Some((Ok::<_, Infallible>(event), receiver))
+    });
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(events))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::error::ErrorResponse;
+    use crate::api::test_app;
+    use actix_web::{http::StatusCode, test};
+
+    /// Returns the names and JSON payloads of the events in a stream body.
+    fn parse_events(body: &[u8]) -> Vec<(String, serde_json::Value)> {
+        std::str::from_utf8(body)
+            .unwrap()
+            .split_terminator("\n\n")
+            .map(|event| {
+                let (name, data) = event.split_once('\n').unwrap();
+                (
+                    name.strip_prefix("event: ").unwrap().to_string(),
+                    serde_json::from_str(data.strip_prefix("data: ").unwrap()).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_stream() {
+        let app = test::init_service(test_app().service(estimate_stream)).await;
+        let req = test::TestRequest::get()
+            .uri("/estimate/stream?mask=.....,.....,.....,.....&rectangles=2x1&simulations=25000&seed=1")
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get("content-type").unwrap(),
+            "text/event-stream"
+        );
+
+        let events = parse_events(&test::read_body(resp).await);
+        let names: Vec<&str> = events.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["progress", "progress", "done"]);
+        assert_eq!(events[0].1["progress"], 0.4);
+        assert_eq!(events[1].1["progress"], 0.8);
+        assert_eq!(events[2].1["feasibility"], 1.0);
+        assert_eq!(events[2].1["probabilities"]["rows"], 4);
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_stream_exact() {
+        let app = test::init_service(test_app().service(estimate_stream)).await;
+        let req = test::TestRequest::get()
+            .uri("/estimate/stream?mask=..,..&rectangles=2x1")
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        let events = parse_events(&test::read_body(resp).await);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].0, "done");
+        assert_eq!(events[0].1["probabilities"]["data"][0][0][0], 0.5);
+    }
+
+    #[actix_web::test]
+    async fn test_estimate_stream_invalid() {
+        let app = test::init_service(test_app().service(estimate_stream)).await;
+        let req = test::TestRequest::get()
+            .uri("/estimate/stream?mask=...&rectangles=4x1")
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body: ErrorResponse = test::read_body_json(resp).await;
+        assert_eq!(body.code, "rectangle_too_large");
+    }
+}
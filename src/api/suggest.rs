@@ -0,0 +1,114 @@
+use crate::api::error::ApiError;
+use crate::api::estimate::{RequestParams, run_estimate};
+use crate::api::limits::GridLimits;
+use crate::api::pool::EstimatorPool;
+use crate::estimator::suggest_from;
+use crate::types::Position;
+use actix_web::HttpResponse;
+use actix_web::{post, web};
+use serde::{Deserialize, Serialize};
+
+/// The response message.
+#[derive(Serialize, Deserialize)]
+struct ResponseMessage {
+    /// The position of the most informative cell to reveal next.
+    position: Position,
+    /// The entropy of the cell.
+    entropy: f64,
+}
+
+/// Suggests the unmasked cell with the highest entropy.
+///
+/// Responds with `204 No Content` if every cell is masked or no feasible layout exists.
+#[post("/suggest")]
+pub async fn suggest(
+    param: web::Json<RequestParams>,
+    pool: web::Data<EstimatorPool>,
+    limits: web::Data<GridLimits>,
+) -> Result<HttpResponse, ApiError> {
+    let param = param.into_inner();
+    let revealed = param.mask.to_revealed();
+    let result = pool.run(move || run_estimate(&param, &limits)).await??;
+    Ok(match suggest_from(&revealed, &result) {
+        Some((position, entropy)) => HttpResponse::Ok().json(ResponseMessage { position, entropy }),
+        None => HttpResponse::NoContent().finish(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::limits::{DEFAULT_MAX_GRID_COLS, DEFAULT_MAX_GRID_ROWS};
+    use crate::api::test_app;
+    use crate::types::{Grid, Rectangle};
+    use actix_web::{http::StatusCode, test};
+
+    fn request(mask: Grid<bool>, rectangles: Vec<Rectangle>) -> RequestParams {
+        RequestParams {
+            mask: Grid::from(&mask),
+            rectangles,
+            simulations: None,
+            seed: None,
+            tolerance: None,
+            required: None,
+            probability_cmap: None,
+            entropy_cmap: None,
+            colorize: None,
+            normalize_entropy: None,
+            rescale_probabilities: None,
+            probability_threshold: None,
+            legend_stops: None,
+            of_rectangles: None,
+            sunk: None,
+            baseline: None,
+            delta_threshold: None,
+            antithetic: None,
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_suggest() {
+        let app = test::init_service(test_app().service(suggest)).await;
+        let req = test::TestRequest::post()
+            .uri("/suggest")
+            .set_json(request(
+                Grid::new(1, 3, false),
+                vec![Rectangle::new(2, 1).unwrap()],
+            ))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let message: ResponseMessage = test::read_body_json(resp).await;
+        assert_eq!(message.position, Position::new(0, 0));
+        assert!((message.entropy - 1.0).abs() < 1e-9);
+    }
+
+    #[actix_web::test]
+    async fn test_suggest_no_content() {
+        let app = test::init_service(test_app().service(suggest)).await;
+        let req = test::TestRequest::post()
+            .uri("/suggest")
+            .set_json(request(Grid::new(2, 2, true), vec![]))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[actix_web::test]
+    async fn test_suggest_invalid_grid_size() {
+        let app = test::init_service(test_app().service(suggest)).await;
+        let req = test::TestRequest::post()
+            .uri("/suggest")
+            .set_json(request(
+                Grid::new(DEFAULT_MAX_GRID_ROWS + 1, DEFAULT_MAX_GRID_COLS + 1, false),
+                vec![],
+            ))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+}
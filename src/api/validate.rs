@@ -0,0 +1,188 @@
+use crate::api::error::{ApiError, ErrorResponse};
+use crate::api::estimate::{RequestParams, find_invalid_params};
+use crate::api::limits::GridLimits;
+use crate::battleship::remove_sunk;
+use crate::error::Error;
+use actix_web::HttpResponse;
+use actix_web::{post, web};
+use serde::{Deserialize, Serialize};
+
+/// The response message.
+#[derive(Serialize, Deserialize)]
+struct ResponseMessage {
+    /// Whether the board has no problem, so `POST /estimate` would estimate it.
+    valid: bool,
+    /// Every problem found, with the code and message `POST /estimate` would reject it with.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    problems: Vec<ErrorResponse>,
+}
+
+/// Checks a board without estimating it, reporting every problem rather than only the first.
+///
+/// Runs the checks of `POST /estimate` on the grid size, the simulations, the tolerance, the
+/// rectangles, the required cells and the weights, followed by the sunk ships. A valid board is
+/// also checked for pieces covering more cells than the grid leaves free, with the code
+/// `insufficient_area`, which `POST /estimate` estimates as infeasible instead of rejecting.
+/// Responds with `{"valid": true}` if nothing was found.
+///
+/// Values that cannot be deserialized, e.g. a zero-dimension rectangle, are rejected like for
+/// `POST /estimate`, with `422 Unprocessable Entity`.
+#[post("/validate")]
+pub async fn validate(
+    param: web::Json<RequestParams>,
+    limits: web::Data<GridLimits>,
+) -> HttpResponse {
+    let problems: Vec<ErrorResponse> = find_problems(&param, &limits)
+        .into_iter()
+        .map(ApiError::into_body)
+        .collect();
+    HttpResponse::Ok().json(ResponseMessage {
+        valid: problems.is_empty(),
+        problems,
+    })
+}
+
+/// Lists the problems of a board, the board itself only being checked if its parameters are valid.
+fn find_problems(param: &RequestParams, limits: &GridLimits) -> Vec<ApiError> {
+    let (mask, required) = param.to_masks();
+    let errors = find_invalid_params(param, &mask, required.as_ref(), limits);
+    if !errors.is_empty() {
+        return errors;
+    }
+
+    let sunk = param.sunk.as_deref().unwrap_or_default();
+    let afloat = match remove_sunk(&mask, &param.rectangles, sunk, required.as_ref()) {
+        Ok(afloat) => afloat,
+        Err(error) => return vec![Error::from(error).into()],
+    };
+    let area: usize = afloat.rectangles.iter().map(|rect| rect.area()).sum();
+    let free = afloat.rect_mask.count(|&cell| !cell);
+    if area > free {
+        return vec![ApiError::unprocessable(
+            "insufficient_area",
+            format!("the rectangles cover {area} cells but only {free} are free"),
+        )];
+    }
+    vec![]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::error::json_config;
+    use actix_web::{App, http::StatusCode, test};
+    use serde_json::{Value, json};
+
+    /// Validates a request, returning the status and the body of the response.
+    async fn call(body: Value) -> (StatusCode, Value) {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(GridLimits::default()))
+                .app_data(json_config())
+                .service(validate),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/validate")
+            .set_json(body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let status = resp.status();
+        (status, test::read_body_json(resp).await)
+    }
+
+    /// Returns the codes of the problems reported.
+    fn codes(body: &Value) -> Vec<&str> {
+        body["problems"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|problem| problem["code"].as_str().unwrap())
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn test_validate_valid() {
+        let (status, body) = call(json!({
+            "mask": {"rows": 2, "cols": 2, "data": [[false, false], [false, false]]},
+            "rectangles": [{"width": 2, "height": 1}, {"width": 2, "height": 1}],
+        }))
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!({"valid": true}));
+    }
+
+    #[actix_web::test]
+    async fn test_validate_grid_size() {
+        let (status, body) = call(json!({
+            "mask": {"rows": 0, "cols": 0, "data": []},
+            "rectangles": [{"width": 1, "height": 1}],
+        }))
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["valid"], false);
+        assert_eq!(codes(&body), ["grid_empty"]);
+
+        let (_, body) = call(json!({
+            "mask": {"rows": 1, "cols": 33, "data": [vec![false; 33]]},
+            "rectangles": [{"width": 1, "height": 1}],
+        }))
+        .await;
+        assert_eq!(codes(&body), ["grid_too_large"]);
+    }
+
+    #[actix_web::test]
+    async fn test_validate_zero_dimension_rectangle() {
+        let (status, body) = call(json!({
+            "mask": {"rows": 1, "cols": 2, "data": [[false, false]]},
+            "rectangles": [{"width": 0, "height": 1}],
+        }))
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["code"], "invalid_json");
+    }
+
+    #[actix_web::test]
+    async fn test_validate_insufficient_area() {
+        let (status, body) = call(json!({
+            "mask": {"rows": 2, "cols": 2, "data": [[false, false], [false, true]]},
+            "rectangles": [{"width": 2, "height": 1}, {"width": 2, "height": 1}],
+        }))
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["valid"], false);
+        assert_eq!(codes(&body), ["insufficient_area"]);
+    }
+
+    #[actix_web::test]
+    async fn test_validate_oversized_rectangle() {
+        let (_, body) = call(json!({
+            "mask": {"rows": 2, "cols": 2, "data": [[false, false], [false, false]]},
+            "rectangles": [{"width": 3, "height": 1}],
+        }))
+        .await;
+        assert_eq!(body["valid"], false);
+        assert_eq!(codes(&body), ["rectangle_too_large"]);
+    }
+
+    #[actix_web::test]
+    async fn test_validate_reports_every_problem() {
+        let (_, body) = call(json!({
+            "mask": {"rows": 1, "cols": 2, "data": [[false, false]]},
+            "rectangles": [{"width": 3, "height": 1}, {"width": 1, "height": 1}, {"width": 1, "height": 1}],
+            "simulations": 0,
+            "tolerance": 2.0,
+        }))
+        .await;
+        assert_eq!(
+            codes(&body),
+            [
+                "invalid_simulations",
+                "invalid_tolerance",
+                "too_many_rectangles",
+                "rectangle_too_large",
+            ]
+        );
+        assert!(body["problems"][0]["message"].is_string());
+    }
+}
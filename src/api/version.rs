@@ -0,0 +1,39 @@
+use actix_web::{HttpResponse, Responder, get};
+use serde::{Deserialize, Serialize};
+
+/// The response message.
+#[derive(Serialize, Deserialize)]
+struct ResponseMessage {
+    /// The version of the crate.
+    version: String,
+    /// The abbreviated git commit the service was built from, or `unknown` if it was built
+    /// outside a git checkout without `PANEL_GAME_GIT_SHA` set.
+    git_sha: String,
+}
+
+/// Reports which build of the service is running.
+#[get("/version")]
+pub async fn version() -> impl Responder {
+    HttpResponse::Ok().json(ResponseMessage {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_sha: env!("PANEL_GAME_GIT_SHA").to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{App, http::StatusCode, test};
+
+    #[actix_web::test]
+    async fn test_version() {
+        let app = test::init_service(App::new().service(version)).await;
+        let req = test::TestRequest::get().uri("/version").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let message: ResponseMessage = test::read_body_json(resp).await;
+        assert_eq!(message.version, env!("CARGO_PKG_VERSION"));
+        assert!(!message.git_sha.is_empty());
+    }
+}
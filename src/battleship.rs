@@ -0,0 +1,314 @@
+//! Battleship rules on top of the estimator.
+//!
+//! Battleship reveals more than whether a cell is covered: a shot is a miss, a hit on a ship still
+//! afloat, or it sinks a ship, revealing its whole footprint. A miss masks its cell and a hit must
+//! be covered, as in any board. A sunk ship takes one rectangle out of the fleet and its footprint
+//! out of the board, so only the ships still afloat are estimated.
+
+use crate::estimator::{Estimate, EstimateOptions, estimate_with_options};
+use crate::types::{Grid, Position, Rectangle, Shape};
+use serde::{Deserialize, Serialize};
+
+/// A ship revealed to be sunk, with its footprint.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SunkShip {
+    /// The top-left cell of the ship.
+    pub position: Position,
+    /// The ship as it lies, its width and height in the orientation it was placed in.
+    pub rectangle: Rectangle,
+}
+
+/// A sunk ship that does not fit the board or the fleet.
+#[derive(Debug, PartialEq)]
+pub enum SunkError {
+    /// The ship extends past the edge of the board.
+    OutOfBounds {
+        /// The index of the sunk ship.
+        ship: usize,
+    },
+    /// The ship covers a miss or overlaps an earlier sunk ship.
+    Overlap {
+        /// The index of the sunk ship.
+        ship: usize,
+    },
+    /// No ship of the fleet still afloat can lie like the sunk ship.
+    NotInFleet {
+        /// The index of the sunk ship.
+        ship: usize,
+    },
+}
+
+impl std::fmt::Display for SunkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SunkError::OutOfBounds { ship } => {
+                write!(f, "sunk ship {ship} extends past the edge of the board")
+            }
+            SunkError::Overlap { ship } => {
+                write!(f, "sunk ship {ship} covers a miss or another sunk ship")
+            }
+            SunkError::NotInFleet { ship } => {
+                write!(f, "sunk ship {ship} matches no ship still afloat")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SunkError {}
+
+/// The board left once the sunk ships are taken out.
+#[derive(Debug, PartialEq)]
+pub struct Afloat {
+    /// The grid mask of the misses and the footprints of the sunk ships.
+    pub rect_mask: Grid<bool>,
+    /// The hits not explained by a sunk ship, which the ships afloat must cover, if any.
+    pub required: Option<Grid<bool>>,
+    /// The ships still afloat.
+    pub rectangles: Vec<Rectangle>,
+    /// The index in the fleet of each ship still afloat.
+    pub ids: Vec<usize>,
+}
+
+/// Takes the sunk ships out of a board.
+///
+/// Each sunk ship removes the first ship of the fleet still afloat that may lie like it, and its
+/// footprint is masked, so the cells it covered no longer need covering.
+///
+/// # Arguments
+///
+/// * `rect_mask` - A grid mask where `true` marks a miss.
+/// * `rectangles` - The whole fleet, sunk ships included.
+/// * `sunk` - The ships revealed to be sunk.
+/// * `required` - A grid where `true` marks a hit, if any.
+///
+/// # Returns
+///
+/// The board of the ships afloat, or the first sunk ship that does not fit.
+pub fn remove_sunk(
+    rect_mask: &Grid<bool>,
+    rectangles: &[Rectangle],
+    sunk: &[SunkShip],
+    required: Option<&Grid<bool>>,
+) -> Result<Afloat, SunkError> {
+    let mut mask = rect_mask.clone();
+    let mut afloat: Vec<usize> = (0..rectangles.len()).collect();
+    for (ship, sunk_ship) in sunk.iter().enumerate() {
+        // Compare the room left past the ship's corner, which cannot overflow, before listing the
+        // cells of the ship, which could be far too many for the board.
+        let (pos, rect) = (&sunk_ship.position, &sunk_ship.rectangle);
+        let fits = pos.x() < mask.cols()
+            && pos.y() < mask.rows()
+            && rect.width() <= mask.cols() - pos.x()
+            && rect.height() <= mask.rows() - pos.y();
+        if !fits {
+            return Err(SunkError::OutOfBounds { ship });
+        }
+        let shape = Shape::from(rect);
+        if !mask.all(&sunk_ship.position, &shape, &false) {
+            return Err(SunkError::Overlap { ship });
+        }
+        let lies_like = |rect: &Rectangle| {
+            rect.orientations().any(|rect| {
+                (rect.width(), rect.height())
+                    == (sunk_ship.rectangle.width(), sunk_ship.rectangle.height())
+            })
+        };
+        let index = afloat
+            .iter()
+            .position(|&id| lies_like(&rectangles[id]))
+            .ok_or(SunkError::NotInFleet { ship })?;
+        afloat.remove(index);
+
+        mask.fill_region(&sunk_ship.position, &sunk_ship.rectangle, true);
+    }
+
+    // A hit within a sunk ship is explained by it, and a masked cell can never be covered.
+    let required = required.map(|required| {
+        let mut required = required.clone();
+        for (pos, cell) in required.iter_positions_mut() {
+            *cell &= !mask[&pos] || rect_mask[&pos];
+        }
+        required
+    });
+    Ok(Afloat {
+        rect_mask: mask,
+        required: required.filter(|required| required.iter_positions().any(|(_, &hit)| hit)),
+        rectangles: afloat.iter().map(|&id| rectangles[id].clone()).collect(),
+        ids: afloat,
+    })
+}
+
+/// Estimates the probabilities of the ships still afloat on a battleship board.
+///
+/// The probabilities and occupants are over the ships afloat only, so the footprints of the sunk
+/// ships have probability 0 and no occupant. Occupants are identified by their 1-based index in
+/// the whole fleet, like the counted rectangles of `options`, where a sunk ship counts for
+/// nothing.
+///
+/// # Arguments
+///
+/// * `rect_mask` - A grid mask where `true` marks a miss.
+/// * `rectangles` - The whole fleet, sunk ships included.
+/// * `sunk` - The ships revealed to be sunk.
+/// * `options` - The options of the estimate, whose required cells are the hits.
+///
+/// # Returns
+///
+/// The estimate, or the first sunk ship that does not fit the board or the fleet.
+pub fn estimate_battleship(
+    rect_mask: &Grid<bool>,
+    rectangles: &[Rectangle],
+    sunk: &[SunkShip],
+    options: &EstimateOptions,
+) -> Result<Estimate, SunkError> {
+    let afloat = remove_sunk(rect_mask, rectangles, sunk, options.required.as_ref())?;
+    let counted = options.counted.as_ref().map(|counted| {
+        (0..afloat.ids.len())
+            .filter(|&i| counted.contains(&afloat.ids[i]))
+            .collect()
+    });
+    let options = EstimateOptions {
+        required: afloat.required,
+        counted,
+        ..options.clone()
+    };
+    let mut estimate = estimate_with_options(&afloat.rect_mask, &afloat.rectangles, &options);
+    estimate.occupants = estimate.occupants.map(|&(id, probability)| match id {
+        0 => (0, probability),
+        id => (afloat.ids[id - 1] + 1, probability),
+    });
+    Ok(estimate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 2x1 ship sunk at the left of the top row.
+    fn sunk_destroyer() -> SunkShip {
+        SunkShip {
+            position: Position::new(0, 0),
+            rectangle: Rectangle::new_unchecked(2, 1),
+        }
+    }
+
+    #[test]
+    fn test_remove_sunk() {
+        let rect_mask = Grid::new(2, 3, false);
+        let mut required = Grid::new(2, 3, false);
+        required[&Position::new(0, 0)] = true;
+        required[&Position::new(2, 1)] = true;
+        // The sunk ship lies across, so it takes out the upright 1x2 ship.
+        let rectangles = vec![
+            Rectangle::new_unchecked(3, 1),
+            Rectangle::new_unchecked(1, 2),
+        ];
+
+        let afloat = remove_sunk(
+            &rect_mask,
+            &rectangles,
+            &[sunk_destroyer()],
+            Some(&required),
+        )
+        .unwrap();
+        assert_eq!(afloat.rectangles, vec![Rectangle::new_unchecked(3, 1)]);
+        assert_eq!(afloat.ids, vec![0]);
+        assert!(afloat.rect_mask[&Position::new(1, 0)]);
+        assert!(!afloat.rect_mask[&Position::new(2, 0)]);
+        // The hit within the sunk ship is explained, the other one still needs covering.
+        let required = afloat.required.unwrap();
+        assert!(!required[&Position::new(0, 0)]);
+        assert!(required[&Position::new(2, 1)]);
+    }
+
+    #[test]
+    fn test_remove_sunk_invalid() {
+        let mut rect_mask = Grid::new(2, 3, false);
+        let fleet = vec![Rectangle::new_unchecked(2, 1)];
+
+        let off_board = SunkShip {
+            position: Position::new(2, 0),
+            ..sunk_destroyer()
+        };
+        let result = remove_sunk(&rect_mask, &fleet, &[off_board], None);
+        assert_eq!(result, Err(SunkError::OutOfBounds { ship: 0 }));
+        // A corner so far out that adding the width would overflow is still off the board.
+        let far_off = SunkShip {
+            position: Position::new(usize::MAX, 0),
+            ..sunk_destroyer()
+        };
+        let result = remove_sunk(&rect_mask, &fleet, &[far_off], None);
+        assert_eq!(result, Err(SunkError::OutOfBounds { ship: 0 }));
+        // So is a ship far too large to list the cells of.
+        let huge = SunkShip {
+            position: Position::new(0, 0),
+            rectangle: Rectangle::new_unchecked(usize::MAX, 1),
+        };
+        let result = remove_sunk(&rect_mask, &fleet, &[huge], None);
+        assert_eq!(result, Err(SunkError::OutOfBounds { ship: 0 }));
+
+        let twice = [sunk_destroyer(), sunk_destroyer()];
+        let result = remove_sunk(&rect_mask, &vec![fleet[0].clone(); 2], &twice, None);
+        assert_eq!(result, Err(SunkError::Overlap { ship: 1 }));
+
+        let elsewhere = SunkShip {
+            position: Position::new(0, 1),
+            ..sunk_destroyer()
+        };
+        let result = remove_sunk(&rect_mask, &fleet, &[sunk_destroyer(), elsewhere], None);
+        assert_eq!(result, Err(SunkError::NotInFleet { ship: 1 }));
+
+        rect_mask[&Position::new(1, 0)] = true;
+        let result = remove_sunk(&rect_mask, &fleet, &[sunk_destroyer()], None);
+        assert_eq!(result, Err(SunkError::Overlap { ship: 0 }));
+    }
+
+    #[test]
+    fn test_estimate_battleship_one_sunk() {
+        // A 3x3 board with a destroyer sunk at the left of the top row. The cruiser afloat lies
+        // along the middle row, the bottom row or the right column, each as likely.
+        let rect_mask = Grid::new(3, 3, false);
+        let fleet = vec![
+            Rectangle::new_unchecked(2, 1),
+            Rectangle::new_unchecked(3, 1),
+        ];
+        let options = EstimateOptions {
+            simulations: 6_000,
+            seed: Some(1),
+            ..Default::default()
+        };
+
+        let estimate =
+            estimate_battleship(&rect_mask, &fleet, &[sunk_destroyer()], &options).unwrap();
+        assert_eq!(estimate.feasibility, 1.0);
+        for x in 0..2 {
+            assert_eq!(estimate.probabilities[&Position::new(x, 0)], 0.0);
+            assert_eq!(estimate.occupants[&Position::new(x, 0)], (0, 0.0));
+        }
+        let expected = [[1.0, 1.0, 2.0], [1.0, 1.0, 2.0]];
+        for (y, row) in expected.iter().enumerate() {
+            for (x, thirds) in row.iter().enumerate() {
+                let p = estimate.probabilities[&Position::new(x, y + 1)];
+                assert!((p - thirds / 3.0).abs() < 0.03, "p = {p}");
+            }
+        }
+        let p = estimate.probabilities[&Position::new(2, 0)];
+        assert!((p - 1.0 / 3.0).abs() < 0.03, "p = {p}");
+        // The cruiser is the second ship of the fleet.
+        assert_eq!(estimate.occupants[&Position::new(2, 1)].0, 2);
+
+        // A hit on the bottom row settles where the cruiser is.
+        let mut required = Grid::new(3, 3, false);
+        required[&Position::new(1, 2)] = true;
+        let options = EstimateOptions {
+            required: Some(required),
+            ..options
+        };
+        let estimate =
+            estimate_battleship(&rect_mask, &fleet, &[sunk_destroyer()], &options).unwrap();
+        assert_eq!(estimate.probabilities[&Position::new(0, 2)], 1.0);
+        assert_eq!(estimate.probabilities[&Position::new(0, 1)], 0.0);
+        assert_eq!(estimate.probabilities[&Position::new(2, 0)], 0.0);
+    }
+}
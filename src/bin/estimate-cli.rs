@@ -0,0 +1,236 @@
+//! Estimates the probabilities of a board from the command line, without the HTTP service.
+//!
+//! The board is read from a file, or from stdin if none is given, in the notation of
+//! [`parse_board`], and the probability grid is printed as text or JSON.
+
+use panel_game::colors::{Color, ColorMap};
+use panel_game::estimator::{DEFAULT_SIMULATIONS, to_checked_shapes};
+use panel_game::notation::parse_board;
+use panel_game::{EstimateOptions, Grid, Position, estimate_with_options};
+use serde::Serialize;
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+usage: estimate-cli [options] [FILE]
+
+Reads a board from FILE, or from stdin if it is omitted: the rows of the mask of `.` (free) and
+`#` (masked) cells, one per line, followed by a line of rectangles such as `2x1,3x1`.
+
+options:
+  --simulations N   the number of simulations to run
+  --seed N          the seed of the simulations, for reproducible estimates
+  --cmap NAME       color the probabilities with a color map, e.g. magma or viridis
+  --json            print the estimate as JSON instead of text
+  --help            print this message";
+
+/// The parsed command line arguments.
+#[derive(PartialEq, Debug)]
+struct Args {
+    /// The file to read the board from, or `None` for stdin.
+    file: Option<PathBuf>,
+    /// The number of simulations to run.
+    simulations: usize,
+    /// The seed of the simulations, if any.
+    seed: Option<u64>,
+    /// The color map to color the probabilities with, if any.
+    cmap: Option<ColorMap>,
+    /// Whether to print JSON instead of text.
+    json: bool,
+}
+
+/// The estimate of a board, as printed with `--json`.
+#[derive(Serialize)]
+struct Output {
+    /// The probability that each cell is covered by a rectangle.
+    probabilities: Grid<f64>,
+    /// The color of each probability, if a color map was given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    colors: Option<Grid<Color>>,
+    /// The fraction of simulations in which every rectangle was placed.
+    feasibility: f64,
+}
+
+/// Parses the command line arguments, without the program name.
+///
+/// # Returns
+///
+/// The arguments, `None` if help was asked for, or a description of the first invalid argument.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut parsed = Args {
+        file: None,
+        simulations: DEFAULT_SIMULATIONS,
+        seed: None,
+        cmap: None,
+        json: false,
+    };
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or(format!("{name} needs a value"));
+        match arg.as_str() {
+            "--help" | "-h" => return Ok(None),
+            "--json" => parsed.json = true,
+            "--simulations" => {
+                parsed.simulations = match value("--simulations")?.parse() {
+                    Ok(simulations) if simulations > 0 => simulations,
+                    _ => return Err("--simulations must be a positive integer".to_string()),
+                }
+            }
+            "--seed" => {
+                let seed = value("--seed")?.parse();
+                parsed.seed = Some(seed.map_err(|_| "--seed must be an unsigned integer")?);
+            }
+            "--cmap" => {
+                let cmap = value("--cmap")?.parse::<ColorMap>();
+                parsed.cmap = Some(cmap.map_err(|error| error.to_string())?);
+            }
+            _ if arg.starts_with('-') && arg != "-" => {
+                return Err(format!("unknown option {arg}"));
+            }
+            _ if parsed.file.is_some() => return Err("only one board may be given".to_string()),
+            // `-` stands for stdin, like an omitted file.
+            _ => parsed.file = (arg != "-").then(|| PathBuf::from(arg)),
+        }
+    }
+    Ok(Some(parsed))
+}
+
+/// Reads the board text from the file of the arguments, or from stdin.
+fn read_board(args: &Args) -> Result<String, String> {
+    match &args.file {
+        Some(file) => std::fs::read_to_string(file)
+            .map_err(|error| format!("cannot read {}: {error}", file.display())),
+        None => {
+            let mut text = String::new();
+            std::io::stdin()
+                .read_to_string(&mut text)
+                .map_err(|error| format!("cannot read stdin: {error}"))?;
+            Ok(text)
+        }
+    }
+}
+
+/// Renders the probabilities like their [`Grid<f64>`] display, on the background of their colors.
+fn to_colored_text(probabilities: &Grid<f64>, colors: &Grid<Color>) -> String {
+    (0..probabilities.rows())
+        .map(|y| {
+            let cells: Vec<String> = (0..probabilities.cols())
+                .map(|x| {
+                    let pos = Position::new(x, y);
+                    let Color(r, g, b) = colors[&pos];
+                    format!("\x1b[48;2;{r};{g};{b}m{:.2}\x1b[0m", probabilities[&pos])
+                })
+                .collect();
+            cells.join(" ")
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Estimates the board of the arguments and renders the estimate.
+///
+/// A board the estimator cannot estimate, e.g. with a rectangle larger than the grid, is an
+/// error, while a board without any layout is estimated with a feasibility of 0.
+fn run(args: &Args) -> Result<String, String> {
+    let (mask, rectangles) = parse_board(&read_board(args)?)?;
+    let options = EstimateOptions {
+        simulations: args.simulations,
+        seed: args.seed,
+        ..Default::default()
+    };
+    let shapes =
+        to_checked_shapes(&mask, &rectangles, &options).map_err(|error| error.to_string())?;
+    let estimate = estimate_with_options(&mask, &shapes, &options);
+    let colors = args.cmap.as_ref().map(|cmap| {
+        estimate
+            .probabilities
+            .to_value_color_pairs(cmap)
+            .map(|(_, color)| color.clone())
+    });
+
+    if args.json {
+        let output = Output {
+            probabilities: estimate.probabilities,
+            colors,
+            feasibility: estimate.feasibility,
+        };
+        return serde_json::to_string(&output).map_err(|error| error.to_string());
+    }
+    Ok(match colors {
+        Some(colors) => to_colored_text(&estimate.probabilities, &colors),
+        None => estimate.probabilities.to_string(),
+    })
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(error) => {
+            eprintln!("estimate-cli: {error}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(&args) {
+        Ok(output) => {
+            println!("{output}");
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("estimate-cli: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Args>, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args = parse(&["--simulations", "500", "--seed", "7", "--cmap", "viridis"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(args.file, None);
+        assert_eq!(args.simulations, 500);
+        assert_eq!(args.seed, Some(7));
+        assert_eq!(args.cmap, Some(ColorMap::Viridis));
+        assert!(!args.json);
+
+        let args = parse(&["board.txt", "--json"]).unwrap().unwrap();
+        assert_eq!(args.file, Some(PathBuf::from("board.txt")));
+        assert_eq!(args.simulations, DEFAULT_SIMULATIONS);
+        assert!(args.json);
+
+        assert_eq!(parse(&["-"]).unwrap().unwrap().file, None);
+        assert_eq!(parse(&["--help"]).unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_args_invalid() {
+        assert!(parse(&["--simulations"]).is_err());
+        assert!(parse(&["--simulations", "0"]).is_err());
+        assert!(parse(&["--seed", "-1"]).is_err());
+        assert!(parse(&["--cmap", "rainbow"]).is_err());
+        assert!(parse(&["--verbose"]).is_err());
+        assert!(parse(&["a.txt", "b.txt"]).is_err());
+    }
+
+    #[test]
+    fn test_to_colored_text() {
+        let probabilities = Grid::from_rows(vec![vec![0.0, 1.0]]).unwrap();
+        let colors = Grid::from_rows(vec![vec![Color(0, 0, 0), Color(255, 128, 0)]]).unwrap();
+        assert_eq!(
+            to_colored_text(&probabilities, &colors),
+            "\x1b[48;2;0;0;0m0.00\x1b[0m \x1b[48;2;255;128;0m1.00\x1b[0m"
+        );
+    }
+}
@@ -0,0 +1,96 @@
+//! The errors of the estimator, shared by every layer up to the HTTP service.
+
+use crate::battleship::SunkError;
+use crate::types::GridError;
+use thiserror::Error;
+
+/// Why a board cannot be estimated.
+#[derive(Error, Debug, PartialEq)]
+pub enum Error {
+    /// The grid has no rows or no columns.
+    #[error("grid must have at least one row and one column")]
+    ZeroDimension,
+    /// The simulations may try no candidate placement of a shape.
+    #[error("max_candidates must be at least 1")]
+    ZeroMaxCandidates,
+    /// A grid that goes with the mask has a different size from it.
+    #[error("{grid} must have the same size as the mask, {}x{}", expected.0, expected.1)]
+    GridDimensionMismatch {
+        /// The grid of the wrong size.
+        grid: BoardGrid,
+        /// The number of rows and columns of the mask.
+        expected: (usize, usize),
+        /// The number of rows and columns of the grid.
+        found: (usize, usize),
+    },
+    /// A rectangle does not fit within the grid in any orientation it may be placed in.
+    #[error("rectangle {rectangle} does not fit within the grid")]
+    OversizedRectangle {
+        /// The index of the rectangle.
+        rectangle: usize,
+    },
+    /// An index refers to a rectangle that does not exist.
+    #[error("rectangle {index} does not exist, there are {rectangles} rectangles")]
+    InvalidRectangleIndex {
+        /// The index.
+        index: usize,
+        /// The number of rectangles.
+        rectangles: usize,
+    },
+    /// No layout of every rectangle consistent with the board was found.
+    #[error("no layout was found that is consistent with the board")]
+    Infeasible,
+    /// The data of a grid is malformed.
+    #[error(transparent)]
+    Grid(#[from] GridError),
+    /// A sunk ship does not fit the board or the fleet.
+    #[error(transparent)]
+    Sunk(#[from] SunkError),
+}
+
+/// A grid that must have the same size as the mask of a board.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BoardGrid {
+    /// The cells required to be covered.
+    Required,
+    /// The placement weights of a rectangle.
+    Weights {
+        /// The index of the rectangle.
+        rectangle: usize,
+    },
+}
+
+impl std::fmt::Display for BoardGrid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BoardGrid::Required => write!(f, "the required cells"),
+            BoardGrid::Weights { rectangle } => write!(f, "the weights of rectangle {rectangle}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_display() {
+        let error = Error::GridDimensionMismatch {
+            grid: BoardGrid::Weights { rectangle: 1 },
+            expected: (2, 3),
+            found: (3, 2),
+        };
+        assert_eq!(
+            error.to_string(),
+            "the weights of rectangle 1 must have the same size as the mask, 2x3"
+        );
+        assert_eq!(
+            Error::from(GridError::Empty).to_string(),
+            GridError::Empty.to_string()
+        );
+        assert_eq!(
+            Error::from(SunkError::NotInFleet { ship: 0 }),
+            Error::Sunk(SunkError::NotInFleet { ship: 0 })
+        );
+    }
+}
@@ -1,332 +1,3938 @@
-use crate::types::{Grid, Position, Rectangle};
+use crate::error::{BoardGrid, Error};
+use crate::types::{Grid, Position, Rectangle, Shape, Symmetry};
 use rand::{prelude::*, rng};
 use rayon::prelude::*;
-use std::sync::{Arc, RwLock};
+use std::cmp::Ordering;
+use std::ops::ControlFlow;
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+// `std::time::Instant` panics on `wasm32-unknown-unknown`, which has no clock of its own.
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
 
-/// The number of simulations to run.
-const SIMULATIONS: usize = 100000;
+/// The default number of simulations to run.
+pub const DEFAULT_SIMULATIONS: usize = 100000;
 
-/// Finds all masked positions in a grid.
+/// The number of simulations run with a single random number generator.
 ///
-/// # Arguments
-///
-/// * `rect_mask` - A grid mask where `true` indicates a unmasked position and `false` indicates an masked position.
+/// Each chunk is seeded independently, so the result of a seeded estimate does not depend on how
+/// rayon schedules the chunks across the workers.
+const SIMULATIONS_PER_CHUNK: usize = 1000;
+
+/// The maximum number of placement checks a single simulation may make while backtracking.
 ///
-/// # Returns
+/// Bounds the cost of a simulation on boards where a layout is hard to find or does not exist at all.
+/// A simulation that exhausts the budget counts as a failed placement.
+const BACKTRACK_BUDGET: usize = 1000;
+
+/// The largest area, that of a 9x9 board, placed with the plain randomized backtracking search.
 ///
-/// A vector of [`Position`]s that are masked in the grid.
-fn find_masked_positions(rect_mask: &Grid<bool>) -> Vec<Position> {
-    let mut positions = Vec::with_capacity(rect_mask.rows() * rect_mask.cols());
-    for y in 0..rect_mask.rows() {
-        for x in 0..rect_mask.cols() {
-            let pos = Position::new(x, y);
-            if !rect_mask[&pos] {
-                positions.push(pos);
-            }
+/// On larger boards, the search prunes every branch whose free cells cannot hold the shapes left,
+/// and may backtrack up to [`LARGE_BACKTRACK_BUDGET`] times, so a layout is still found instead of
+/// most simulations failing.
+const LARGE_GRID_AREA: usize = 81;
+
+/// The maximum number of placement checks a single simulation may make on a board larger than
+/// [`LARGE_GRID_AREA`].
+const LARGE_BACKTRACK_BUDGET: usize = 20_000;
+
+/// The number of chunks run in parallel between two convergence checks.
+const CHUNKS_PER_BATCH: usize = 10;
+
+/// The largest area of a board without any masked cell that [`estimate_probabilities`] enumerates
+/// exactly rather than samples.
+const OPEN_EXACT_MAX_AREA: usize = 16;
+
+/// The default number of search nodes [`estimate_exact`] may visit before giving up.
+pub const DEFAULT_NODE_BUDGET: usize = 1_000_000;
+
+/// The default number of simulations [`sample_layout`] may run before giving up.
+pub const DEFAULT_LAYOUT_ATTEMPTS: usize = 1000;
+
+/// Options for [`estimate_with_options`].
+#[derive(Clone, Debug)]
+pub struct EstimateOptions {
+    /// The number of simulations to run.
+    pub simulations: usize,
+    /// The seed of the random number generator. A random seed is drawn if `None`.
+    pub seed: Option<u64>,
+    /// Stops early once no probability changes by more than this between two batches, if given.
+    ///
+    /// `simulations` is then the maximum number of simulations to run.
+    pub tolerance: Option<f64>,
+    /// Stops after the first batch that ends past this much time since the start, if given.
+    ///
+    /// The estimate is then over the simulations completed by that batch.
+    pub deadline: Option<Duration>,
+    /// A grid where `true` marks a cell known to be covered by some rectangle, if any.
+    ///
+    /// Only layouts covering every required cell are counted. This is independent of the grid mask:
+    /// a cell that is both masked and required can never be covered, so no layout is counted.
+    pub required: Option<Grid<bool>>,
+    /// The most candidate placements a simulation tries for each shape, if given. All of them are
+    /// tried if `None`.
+    ///
+    /// The candidates are drawn a free cell at a time, and the scan stops once this many have
+    /// fit, so each simulation makes a bounded number of searches per shape even on large sparse
+    /// boards. Weighted shapes still list every candidate to draw them by weight. The price is
+    /// bias: a shape that only fits a few of its candidates is more likely to fail, so layouts
+    /// where the earlier shapes leave little room are undercounted, and fewer simulations succeed.
+    ///
+    /// Must be at least 1.
+    pub max_candidates: Option<usize>,
+    /// The indices of the rectangles whose coverage the probabilities count, if given. Every
+    /// rectangle counts if `None`.
+    ///
+    /// The probability of a cell is then that of one of these rectangles covering it, e.g. "only
+    /// the 4-long ship". The layouts are sampled with every rectangle all the same, and the
+    /// occupants and the expected coverage still count every rectangle.
+    pub counted: Option<Vec<usize>>,
+    /// Whether to pair each simulation with its antithetic one, which shifts every uniform draw `u`
+    /// of the first to `u + 1/2` modulo 1, for a lower variance at the same number of simulations.
+    ///
+    /// A shape of the antithetic simulation tries the candidates half the list away from those of
+    /// the first, so the two tend to cover different parts of the board and their errors partly
+    /// cancel. The complement `1 - u` would mirror the layout instead, which the averaging over
+    /// the symmetries of a symmetric board already does. Only the sampled estimates pair
+    /// simulations; [`sample_layouts`] and [`failure_rates`] ignore it.
+    pub antithetic: bool,
+}
+
+impl Default for EstimateOptions {
+    fn default() -> Self {
+        EstimateOptions {
+            simulations: DEFAULT_SIMULATIONS,
+            seed: None,
+            tolerance: None,
+            deadline: None,
+            required: None,
+            max_candidates: None,
+            counted: None,
+            antithetic: false,
         }
     }
-    positions
 }
 
-/// Filters out positions that are not valid for placing a rectangle within a grid.
-///
-/// A position is not valid if the rectangle would extend outside the grid.
-/// The rectangle is allowed to rotate.
+/// How an estimate was computed.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct EstimateStats {
+    /// The number of simulations run, or 0 for an exact estimate or one that needed no simulations.
+    pub simulations_run: usize,
+    /// The number of simulations in which every rectangle was placed, or the number of layouts of
+    /// an exact estimate.
+    pub feasible_samples: usize,
+    /// The time the estimate took.
+    pub elapsed: Duration,
+}
+
+/// The result of an estimate.
+#[derive(Clone)]
+pub struct Estimate {
+    /// The probability that each cell is covered by a rectangle.
+    pub probabilities: Grid<f64>,
+    /// The standard error of each probability.
+    pub std_error: Grid<f64>,
+    /// The most likely rectangle to cover each cell, and the probability that it does.
+    ///
+    /// Rectangles are identified by their 1-based index in the given rectangles, and 0 marks a cell
+    /// that no rectangle ever covered.
+    pub occupants: Grid<(usize, f64)>,
+    /// The expected number of rectangles covering each cell.
+    ///
+    /// Counts every rectangle covering a cell rather than whether any does, so it would exceed the
+    /// probability where rectangles could overlap. As the rectangles of a layout never overlap, it
+    /// equals [`probabilities`](Estimate::probabilities) for now.
+    pub expected_coverage: Grid<f64>,
+    /// The fraction of simulations in which every rectangle was placed.
+    pub feasibility: f64,
+    /// How the estimate was computed.
+    pub stats: EstimateStats,
+}
+
+/// Finds all free positions in a grid.
 ///
 /// # Arguments
 ///
-/// * `positions` - The positions to filter.
-/// * `rect` - The rectangle to place.
-/// * `grid_size` - The size of the grid.
+/// * `rect_mask` - A grid mask where `true` indicates a blocked position and `false` indicates a free position.
+/// * `positions` - The buffer to write the free [`Position`]s of the grid into, in row-major order. Its previous contents are discarded.
+fn find_free_positions(rect_mask: &Grid<bool>, positions: &mut Vec<Position>) {
+    positions.clear();
+    positions.extend(
+        rect_mask
+            .iter_positions()
+            .filter(|&(_, &blocked)| !blocked)
+            .map(|(pos, _)| pos),
+    );
+}
+
+/// Lists the placements of a shape anchored at the given positions that keep it within a grid.
 ///
-/// # Returns
+/// A placement is the position of the top-left corner of the bounding box of an orientation,
+/// paired with the index of the orientation. It is anchored at the position of its first cell, so
+/// a placement whose first cell is not among `positions` is left out, and none is listed twice.
+///
+/// The scan is sequential, like that of [`filter_fitting`]. The simulations already keep every
+/// rayon worker busy with a chunk of their own, so scanning a single shape in parallel has no idle
+/// worker to hand the work to and only pays for the splitting.
 ///
-/// A vector of positions that are valid for placing the rectangle within the grid.
+/// # Arguments
+///
+/// * `positions` - The positions the first cell of the shape may take.
+/// * `orientations` - The orientations of the shape to place.
+/// * `grid_size` - The size of the grid.
+/// * `placements` - The buffer to write the placements into. Its previous contents are discarded.
 fn filter_positions(
-    positions: Vec<Position>,
-    rect: &Rectangle,
+    positions: &[Position],
+    orientations: &[Shape],
     grid_size: (usize, usize),
-) -> Vec<Position> {
-    positions
-        .into_iter()
-        .filter(|pos| {
-            (pos.x() + rect.width() <= grid_size.0 && pos.y() + rect.height() <= grid_size.1)
-                || (pos.x() + rect.height() <= grid_size.0 && pos.y() + rect.width() <= grid_size.1)
+    placements: &mut Vec<(Position, usize)>,
+) {
+    placements.clear();
+    placements.extend(
+        positions
+            .iter()
+            .flat_map(|pos| anchored(pos, orientations, grid_size)),
+    );
+}
+
+/// Lists the placements of a shape whose first cell is at `pos` that keep it within a grid.
+fn anchored(
+    pos: &Position,
+    orientations: &[Shape],
+    grid_size: (usize, usize),
+) -> impl Iterator<Item = (Position, usize)> {
+    let (anchor_x, anchor_y) = (pos.x(), pos.y());
+    orientations
+        .iter()
+        .enumerate()
+        .filter_map(move |(orientation, shape)| {
+            let first = &shape.cells()[0];
+            let x = anchor_x.checked_sub(first.x())?;
+            let y = anchor_y.checked_sub(first.y())?;
+            (x + shape.width() <= grid_size.0 && y + shape.height() <= grid_size.1)
+                .then(|| (Position::new(x, y), orientation))
         })
-        .collect()
 }
 
-/// Places rectangles within a grid.
+/// Lists the placements that still fit the free cells of a grid, in order.
 ///
 /// # Arguments
 ///
-/// * `rect_mask` - A grid mask.
-/// * `rectangles` - The rectangles to be placed.
+/// * `rect_mask` - A grid mask where `true` indicates a blocked position and `false` indicates a free position.
+/// * `previous` - The placements to check.
+/// * `orientations` - The orientations of the shape the placements refer to by index.
+/// * `placements` - The buffer to write the fitting placements into. Its previous contents are discarded.
+fn filter_fitting(
+    rect_mask: &Grid<bool>,
+    previous: &[(Position, usize)],
+    orientations: &[Shape],
+    placements: &mut Vec<(Position, usize)>,
+) {
+    placements.clear();
+    let fits = |(pos, orientation): &&(Position, usize)| {
+        rect_mask.all(pos, &orientations[*orientation], &false)
+    };
+    placements.extend(previous.iter().filter(fits).cloned());
+}
+
+/// Finds the cells that some shape covers in some placement fitting the free cells of a grid.
+///
+/// Every other free cell is dead: no layout ever covers it, so its probability is 0 and the
+/// simulations may treat it as blocked.
+///
+/// # Arguments
+///
+/// * `rect_mask` - A grid mask where `true` indicates a blocked position and `false` indicates a free position.
+/// * `orientations` - The orientations of each shape to be placed, as listed by [`to_orientations`].
 ///
 /// # Returns
 ///
-/// If all the rectangles were placed, returns a grid of the placed rectangles (0 for empty and rect_id for the rectangle).
-/// Otherwise, returns None.
-fn place_rectangles(
-    mut rect_mask: Grid<bool>,
-    mut rectangles: Vec<Rectangle>,
-) -> Option<Grid<usize>> {
-    let mut positions = Grid::new(rect_mask.rows(), rect_mask.cols(), 0);
-    let mut rng = rng();
-
-    for (rect_idx, rect) in rectangles.iter_mut().enumerate() {
-        let mut placed = false;
-
-        // Find the positions where the rectangle may be placed.
-        let unmasked_positions = find_masked_positions(&rect_mask);
-        let mut filtered_positions = filter_positions(
-            unmasked_positions,
-            rect,
-            (rect_mask.cols(), rect_mask.rows()),
-        );
-        if filtered_positions.is_empty() {
-            return None;
-        }
-        filtered_positions.shuffle(&mut rng);
-
-        // Try to place the rectangle at each position.
-        for sample_pos in &filtered_positions {
-            // Try to place the rectangle in two rotations.
-            for _ in 0..2 {
-                let y = sample_pos.y();
-                let x = sample_pos.x();
-
-                if rect_mask.all(&Position::new(x, y), rect, &false) {
-                    for i in 0..rect.height() {
-                        for j in 0..rect.width() {
-                            let pos = Position::new(x + j, y + i);
-                            rect_mask[&pos] = true;
-                            positions[&pos] = rect_idx + 1;
-                        }
+/// A grid where `true` marks a cell that can be covered.
+fn to_reachable(rect_mask: &Grid<bool>, orientations: &[Vec<Shape>]) -> Grid<bool> {
+    let mut reachable = Grid::new(rect_mask.rows(), rect_mask.cols(), false);
+    let mut free = vec![];
+    let mut placements = vec![];
+    find_free_positions(rect_mask, &mut free);
+    let grid_size = (rect_mask.cols(), rect_mask.rows());
+    for shapes in orientations {
+        filter_positions(&free, shapes, grid_size, &mut placements);
+        for (pos, orientation) in &placements {
+            let shape = &shapes[*orientation];
+            if rect_mask.all(pos, shape, &false) {
+                for cell in shape.cells() {
+                    if let Some(pos) = pos.offset(cell.x() as isize, cell.y() as isize) {
+                        reachable[&pos] = true;
                     }
-                    placed = true;
+                }
+            }
+        }
+    }
+    reachable
+}
+
+/// Blocks the dead cells of a grid mask, those [`to_reachable`] finds no shape can cover, so the
+/// simulations neither list placements anchored at them nor count them.
+fn block_unreachable(rect_mask: &Grid<bool>, reachable: &Grid<bool>) -> Grid<bool> {
+    let mut pruned = rect_mask.clone();
+    for (pos, blocked) in pruned.iter_positions_mut() {
+        *blocked |= !reachable[&pos];
+    }
+    pruned
+}
+
+/// Converts pieces such as [`Rectangle`](crate::types::Rectangle)s into [`Shape`]s.
+fn to_shapes<P: Clone + Into<Shape>>(pieces: &[P]) -> Vec<Shape> {
+    pieces.iter().cloned().map(Into::into).collect()
+}
+
+/// Lists the distinct orientations of each shape, to be computed once per estimate.
+fn to_orientations(shapes: &[Shape]) -> Vec<Vec<Shape>> {
+    shapes.iter().map(Shape::orientations).collect()
+}
+
+/// Reusable buffers for running simulations on one worker.
+///
+/// Allocated once per rayon worker and reset between simulations, so a simulation does not allocate.
+struct Scratch {
+    /// The grid mask, updated as rectangles are placed.
+    rect_mask: Grid<bool>,
+    /// The grid of placed rectangles (0 for empty and rect_id for the rectangle).
+    positions: Grid<usize>,
+    /// The free positions of the grid mask, refreshed before listing the candidates of a depth.
+    free: Vec<Position>,
+    /// The candidate placements (position and orientation) of the shape at each depth of the search.
+    candidates: Vec<Vec<(Position, usize)>>,
+    /// The free positions at each depth of a capped search, drawn from one at a time.
+    anchors: Vec<Vec<Position>>,
+    /// The weight of each candidate placement at each depth, empty for unweighted shapes.
+    weights: Vec<Vec<f64>>,
+    /// Whether the shape at each depth is identical to the one before it, so it takes its
+    /// candidates from the previous depth instead of listing them anew.
+    repeats: Vec<bool>,
+    /// The most candidate placements tried for each shape of a random simulation, all if `None`.
+    max_candidates: Option<usize>,
+    /// The deepest shape the search reached since the last reset, the one it failed to place if
+    /// it found no layout.
+    deepest: usize,
+}
+
+impl Scratch {
+    /// Creates buffers for simulations on the given grid mask.
+    fn new(rect_mask: &Grid<bool>) -> Scratch {
+        Scratch {
+            rect_mask: rect_mask.clone(),
+            positions: Grid::new(rect_mask.rows(), rect_mask.cols(), 0),
+            free: vec![],
+            candidates: vec![],
+            anchors: vec![],
+            weights: vec![],
+            repeats: vec![],
+            max_candidates: None,
+            deepest: 0,
+        }
+    }
+
+    /// Limits the candidate placements tried for each shape of a random simulation, if given.
+    fn with_max_candidates(mut self, max_candidates: Option<usize>) -> Scratch {
+        self.max_candidates = max_candidates;
+        self
+    }
+
+    /// Resets the buffers for placing shapes of the given orientations on a grid mask.
+    fn reset(&mut self, rect_mask: &Grid<bool>, orientations: &[Vec<Shape>]) {
+        self.rect_mask.clone_from(rect_mask);
+        self.positions.fill(0);
+        self.deepest = 0;
+        self.candidates.resize_with(orientations.len(), Vec::new);
+        self.anchors.resize_with(orientations.len(), Vec::new);
+        self.weights.resize_with(orientations.len(), Vec::new);
+        self.repeats.clear();
+        self.repeats.extend(
+            (0..orientations.len()).map(|i| i > 0 && orientations[i] == orientations[i - 1]),
+        );
+    }
+}
+
+/// The counts accumulated over a set of simulations.
+struct Tally {
+    /// The number of successful simulations in which each cell was covered.
+    hits: Grid<f64>,
+    /// The number of successful simulations in which each rectangle covered each cell.
+    ///
+    /// Indexed by `(y * cols + x) * rectangles + rect_idx`.
+    occupants: Vec<f64>,
+    /// The number of rectangles placed in each simulation.
+    rectangles: usize,
+    /// The number of successful simulations.
+    placed: usize,
+}
+
+impl Tally {
+    /// Creates an empty [`Tally`] for a grid of the given size and number of rectangles.
+    fn new(rows: usize, cols: usize, rectangles: usize) -> Tally {
+        Tally {
+            hits: Grid::new(rows, cols, 0.0),
+            occupants: vec![0.0; rows * cols * rectangles],
+            rectangles,
+            placed: 0,
+        }
+    }
+
+    /// Records a successful simulation given its grid of placed rectangles.
+    fn record(&mut self, positions: &Grid<usize>) {
+        self.placed += 1;
+        for (cell, (pos, &rect_id)) in positions.iter_positions().enumerate() {
+            if rect_id > 0 {
+                self.hits[&pos] += 1.0;
+                self.occupants[cell * self.rectangles + rect_id - 1] += 1.0;
+            }
+        }
+    }
+
+    /// Records a successful simulation given its grid of placed rectangles, looking only at the
+    /// given cells, as every other cell is known to be empty.
+    fn record_cells(&mut self, positions: &Grid<usize>, cells: &[Position]) {
+        self.placed += 1;
+        let cols = positions.cols();
+        for pos in cells {
+            let rect_id = positions[pos];
+            if rect_id > 0 {
+                self.hits[pos] += 1.0;
+                self.occupants[(pos.y() * cols + pos.x()) * self.rectangles + rect_id - 1] += 1.0;
+            }
+        }
+    }
 
-                    break;
+    /// Returns the probability that each cell is covered, given the successful simulations so far.
+    ///
+    /// Without any successful simulation, no cell is ever covered, so every probability is 0.
+    fn probabilities(&self) -> Grid<f64> {
+        if self.placed == 0 {
+            self.hits.clone()
+        } else {
+            self.hits.clone() / self.placed as f64
+        }
+    }
+
+    /// Converts this [`Tally`] of `simulations` simulations, run since `start`, into an [`Estimate`].
+    ///
+    /// `ids` maps the index of each placed rectangle to its index in the caller's rectangles, and
+    /// the counts are averaged over the `symmetries` of the board, as by [`apply_symmetry`]. The
+    /// probabilities only count the coverage of the `counted` rectangles, if given, as by
+    /// [`EstimateOptions::counted`].
+    fn to_estimate(
+        &self,
+        simulations: usize,
+        start: Instant,
+        ids: &[usize],
+        symmetries: &[Symmetry],
+        counted: Option<&[usize]>,
+    ) -> Estimate {
+        let feasibility = if self.placed == 0 {
+            0.0
+        } else {
+            self.placed as f64 / simulations as f64
+        };
+        let grid_size = (self.hits.rows(), self.hits.cols());
+        let counts = self.symmetric_occupants(symmetries);
+        let probabilities = match counted {
+            Some(counted) => to_coverage_of(&counts, self.placed, ids, counted, grid_size),
+            None => apply_symmetry(&self.probabilities(), symmetries),
+        };
+        let std_error = to_std_error(&probabilities, self.placed);
+        Estimate {
+            probabilities,
+            std_error,
+            occupants: to_occupants(&counts, self.placed, ids, grid_size),
+            expected_coverage: to_expected_coverage(&counts, self.placed, ids.len(), grid_size),
+            feasibility,
+            stats: EstimateStats {
+                simulations_run: simulations,
+                feasible_samples: self.placed,
+                elapsed: start.elapsed(),
+            },
+        }
+    }
+
+    /// Returns the counts of each rectangle covering each cell, averaged over the `symmetries`.
+    fn symmetric_occupants(&self, symmetries: &[Symmetry]) -> Vec<f64> {
+        if symmetries.is_empty() || self.rectangles == 0 {
+            return self.occupants.clone();
+        }
+        let (rows, cols) = (self.hits.rows(), self.hits.cols());
+        let orbit = (symmetries.len() + 1) as f64;
+        let mut occupants = self.occupants.clone();
+        for (cell, counts) in occupants.chunks_mut(self.rectangles).enumerate() {
+            let pos = Position::new(cell % cols, cell / cols);
+            for symmetry in symmetries {
+                let image = symmetry.apply(&pos, cols, rows);
+                let image = (image.y() * cols + image.x()) * self.rectangles;
+                for (count, other) in counts.iter_mut().zip(&self.occupants[image..]) {
+                    *count += other;
                 }
-                rect.transpose();
             }
-            if placed {
-                break;
+            for count in counts {
+                *count /= orbit;
             }
         }
-        if !placed {
-            return None;
+        occupants
+    }
+
+    /// Merges the counts of two tallies.
+    fn merge(mut self, other: Tally) -> Tally {
+        self.placed += other.placed;
+        for (count, other) in self.occupants.iter_mut().zip(&other.occupants) {
+            *count += other;
         }
+        for (pos, hits) in self.hits.iter_positions_mut() {
+            *hits += other.hits[&pos];
+        }
+        self
     }
-    Some(positions)
 }
 
-/// Estimates the probabilities of a grid from its rectangles.
+/// Places shapes within a grid.
+///
+/// Each shape is tried at its candidate positions in random order. When a shape cannot be placed,
+/// the search backtracks and tries alternative positions for the earlier shapes, so a layout is
+/// found whenever one exists and the [`BACKTRACK_BUDGET`] is not exhausted. Boards larger than
+/// [`LARGE_GRID_AREA`] are searched with pruning and the [`LARGE_BACKTRACK_BUDGET`] instead.
 ///
 /// # Arguments
 ///
-/// * `rect_mask` - A grid mask of rectangles.
-/// * `rectangles` - The rectangles to be placed.
-pub fn estimate_probabilities(rect_mask: &Grid<bool>, rectangles: &[Rectangle]) -> Grid<f64> {
-    let prob_matrix = Arc::new(RwLock::new(Grid::new(
-        rect_mask.rows(),
-        rect_mask.cols(),
-        0.0,
-    )));
-    let all_placed_count = Arc::new(RwLock::new(0));
-
-    // Sort the rectangles by area in descending order.
-    let mut rectangles = rectangles.to_owned();
-    rectangles.sort_by_key(|b| std::cmp::Reverse(b.area()));
-
-    // Run the simulation in parallel.
-    (0..SIMULATIONS).into_par_iter().for_each(|_| {
-        let result = place_rectangles(rect_mask.clone(), rectangles.clone());
-
-        if let Some(result) = &result {
-            *all_placed_count.write().unwrap() += 1;
-            let mut matrix = prob_matrix.write().unwrap();
-
-            for i in 0..rect_mask.rows() {
-                for j in 0..rect_mask.cols() {
-                    let pos = Position::new(j, i);
-                    matrix[&pos] += if result[&pos] > 0 { 1.0 } else { 0.0 };
-                }
-            }
-        }
-    });
+/// * `rect_mask` - A grid mask.
+/// * `orientations` - The orientations of each shape to be placed, as listed by [`to_orientations`].
+/// * `required` - A grid where `true` marks a cell that must be covered, if any.
+/// * `scratch` - The buffers to run the simulation in.
+/// * `rng` - The random number generator used to shuffle the candidate positions.
+///
+/// # Returns
+///
+/// If all the shapes were placed covering every required cell, returns a grid of the placed shapes (0 for empty and rect_id for the shape).
+/// Otherwise, returns None.
+fn place_rectangles<'a, R: Rng + ?Sized>(
+    rect_mask: &Grid<bool>,
+    orientations: &[Vec<Shape>],
+    required: Option<&Grid<bool>>,
+    scratch: &'a mut Scratch,
+    rng: &mut R,
+) -> Option<&'a Grid<usize>> {
+    scratch.reset(rect_mask, orientations);
+    let mut budget = if is_large(rect_mask) {
+        LARGE_BACKTRACK_BUDGET
+    } else {
+        BACKTRACK_BUDGET
+    };
 
-    let all_placed_count: i64 = *all_placed_count.read().unwrap();
-    prob_matrix.read().unwrap().clone() / (all_placed_count as f64 + f64::EPSILON)
+    if place_from(
+        scratch,
+        orientations,
+        required,
+        0,
+        &[],
+        Some(rng),
+        &mut budget,
+    ) {
+        Some(&scratch.positions)
+    } else {
+        None
+    }
 }
 
-/// Computes the entropy of a grid from its probabilities.
+/// Places shapes within a grid deterministically, to tell whether any layout exists.
+///
+/// Like [`place_rectangles`], but each shape is tried at its candidate positions in row-major order
+/// of their first cell, without a random number generator and ignoring any weights. The first
+/// layout found is therefore always the same, and if none is found before the budget runs out, no
+/// layout exists.
 ///
 /// # Arguments
 ///
-/// * `probabilities` - A grid of probabilities.
+/// * `rect_mask` - A grid mask.
+/// * `orientations` - The orientations of each shape to be placed, as listed by [`to_orientations`].
+/// * `required` - A grid where `true` marks a cell that must be covered, if any.
+/// * `scratch` - The buffers to run the search in.
+/// * `budget` - The number of placement checks allowed, decreased by the checks made.
 ///
 /// # Returns
 ///
-/// A grid of entropies computed from the probabilities element-wise.
-pub fn to_entropy(probabilities: &Grid<f64>) -> Grid<f64> {
-    let mut entropy = Grid::new(probabilities.rows(), probabilities.cols(), 0.0);
-    for i in 0..probabilities.rows() {
-        for j in 0..probabilities.cols() {
-            let pos = Position::new(j, i);
-            let p = probabilities[&pos];
-            entropy[&pos] = (-p * (p + f64::EPSILON).log2()
-                - (1.0 - p) * (1.0 - p + f64::EPSILON).log2())
-            .clamp(0.0, 1.0);
-        }
+/// If all the shapes were placed covering every required cell, returns a grid of the placed shapes (0 for empty and rect_id for the shape).
+/// Otherwise, returns None: no layout exists if `budget` is left nonzero, and the search gave up
+/// if it reached zero.
+fn place_rectangles_first_fit<'a>(
+    rect_mask: &Grid<bool>,
+    orientations: &[Vec<Shape>],
+    required: Option<&Grid<bool>>,
+    scratch: &'a mut Scratch,
+    budget: &mut usize,
+) -> Option<&'a Grid<usize>> {
+    scratch.reset(rect_mask, orientations);
+
+    if place_from::<StdRng>(scratch, orientations, required, 0, &[], None, budget) {
+        Some(&scratch.positions)
+    } else {
+        None
     }
-    entropy
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Places the shapes from `rect_idx` onwards, backtracking when a shape cannot be placed.
+///
+/// # Arguments
+///
+/// * `scratch` - The buffers to run the simulation in, updated as shapes are placed.
+/// * `orientations` - The orientations of each shape to be placed.
+/// * `required` - A grid where `true` marks a cell that must be covered, if any.
+/// * `rect_idx` - The index of the first shape to place.
+/// * `previous` - The candidate placements of the shape before it, reused if the shapes are
+///   identical.
+/// * `rng` - The random number generator used to shuffle the candidate positions, or `None` to
+///   try them in order.
+/// * `budget` - The number of placement checks left.
+///
+/// # Returns
+///
+/// `true` if all the remaining shapes were placed and every required cell is covered.
+/// Otherwise, `false`, and the grids are left as they were.
+fn place_from<R: Rng + ?Sized>(
+    scratch: &mut Scratch,
+    orientations: &[Vec<Shape>],
+    required: Option<&Grid<bool>>,
+    rect_idx: usize,
+    previous: &[(Position, usize)],
+    mut rng: Option<&mut R>,
+    budget: &mut usize,
+) -> bool {
+    scratch.deepest = scratch.deepest.max(rect_idx);
+    let Some(shapes) = orientations.get(rect_idx) else {
+        // A complete layout that misses a required cell is rejected, so the search backtracks.
+        return required.is_none_or(|required| covers_required(&scratch.positions, required));
+    };
+    // A random simulation capped to fewer candidates draws them from the free cells as it goes. A
+    // weighted shape lists them all instead, as drawing by weight needs every weight.
+    let capped = match (&rng, scratch.max_candidates) {
+        (Some(_), Some(max_candidates)) if shapes[0].weights().is_none() => Some(max_candidates),
+        _ => None,
+    };
+    // The capped candidates of the previous shape are too few to draw a copy of it from.
+    let repeat = scratch.repeats[rect_idx] && capped.is_none();
+    let large = is_large(&scratch.rect_mask);
+    if !repeat || large {
+        find_free_positions(&scratch.rect_mask, &mut scratch.free);
+    }
+    // On a large board, give up on a branch as soon as the shapes left outnumber the free cells.
+    if large && shapes_area(&orientations[rect_idx..]) > scratch.free.len() {
+        return false;
+    }
+    // Find the placements of the shape, reusing this depth's buffer. A copy of the previous shape
+    // keeps those of its placements that still fit: listing every free cell again would only add
+    // placements that fail, and the fitting ones are still tried in a uniformly random order.
+    if let (Some(max_candidates), Some(rng)) = (capped, rng.as_deref_mut()) {
+        return place_sampled(
+            scratch,
+            orientations,
+            required,
+            rect_idx,
+            max_candidates,
+            rng,
+            budget,
+        );
+    }
+    let mut candidates = std::mem::take(&mut scratch.candidates[rect_idx]);
+    if repeat {
+        filter_fitting(&scratch.rect_mask, previous, shapes, &mut candidates);
+    } else {
+        filter_positions(
+            &scratch.free,
+            shapes,
+            (scratch.rect_mask.cols(), scratch.rect_mask.rows()),
+            &mut candidates,
+        );
+    }
+    let mut weights = std::mem::take(&mut scratch.weights[rect_idx]);
+    weights.clear();
+    if let (Some(shape_weights), Some(_)) = (shapes[0].weights(), &rng) {
+        weights.extend(
+            candidates.iter().map(|(pos, orientation)| {
+                shape_weights.placement_weight(pos, &shapes[*orientation])
+            }),
+        );
+    }
 
-    #[test]
-    fn test_place_rectangles_all_placed() {
-        let rect_mask = Grid::new(5, 9, false);
-        let rectangles = vec![
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-        ];
-        let result = place_rectangles(rect_mask, rectangles.clone());
-        assert!(result.is_some());
-        let result = result.unwrap();
-        let mut count = 0;
-        for y in 0..result.rows() {
-            for x in 0..result.cols() {
-                let pos = Position::new(x, y);
-                if result[&pos] > 0 {
-                    count += 1;
-                }
-            }
+    // Try the placements in random order. Drawing the next one as it is needed shuffles the
+    // positions and orientations together without paying for the placements never tried.
+    // Weighted shapes draw each placement with a probability proportional to its weight, and
+    // never try the placements of weight 0. A random weighted simulation capped to fewer
+    // candidates tries the first ones drawn, a random subset of them.
+    let mut placed = false;
+    let count = candidates.len();
+    let tried = match (&rng, scratch.max_candidates) {
+        (Some(_), Some(max_candidates)) => count.min(max_candidates),
+        _ => count,
+    };
+    for i in 0..tried {
+        if *budget == 0 {
+            break;
+        }
+        *budget -= 1;
+
+        let next = match rng.as_deref_mut() {
+            None => i,
+            Some(rng) if weights.is_empty() => rng.random_range(i..count),
+            Some(rng) => match draw_weighted(&weights[i..], rng) {
+                Some(offset) => i + offset,
+                None => break,
+            },
+        };
+        candidates.swap(i, next);
+        if !weights.is_empty() {
+            weights.swap(i, next);
+        }
+        let (sample_pos, orientation) = &candidates[i];
+        let shape = &shapes[*orientation];
+        if scratch.rect_mask.all(sample_pos, shape, &false)
+            && place_at(
+                scratch,
+                orientations,
+                required,
+                rect_idx,
+                sample_pos,
+                shape,
+                &candidates,
+                rng.as_deref_mut(),
+                budget,
+            )
+        {
+            placed = true;
+            break;
         }
-        assert!(count == rectangles.len());
     }
+    scratch.candidates[rect_idx] = candidates;
+    scratch.weights[rect_idx] = weights;
+    placed
+}
 
-    #[test]
-    fn test_place_rectangles_no_placed() {
-        let mut rect_mask = Grid::new(5, 9, false);
-        for y in 0..rect_mask.rows() {
-            for x in 0..rect_mask.cols() {
-                let pos = Position::new(x, y);
-                rect_mask[&pos] = true;
+/// Places the shape at `rect_idx` of a random simulation capped to `max_candidates`, then the
+/// shapes after it, as [`place_from`] does.
+///
+/// Rather than listing every placement and drawing from them, this draws a free position at a
+/// time and tries the placements anchored at it in random order. The scan stops as soon as one
+/// leads to a layout or `max_candidates` of them have fit, so a shape costs at most that many
+/// searches on a board of any size, instead of a pass over all of its free cells.
+///
+/// # Arguments
+///
+/// * `scratch` - The buffers to run the simulation in, with the free positions of the grid mask.
+/// * `orientations` - The orientations of each shape to be placed.
+/// * `required` - A grid where `true` marks a cell that must be covered, if any.
+/// * `rect_idx` - The index of the shape to place.
+/// * `max_candidates` - The most fitting placements to try.
+/// * `rng` - The random number generator used to draw the placements.
+/// * `budget` - The number of placement checks left.
+///
+/// # Returns
+///
+/// `true` if all the remaining shapes were placed and every required cell is covered.
+/// Otherwise, `false`, and the grids are left as they were.
+fn place_sampled<R: Rng + ?Sized>(
+    scratch: &mut Scratch,
+    orientations: &[Vec<Shape>],
+    required: Option<&Grid<bool>>,
+    rect_idx: usize,
+    max_candidates: usize,
+    rng: &mut R,
+    budget: &mut usize,
+) -> bool {
+    let shapes = &orientations[rect_idx];
+    let grid_size = (scratch.rect_mask.cols(), scratch.rect_mask.rows());
+    let mut anchors = std::mem::take(&mut scratch.anchors[rect_idx]);
+    anchors.clone_from(&scratch.free);
+    // The placements anchored at the last position drawn that are left to try.
+    let mut candidates = std::mem::take(&mut scratch.candidates[rect_idx]);
+    candidates.clear();
+
+    let mut placed = false;
+    let mut drawn = 0;
+    let mut fitting = 0;
+    while fitting < max_candidates && *budget > 0 {
+        if candidates.is_empty() {
+            if drawn == anchors.len() {
+                break;
             }
+            let next = rng.random_range(drawn..anchors.len());
+            anchors.swap(drawn, next);
+            candidates.extend(anchored(&anchors[drawn], shapes, grid_size));
+            drawn += 1;
+            continue;
+        }
+        *budget -= 1;
+
+        let (sample_pos, orientation) =
+            candidates.swap_remove(rng.random_range(0..candidates.len()));
+        let shape = &shapes[orientation];
+        if !scratch.rect_mask.all(&sample_pos, shape, &false) {
+            continue;
+        }
+        fitting += 1;
+        // A capped copy of this shape draws its own placements, so it takes none from here.
+        if place_at(
+            scratch,
+            orientations,
+            required,
+            rect_idx,
+            &sample_pos,
+            shape,
+            &[],
+            Some(&mut *rng),
+            budget,
+        ) {
+            placed = true;
+            break;
         }
-        let rectangles = vec![
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-            Rectangle::new(1, 1),
-        ];
-        let result = place_rectangles(rect_mask, rectangles);
-        assert!(result.is_none());
     }
+    scratch.anchors[rect_idx] = anchors;
+    scratch.candidates[rect_idx] = candidates;
+    placed
+}
 
-    #[test]
-    fn test_estimate_probabilities() {
-        let mut rect_mask = Grid::new(5, 9, false);
-        rect_mask[&Position::new(4, 2)] = true;
-        rect_mask[&Position::new(1, 0)] = true;
-        rect_mask[&Position::new(8, 4)] = true;
-        rect_mask[&Position::new(6, 1)] = true;
-        rect_mask[&Position::new(2, 3)] = true;
-        rect_mask[&Position::new(2, 4)] = true;
-        rect_mask[&Position::new(3, 4)] = true;
-        rect_mask[&Position::new(4, 4)] = true;
-        rect_mask[&Position::new(5, 4)] = true;
-        rect_mask[&Position::new(3, 2)] = true;
+/// Places the shape at `rect_idx` at a placement that fits, then the shapes after it, removing it
+/// again if they cannot all be placed.
+///
+/// `candidates` are the placements of the shape, passed on to an identical shape after it.
+#[allow(clippy::too_many_arguments)]
+fn place_at<R: Rng + ?Sized>(
+    scratch: &mut Scratch,
+    orientations: &[Vec<Shape>],
+    required: Option<&Grid<bool>>,
+    rect_idx: usize,
+    pos: &Position,
+    shape: &Shape,
+    candidates: &[(Position, usize)],
+    rng: Option<&mut R>,
+    budget: &mut usize,
+) -> bool {
+    mark_shape(
+        &mut scratch.rect_mask,
+        &mut scratch.positions,
+        pos,
+        shape,
+        rect_idx + 1,
+    );
+    if place_from(
+        scratch,
+        orientations,
+        required,
+        rect_idx + 1,
+        candidates,
+        rng,
+        budget,
+    ) {
+        return true;
+    }
+    // Backtrack so the caller can try the next candidate.
+    mark_shape(
+        &mut scratch.rect_mask,
+        &mut scratch.positions,
+        pos,
+        shape,
+        0,
+    );
+    false
+}
 
-        let rectangles = vec![
-            Rectangle::new(2, 1),
-            Rectangle::new(2, 1),
-            Rectangle::new(2, 1),
-            Rectangle::new(2, 1),
-            Rectangle::new(2, 1),
-            Rectangle::new(2, 1),
-            Rectangle::new(3, 1),
-            Rectangle::new(3, 1),
-            Rectangle::new(3, 1),
-            Rectangle::new(3, 1),
-            Rectangle::new(4, 1),
-            Rectangle::new(4, 1),
-        ];
-        let probabilities = estimate_probabilities(&rect_mask, &rectangles);
-        assert_eq!(probabilities.rows(), rect_mask.rows());
-        assert_eq!(probabilities.cols(), rect_mask.cols());
-        // Check if all values are between 0 and 1
-        for y in 0..rect_mask.rows() {
-            for x in 0..rect_mask.cols() {
-                let pos = Position::new(x, y);
-                assert!(probabilities[&pos] >= 0.0 && probabilities[&pos] <= 1.0);
-            }
+/// Derives the seed of a chunk of simulations from the seed of the estimate.
+///
+/// Both are scrambled with SplitMix64, so estimates whose seeds are close, e.g. `1` and `2`, do not
+/// share the streams of their chunks as they would with `seed + chunk`.
+fn chunk_seed(seed: u64, chunk: usize) -> u64 {
+    splitmix64(splitmix64(seed) ^ chunk as u64)
+}
+
+/// Scrambles every bit of a value, as the SplitMix64 generator does to each of its outputs.
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// A random number generator drawing the bits of another with the top bit flipped, so each
+/// uniform draw `u` of the other becomes `u + 1/2` modulo 1, for antithetic simulations.
+struct Antithetic<R>(R);
+
+impl<R: RngCore> RngCore for Antithetic<R> {
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32() ^ (1 << 31)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64() ^ (1 << 63)
+    }
+
+    /// Fills `dst` with the little-endian bytes of successive [`RngCore::next_u64`] draws, so the
+    /// top bit of each word drawn is flipped rather than that of every byte. A final partial word
+    /// keeps only its low bytes, as the generators of `rand` do.
+    fn fill_bytes(&mut self, dst: &mut [u8]) {
+        for chunk in dst.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
         }
     }
+}
 
-    #[test]
-    fn test_to_entropy() {
-        let probabilities = Grid::new(5, 9, 0.5);
-        let entropy = to_entropy(&probabilities);
-        assert_eq!(entropy.rows(), probabilities.rows());
-        assert_eq!(entropy.cols(), probabilities.cols());
-        // Check if all values are between 0 and 1
-        for y in 0..probabilities.rows() {
-            for x in 0..probabilities.cols() {
-                let pos = Position::new(x, y);
-                assert!(entropy[&pos] >= 0.0 && entropy[&pos] <= 1.0);
-            }
+/// Checks if a board is larger than [`LARGE_GRID_AREA`], to be searched with pruning.
+fn is_large(rect_mask: &Grid<bool>) -> bool {
+    rect_mask.rows() * rect_mask.cols() > LARGE_GRID_AREA
+}
+
+/// Returns the total area of the shapes of the given orientations.
+fn shapes_area(orientations: &[Vec<Shape>]) -> usize {
+    orientations.iter().map(|shapes| shapes[0].area()).sum()
+}
+
+/// Draws an index with a probability proportional to its weight.
+///
+/// # Returns
+///
+/// The index drawn, or `None` if every weight is zero.
+fn draw_weighted<R: Rng + ?Sized>(weights: &[f64], rng: &mut R) -> Option<usize> {
+    let total: f64 = weights.iter().sum();
+    if total <= 0.0 {
+        return None;
+    }
+    let mut target = rng.random::<f64>() * total;
+    for (i, &weight) in weights.iter().enumerate() {
+        if target < weight {
+            return Some(i);
         }
+        target -= weight;
+    }
+    // Rounding may leave the target just past the last weight; fall back to the last nonzero one.
+    weights.iter().rposition(|&weight| weight > 0.0)
+}
+
+/// Checks that every required cell is covered by a placed rectangle.
+///
+/// # Arguments
+///
+/// * `positions` - A grid of the placed rectangles (0 for empty and rect_id for the rectangle).
+/// * `required` - A grid where `true` marks a cell that must be covered.
+fn covers_required(positions: &Grid<usize>, required: &Grid<bool>) -> bool {
+    required
+        .iter_positions()
+        .all(|(pos, &required)| !required || positions[&pos] != 0)
+}
+
+/// Writes a shape at the given position into a grid mask and a grid of placed shapes.
+///
+/// A `rect_id` of 0 removes the shape again.
+fn mark_shape(
+    rect_mask: &mut Grid<bool>,
+    positions: &mut Grid<usize>,
+    pos: &Position,
+    shape: &Shape,
+    rect_id: usize,
+) {
+    for cell in shape.cells() {
+        let pos = pos
+            .offset(cell.x() as isize, cell.y() as isize)
+            .expect("a placed shape lies within the grid");
+        rect_mask[&pos] = rect_id > 0;
+        positions[&pos] = rect_id;
+    }
+}
+
+/// Estimates the probabilities of a grid from its rectangles.
+///
+/// Runs [`DEFAULT_SIMULATIONS`] simulations. A board without any masked cell of at most
+/// [`OPEN_EXACT_MAX_AREA`] cells is enumerated exactly instead, as sampling it is pure overhead,
+/// unless the enumeration gives up within [`DEFAULT_NODE_BUDGET`] nodes. A larger open board is
+/// sampled like any other board.
+///
+/// The two paths do not compute quite the same probabilities. The enumeration weighs every layout
+/// equally, while a simulation places the rectangles one after another, which favors some layouts
+/// over others. Beyond the noise of sampling, the probabilities of an open board may therefore
+/// shift by a few hundredths between [`OPEN_EXACT_MAX_AREA`] cells and one more, or when the
+/// enumeration gives up.
+///
+/// # Arguments
+///
+/// * `rect_mask` - A grid mask of rectangles.
+/// * `rectangles` - The rectangles to be placed.
+pub fn estimate_probabilities(rect_mask: &Grid<bool>, rectangles: &[Rectangle]) -> Grid<f64> {
+    let open = rect_mask.rows() * rect_mask.cols() <= OPEN_EXACT_MAX_AREA
+        && rect_mask.count(|&masked| masked) == 0;
+    if open
+        && let Some(exact) = estimate_exact_with(rect_mask, rectangles, None, DEFAULT_NODE_BUDGET)
+    {
+        return exact.probabilities;
+    }
+    estimate_probabilities_with(rect_mask, rectangles, DEFAULT_SIMULATIONS)
+}
+
+/// Estimates the probabilities of a grid from its rectangles with the given number of simulations.
+///
+/// # Arguments
+///
+/// * `rect_mask` - A grid mask of rectangles.
+/// * `rectangles` - The rectangles to be placed.
+/// * `simulations` - The number of simulations to run.
+pub fn estimate_probabilities_with(
+    rect_mask: &Grid<bool>,
+    rectangles: &[Rectangle],
+    simulations: usize,
+) -> Grid<f64> {
+    let options = EstimateOptions {
+        simulations,
+        ..Default::default()
+    };
+    estimate_with_options(rect_mask, rectangles, &options).probabilities
+}
+
+/// Estimates the probabilities of a grid from its rectangles with a fixed seed.
+///
+/// The same inputs and seed always give identical probabilities.
+///
+/// # Arguments
+///
+/// * `rect_mask` - A grid mask of rectangles.
+/// * `rectangles` - The rectangles to be placed.
+/// * `seed` - The seed of the random number generator.
+pub fn estimate_probabilities_seeded(
+    rect_mask: &Grid<bool>,
+    rectangles: &[Rectangle],
+    seed: u64,
+) -> Grid<f64> {
+    let options = EstimateOptions {
+        seed: Some(seed),
+        ..Default::default()
+    };
+    estimate_with_options(rect_mask, rectangles, &options).probabilities
+}
+
+/// Estimates the probabilities of a grid from its rectangles until they stop changing.
+///
+/// # Arguments
+///
+/// * `rect_mask` - A grid mask of rectangles.
+/// * `rectangles` - The rectangles to be placed.
+/// * `tolerance` - The maximum per-cell change between two checkpoints for the probabilities to count as converged.
+/// * `max_sims` - The maximum number of simulations to run.
+///
+/// # Returns
+///
+/// The estimate, whose stats tell how many simulations were actually run.
+pub fn estimate_until_converged(
+    rect_mask: &Grid<bool>,
+    rectangles: &[Rectangle],
+    tolerance: f64,
+    max_sims: usize,
+) -> Estimate {
+    let options = EstimateOptions {
+        simulations: max_sims,
+        tolerance: Some(tolerance),
+        ..Default::default()
+    };
+    estimate_with_options(rect_mask, rectangles, &options)
+}
+
+/// Estimates the probabilities of a grid from its rectangles, stopping early past a deadline.
+///
+/// Runs up to [`DEFAULT_SIMULATIONS`] simulations, checking the time between batches of
+/// [`CHUNKS_PER_BATCH`] chunks, so at least one batch is always completed and the deadline may be
+/// overrun by up to a batch.
+///
+/// # Arguments
+///
+/// * `rect_mask` - A grid mask of rectangles.
+/// * `rectangles` - The rectangles to be placed.
+/// * `deadline` - The time after which no further batch is started.
+///
+/// # Returns
+///
+/// The estimate so far, whose stats tell how many simulations were actually completed.
+pub fn estimate_probabilities_within(
+    rect_mask: &Grid<bool>,
+    rectangles: &[Rectangle],
+    deadline: Duration,
+) -> Estimate {
+    let options = EstimateOptions {
+        deadline: Some(deadline),
+        ..Default::default()
+    };
+    estimate_with_options(rect_mask, rectangles, &options)
+}
+
+/// Estimates the probabilities of a grid from its rectangles with the given options.
+///
+/// The simulations run in batches of [`CHUNKS_PER_BATCH`] chunks. If a tolerance is given, the
+/// probabilities are compared after every batch, and the estimate stops early once the largest
+/// per-cell change since the previous batch is below the tolerance. If a deadline is given, the
+/// estimate stops after the first batch ending past it.
+///
+/// If the rectangles cover more cells than are free, no simulation can succeed, so none are run
+/// and the estimate is all zeros with a feasibility of 0.
+///
+/// The counts are averaged over the symmetries of the board found by [`detect_symmetries`], so the
+/// estimate of a symmetric board is symmetric too. The standard errors ignore that averaging and
+/// are an upper bound.
+///
+/// # Arguments
+///
+/// * `rect_mask` - A grid mask of rectangles.
+/// * `rectangles` - The pieces to be placed, [`Rectangle`](crate::types::Rectangle)s or any other [`Shape`]s.
+/// * `options` - The options of the simulation.
+pub fn estimate_with_options<P: Clone + Into<Shape>>(
+    rect_mask: &Grid<bool>,
+    rectangles: &[P],
+    options: &EstimateOptions,
+) -> Estimate {
+    run_batches(rect_mask, &to_shapes(rectangles), options, None)
+}
+
+/// Estimates the probabilities of a grid from its rectangles like [`estimate_with_options`], once
+/// [`check_board`] finds nothing wrong with the board.
+///
+/// # Arguments
+///
+/// * `rect_mask` - A grid mask of rectangles.
+/// * `rectangles` - The pieces to be placed, [`Rectangle`](crate::types::Rectangle)s or any other [`Shape`]s.
+/// * `options` - The options of the simulation.
+///
+/// # Returns
+///
+/// The estimate, the first error [`check_board`] finds, or [`Error::Infeasible`] if no simulation
+/// placed every rectangle.
+pub fn try_estimate<P: Clone + Into<Shape>>(
+    rect_mask: &Grid<bool>,
+    rectangles: &[P],
+    options: &EstimateOptions,
+) -> Result<Estimate, Error> {
+    let shapes = to_shapes(rectangles);
+    check_board(rect_mask, &shapes, options)?;
+    let estimate = run_batches(rect_mask, &shapes, options, None);
+    if estimate.stats.feasible_samples == 0 {
+        return Err(Error::Infeasible);
+    }
+    Ok(estimate)
+}
+
+/// Checks that a board can be estimated, rather than giving an estimate of nothing or panicking.
+///
+/// # Arguments
+///
+/// * `rect_mask` - A grid mask of rectangles.
+/// * `shapes` - The shapes to be placed.
+/// * `options` - The options of the simulation, whose required cells and counted rectangles are
+///   checked against the board.
+///
+/// # Returns
+///
+/// [`Error::ZeroDimension`] for a grid without cells, [`Error::ZeroMaxCandidates`] for a cap of
+/// no candidates, [`Error::GridDimensionMismatch`] for
+/// required cells or weights of another size than the mask, [`Error::OversizedRectangle`] for a
+/// shape that fits the grid in no orientation, or [`Error::InvalidRectangleIndex`] for a counted
+/// rectangle that does not exist, whichever comes first.
+pub fn check_board(
+    rect_mask: &Grid<bool>,
+    shapes: &[Shape],
+    options: &EstimateOptions,
+) -> Result<(), Error> {
+    let expected = (rect_mask.rows(), rect_mask.cols());
+    if expected.0 == 0 || expected.1 == 0 {
+        return Err(Error::ZeroDimension);
+    }
+    if options.max_candidates == Some(0) {
+        return Err(Error::ZeroMaxCandidates);
+    }
+    let mismatch = |grid, found: (usize, usize)| {
+        (found != expected).then_some(Error::GridDimensionMismatch {
+            grid,
+            expected,
+            found,
+        })
+    };
+    if let Some(required) = &options.required
+        && let Some(error) = mismatch(BoardGrid::Required, (required.rows(), required.cols()))
+    {
+        return Err(error);
+    }
+    for (rectangle, shape) in shapes.iter().enumerate() {
+        if let Some(weights) = shape.weights() {
+            let found = (weights.grid().rows(), weights.grid().cols());
+            if let Some(error) = mismatch(BoardGrid::Weights { rectangle }, found) {
+                return Err(error);
+            }
+        }
+    }
+    let orientations = to_orientations(shapes);
+    if let Some(rectangle) = orientations.iter().position(|orientations| {
+        !orientations
+            .iter()
+            .any(|shape| shape.width() <= expected.1 && shape.height() <= expected.0)
+    }) {
+        return Err(Error::OversizedRectangle { rectangle });
+    }
+    if let Some(&index) = options
+        .counted
+        .iter()
+        .flatten()
+        .find(|&&index| index >= shapes.len())
+    {
+        return Err(Error::InvalidRectangleIndex {
+            index,
+            rectangles: shapes.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Checks that a grid has cells and that every rectangle fits it in some orientation, comparing
+/// their dimensions only.
+///
+/// Turning a rectangle into a [`Shape`] lists its cells, so a board from outside is checked with
+/// this first: an oversized rectangle is then rejected before it takes any memory.
+///
+/// # Returns
+///
+/// [`Error::ZeroDimension`] for a grid without cells, or [`Error::OversizedRectangle`] for the
+/// first rectangle that fits the grid in no orientation.
+pub fn check_rectangles(rect_mask: &Grid<bool>, rectangles: &[Rectangle]) -> Result<(), Error> {
+    let (rows, cols) = (rect_mask.rows(), rect_mask.cols());
+    if rows == 0 || cols == 0 {
+        return Err(Error::ZeroDimension);
+    }
+    match rectangles
+        .iter()
+        .position(|rect| !rect.fits_within(rows, cols))
+    {
+        Some(rectangle) => Err(Error::OversizedRectangle { rectangle }),
+        None => Ok(()),
+    }
+}
+
+/// Checks a board of rectangles from outside and turns them into [`Shape`]s.
+///
+/// Runs [`check_rectangles`] before listing the cells of any rectangle, then [`check_board`] on
+/// the shapes.
+///
+/// # Returns
+///
+/// The shapes of the rectangles, to be estimated with the same `options`, or the first error
+/// either check finds.
+pub fn to_checked_shapes(
+    rect_mask: &Grid<bool>,
+    rectangles: &[Rectangle],
+    options: &EstimateOptions,
+) -> Result<Vec<Shape>, Error> {
+    check_rectangles(rect_mask, rectangles)?;
+    let shapes = to_shapes(rectangles);
+    check_board(rect_mask, &shapes, options)?;
+    Ok(shapes)
+}
+
+/// Estimates the probabilities of a grid from its rectangles, reporting the estimate so far after
+/// every batch of [`CHUNKS_PER_BATCH`] chunks.
+///
+/// Behaves like [`estimate_with_options`] otherwise, and gives the same result for the same seed.
+///
+/// # Arguments
+///
+/// * `rect_mask` - A grid mask of rectangles.
+/// * `rectangles` - The pieces to be placed, [`Rectangle`](crate::types::Rectangle)s or any other [`Shape`]s.
+/// * `options` - The options of the simulation.
+/// * `on_batch` - Called with the estimate so far after every batch. Returning
+///   [`ControlFlow::Break`] stops the estimate, e.g. when nobody is waiting for it anymore.
+///
+/// # Returns
+///
+/// The estimate when it finished, converged, or was stopped.
+pub fn estimate_with_progress<P: Clone + Into<Shape>>(
+    rect_mask: &Grid<bool>,
+    rectangles: &[P],
+    options: &EstimateOptions,
+    mut on_batch: impl FnMut(&Estimate) -> ControlFlow<()>,
+) -> Estimate {
+    run_batches(
+        rect_mask,
+        &to_shapes(rectangles),
+        options,
+        Some(&mut on_batch),
+    )
+}
+
+/// A callback observing the estimate after every batch of simulations.
+type BatchObserver<'a> = &'a mut dyn FnMut(&Estimate) -> ControlFlow<()>;
+
+/// Runs the simulations of an estimate in batches, stopping early at convergence or when asked to.
+///
+/// Without a tolerance, a deadline or an observer there is nothing to do in between, so everything
+/// runs in one batch. The dead cells of the board, which no shape can cover, are blocked before
+/// simulating and never counted.
+fn run_batches(
+    rect_mask: &Grid<bool>,
+    shapes: &[Shape],
+    options: &EstimateOptions,
+    mut on_batch: Option<BatchObserver>,
+) -> Estimate {
+    let start = Instant::now();
+    let simulations = options.simulations;
+    let seed = options.seed.unwrap_or_else(|| rng().random());
+    let _span = tracing::debug_span!(
+        "run_batches",
+        rows = rect_mask.rows(),
+        cols = rect_mask.cols(),
+        rectangles = shapes.len(),
+        simulations,
+    )
+    .entered();
+
+    let (shapes, ids) = sort_shapes(shapes);
+
+    let mut tally = Tally::new(rect_mask.rows(), rect_mask.cols(), shapes.len());
+    if !fits_free_area(rect_mask, &shapes) {
+        return trace_estimate(tally.to_estimate(0, start, &ids, &[], None));
+    }
+    let orientations = to_orientations(&shapes);
+    let symmetries = detect_symmetries(rect_mask, &shapes, options.required.as_ref());
+    let counted = options.counted.as_deref();
+    let reachable = to_reachable(rect_mask, &orientations);
+    let cells: Vec<Position> = reachable
+        .iter_positions()
+        .filter(|&(_, &reachable)| reachable)
+        .map(|(pos, _)| pos)
+        .collect();
+    let pruned = block_unreachable(rect_mask, &reachable);
+
+    let chunks = simulations.div_ceil(SIMULATIONS_PER_CHUNK);
+    let chunks_per_batch =
+        if options.tolerance.is_some() || options.deadline.is_some() || on_batch.is_some() {
+            CHUNKS_PER_BATCH
+        } else {
+            chunks.max(1)
+        };
+
+    let mut simulations_run = 0;
+    let mut checkpoint: Option<Grid<f64>> = None;
+    for first_chunk in (0..chunks).step_by(chunks_per_batch) {
+        let last_chunk = (first_chunk + chunks_per_batch).min(chunks);
+        let batch = run_chunks(
+            &pruned,
+            &orientations,
+            &cells,
+            seed,
+            first_chunk..last_chunk,
+            options,
+        );
+        tally = tally.merge(batch);
+        simulations_run = (last_chunk * SIMULATIONS_PER_CHUNK).min(simulations);
+
+        if let Some(on_batch) = on_batch.as_mut()
+            && last_chunk < chunks
+            && on_batch(&tally.to_estimate(simulations_run, start, &ids, &symmetries, counted))
+                .is_break()
+        {
+            break;
+        }
+        if let Some(tolerance) = options.tolerance {
+            let probabilities = tally.probabilities();
+            if let Some(previous) = &checkpoint
+                && previous
+                    .max_abs_diff(&probabilities)
+                    .is_some_and(|change| change < tolerance)
+            {
+                break;
+            }
+            checkpoint = Some(probabilities);
+        }
+        if options
+            .deadline
+            .is_some_and(|deadline| start.elapsed() >= deadline)
+        {
+            break;
+        }
+    }
+
+    trace_estimate(tally.to_estimate(simulations_run, start, &ids, &symmetries, counted))
+}
+
+/// Emits a debug event describing how a sampled estimate went, and returns the estimate.
+fn trace_estimate(estimate: Estimate) -> Estimate {
+    tracing::debug!(
+        simulations_run = estimate.stats.simulations_run,
+        feasible_samples = estimate.stats.feasible_samples,
+        feasibility = estimate.feasibility,
+        elapsed_ms = estimate.stats.elapsed.as_millis() as u64,
+        "sampled estimate",
+    );
+    estimate
+}
+
+/// Finds the symmetries of a board, under which the probabilities of its cells are invariant.
+///
+/// Every [`Symmetry`] is checked: the mirrors left to right and top to bottom, the half turn, and
+/// on square boards the reflections across either diagonal and the quarter turns. A symmetry of
+/// the board maps the mask onto itself, the required cells onto themselves, the weights of each
+/// piece onto themselves, and every orientation of each piece onto an orientation of the same
+/// piece, so it maps the layouts onto the layouts with their probabilities.
+///
+/// # Arguments
+///
+/// * `rect_mask` - A grid mask of rectangles.
+/// * `rectangles` - The pieces to be placed, [`Rectangle`](crate::types::Rectangle)s or any other [`Shape`]s.
+/// * `required` - A grid where `true` marks a cell that must be covered, if any.
+///
+/// # Returns
+///
+/// The symmetries of the board, which together with the identity are closed under composition.
+pub fn detect_symmetries<P: Clone + Into<Shape>>(
+    rect_mask: &Grid<bool>,
+    rectangles: &[P],
+    required: Option<&Grid<bool>>,
+) -> Vec<Symmetry> {
+    let orientations = to_orientations(&to_shapes(rectangles));
+    Symmetry::ALL
+        .into_iter()
+        .filter(|&symmetry| {
+            (!symmetry.swaps_axes() || rect_mask.rows() == rect_mask.cols())
+                && is_invariant(rect_mask, symmetry)
+                && required.is_none_or(|required| is_invariant(required, symmetry))
+                && orientations.iter().all(|orientations| {
+                    orientations[0].weights().is_none_or(|weights| {
+                        let grid = weights.grid();
+                        (grid.rows(), grid.cols()) == (rect_mask.rows(), rect_mask.cols())
+                            && is_invariant(grid, symmetry)
+                    })
+                })
+                && orientations.iter().all(|orientations| {
+                    orientations.iter().all(|orientation| {
+                        orientations.contains(&orientation.transformed(symmetry))
+                    })
+                })
+        })
+        .collect()
+}
+
+/// Checks if a grid is mapped onto itself by a [`Symmetry`] that keeps its size.
+fn is_invariant<T: Clone + PartialEq>(grid: &Grid<T>, symmetry: Symmetry) -> bool {
+    grid.iter_positions()
+        .all(|(pos, value)| grid[&symmetry.apply(&pos, grid.cols(), grid.rows())] == *value)
+}
+
+/// Averages each cell of a grid with its images under the symmetries of the board.
+///
+/// The probabilities of a symmetric board are the same on every cell of an orbit, so averaging
+/// them cancels part of the sampling noise without biasing the estimate.
+///
+/// # Arguments
+///
+/// * `grid` - A grid of values, e.g. estimated probabilities.
+/// * `symmetries` - Symmetries of the board keeping the size of the grid, such as those found by
+///   [`detect_symmetries`]. Unless they are closed under composition, the result is not symmetric.
+///
+/// # Returns
+///
+/// The grid with each value replaced by the mean over the identity and the `symmetries`.
+pub fn apply_symmetry(grid: &Grid<f64>, symmetries: &[Symmetry]) -> Grid<f64> {
+    let mut result = grid.clone();
+    if symmetries.is_empty() {
+        return result;
+    }
+    let orbit = (symmetries.len() + 1) as f64;
+    for (pos, value) in result.iter_positions_mut() {
+        let images: f64 = symmetries
+            .iter()
+            .map(|symmetry| grid[&symmetry.apply(&pos, grid.cols(), grid.rows())])
+            .sum();
+        *value = (*value + images) / orbit;
+    }
+    result
+}
+
+/// Checks if the total area of the shapes is at most the number of free cells.
+fn fits_free_area(rect_mask: &Grid<bool>, shapes: &[Shape]) -> bool {
+    let free = rect_mask.count(|&blocked| !blocked);
+    shapes.iter().map(Shape::area).sum::<usize>() <= free
+}
+
+/// Sorts the shapes by area in descending order, keeping identical shapes together.
+///
+/// # Returns
+///
+/// The sorted shapes, and the index in `shapes` of each sorted shape.
+fn sort_shapes(shapes: &[Shape]) -> (Vec<Shape>, Vec<usize>) {
+    let mut ids: Vec<usize> = (0..shapes.len()).collect();
+    ids.sort_by(|&a, &b| {
+        let key = |shape: &Shape| {
+            (
+                std::cmp::Reverse(shape.area()),
+                shape.width(),
+                shape.height(),
+            )
+        };
+        let cells = |shape: &Shape| {
+            let cells: Vec<(usize, usize)> = shape
+                .cells()
+                .iter()
+                .map(|cell| (cell.y(), cell.x()))
+                .collect();
+            cells
+        };
+        key(&shapes[a])
+            .cmp(&key(&shapes[b]))
+            .then_with(|| cells(&shapes[a]).cmp(&cells(&shapes[b])))
+    });
+    let sorted = ids.iter().map(|&i| shapes[i].clone()).collect();
+    (sorted, ids)
+}
+
+/// Finds the most likely rectangle to cover each cell.
+///
+/// # Arguments
+///
+/// * `counts` - The number of layouts in which each rectangle covers each cell, indexed by `(y * cols + x) * ids.len() + rect_idx`.
+/// * `layouts` - The number of layouts counted.
+/// * `ids` - The index in the caller's rectangles of each counted rectangle.
+/// * `grid_size` - The number of rows and columns of the grid.
+///
+/// # Returns
+///
+/// A grid of the 1-based index of the most likely rectangle and its probability for each cell.
+/// Ties go to the rectangle listed first by the caller, and cells never covered get `(0, 0.0)`.
+fn to_occupants(
+    counts: &[f64],
+    layouts: usize,
+    ids: &[usize],
+    grid_size: (usize, usize),
+) -> Grid<(usize, f64)> {
+    let (rows, cols) = grid_size;
+    let mut occupants = Grid::new(rows, cols, (0, 0.0));
+    if ids.is_empty() || layouts == 0 {
+        return occupants;
+    }
+    for (cell, counts) in counts.chunks(ids.len()).enumerate() {
+        let best = (0..ids.len())
+            .filter(|&rect_idx| counts[rect_idx] > 0.0)
+            .max_by(|&a, &b| {
+                counts[a]
+                    .total_cmp(&counts[b])
+                    .then_with(|| ids[b].cmp(&ids[a]))
+            });
+        if let Some(best) = best {
+            let pos = Position::new(cell % cols, cell / cols);
+            occupants[&pos] = (ids[best] + 1, counts[best] / layouts as f64);
+        }
+    }
+    occupants
+}
+
+/// Finds the expected number of rectangles covering each cell.
+///
+/// Sums the counts of every rectangle at each cell, so a cell covered by two rectangles of one
+/// layout would count twice.
+///
+/// # Arguments
+///
+/// * `counts` - The number of layouts in which each rectangle covers each cell, indexed by `(y * cols + x) * rectangles + rect_idx`.
+/// * `layouts` - The number of layouts counted.
+/// * `rectangles` - The number of rectangles counted.
+/// * `grid_size` - The number of rows and columns of the grid.
+///
+/// # Returns
+///
+/// A grid of the mean number of rectangles covering each cell, all zero if no layout was counted.
+fn to_expected_coverage(
+    counts: &[f64],
+    layouts: usize,
+    rectangles: usize,
+    grid_size: (usize, usize),
+) -> Grid<f64> {
+    let (rows, cols) = grid_size;
+    let mut coverage = Grid::new(rows, cols, 0.0);
+    if rectangles == 0 || layouts == 0 {
+        return coverage;
+    }
+    for (cell, counts) in counts.chunks(rectangles).enumerate() {
+        let pos = Position::new(cell % cols, cell / cols);
+        coverage[&pos] = counts.iter().sum::<f64>() / layouts as f64;
+    }
+    coverage
+}
+
+/// Finds the probability that each cell is covered by one of the counted rectangles.
+///
+/// As the rectangles of a layout never overlap, this sums the counts of the counted rectangles at
+/// each cell.
+///
+/// # Arguments
+///
+/// * `counts` - The number of layouts in which each rectangle covers each cell, indexed by `(y * cols + x) * ids.len() + rect_idx`.
+/// * `layouts` - The number of layouts counted.
+/// * `ids` - The index in the caller's rectangles of each rectangle in `counts`.
+/// * `counted` - The indices in the caller's rectangles of the rectangles to count.
+/// * `grid_size` - The number of rows and columns of the grid.
+///
+/// # Returns
+///
+/// A grid of the fraction of layouts in which a counted rectangle covers each cell, all zero if
+/// no layout was counted.
+fn to_coverage_of(
+    counts: &[f64],
+    layouts: usize,
+    ids: &[usize],
+    counted: &[usize],
+    grid_size: (usize, usize),
+) -> Grid<f64> {
+    let (rows, cols) = grid_size;
+    let mut coverage = Grid::new(rows, cols, 0.0);
+    if ids.is_empty() || layouts == 0 {
+        return coverage;
+    }
+    for (cell, counts) in counts.chunks(ids.len()).enumerate() {
+        let pos = Position::new(cell % cols, cell / cols);
+        coverage[&pos] = (0..ids.len())
+            .filter(|&rect_idx| counted.contains(&ids[rect_idx]))
+            .map(|rect_idx| counts[rect_idx])
+            .sum::<f64>()
+            / layouts as f64;
+    }
+    coverage
+}
+
+/// Runs a range of seeded simulation chunks in parallel and merges their tallies.
+///
+/// With [`EstimateOptions::antithetic`], every other simulation of a chunk is the antithetic one
+/// of the simulation before it, replaying its draws shifted by a half.
+///
+/// # Arguments
+///
+/// * `rect_mask` - A grid mask of rectangles, whose dead cells are blocked by [`block_unreachable`].
+/// * `orientations` - The orientations of each shape to be placed, sorted in placement order.
+/// * `cells` - The cells some shape can cover, the only ones counted.
+/// * `seed` - The seed of the estimate, mixed with the index of each chunk by [`chunk_seed`].
+/// * `chunks` - The indices of the chunks to run.
+/// * `options` - The options of the estimate, whose `simulations` bounds the last chunk.
+fn run_chunks(
+    rect_mask: &Grid<bool>,
+    orientations: &[Vec<Shape>],
+    cells: &[Position],
+    seed: u64,
+    chunks: std::ops::Range<usize>,
+    options: &EstimateOptions,
+) -> Tally {
+    let required = options.required.as_ref();
+    chunks
+        .into_par_iter()
+        .map_init(
+            || Scratch::new(rect_mask).with_max_candidates(options.max_candidates),
+            |scratch, chunk| {
+                let mut rng = StdRng::seed_from_u64(chunk_seed(seed, chunk));
+                let start = chunk * SIMULATIONS_PER_CHUNK;
+                let end = (start + SIMULATIONS_PER_CHUNK).min(options.simulations);
+
+                let mut tally = Tally::new(rect_mask.rows(), rect_mask.cols(), orientations.len());
+                let mut pair: Option<Antithetic<StdRng>> = None;
+                for _ in start..end {
+                    let result = match pair.take() {
+                        Some(mut antithetic) => place_rectangles(
+                            rect_mask,
+                            orientations,
+                            required,
+                            scratch,
+                            &mut antithetic,
+                        ),
+                        None => {
+                            if options.antithetic {
+                                pair = Some(Antithetic(rng.clone()));
+                            }
+                            place_rectangles(rect_mask, orientations, required, scratch, &mut rng)
+                        }
+                    };
+                    if let Some(result) = result {
+                        tally.record_cells(result, cells);
+                    }
+                }
+                tally
+            },
+        )
+        .reduce(
+            || Tally::new(rect_mask.rows(), rect_mask.cols(), orientations.len()),
+            Tally::merge,
+        )
+}
+
+/// Diagnoses which rectangles keep the simulations of a board from placing every rectangle.
+///
+/// Runs `options.simulations` simulations like [`estimate_with_options`], ignoring any tolerance or
+/// deadline. A failed simulation is blamed on the rectangle it failed to place: the one at the
+/// deepest point its search reached, as the simulation could never get past it. A simulation that
+/// placed every rectangle but missed a required cell is blamed on none.
+///
+/// # Arguments
+///
+/// * `rect_mask` - A grid mask of rectangles.
+/// * `rectangles` - The pieces to be placed, [`Rectangle`](crate::types::Rectangle)s or any other [`Shape`]s.
+/// * `options` - The options of the simulation.
+///
+/// # Returns
+///
+/// The fraction of the simulations blamed on each rectangle, in the order of `rectangles`, all
+/// zero if every simulation succeeded.
+pub fn failure_rates<P: Clone + Into<Shape>>(
+    rect_mask: &Grid<bool>,
+    rectangles: &[P],
+    options: &EstimateOptions,
+) -> Vec<f64> {
+    let (shapes, ids) = sort_shapes(&to_shapes(rectangles));
+    let orientations = to_orientations(&shapes);
+    let rect_mask = &block_unreachable(rect_mask, &to_reachable(rect_mask, &orientations));
+    let required = options.required.as_ref();
+    let simulations = options.simulations;
+    let seed = options.seed.unwrap_or_else(|| rng().random());
+
+    let failures = (0..simulations.div_ceil(SIMULATIONS_PER_CHUNK))
+        .into_par_iter()
+        .map_init(
+            || Scratch::new(rect_mask).with_max_candidates(options.max_candidates),
+            |scratch, chunk| {
+                let mut rng = StdRng::seed_from_u64(chunk_seed(seed, chunk));
+                let start = chunk * SIMULATIONS_PER_CHUNK;
+                let end = (start + SIMULATIONS_PER_CHUNK).min(simulations);
+
+                let mut failures = vec![0; shapes.len()];
+                for _ in start..end {
+                    let placed =
+                        place_rectangles(rect_mask, &orientations, required, scratch, &mut rng)
+                            .is_some();
+                    if !placed && let Some(failed) = failures.get_mut(scratch.deepest) {
+                        *failed += 1;
+                    }
+                }
+                failures
+            },
+        )
+        .reduce(
+            || vec![0; shapes.len()],
+            |mut total, failures| {
+                for (total, failures) in total.iter_mut().zip(failures) {
+                    *total += failures;
+                }
+                total
+            },
+        );
+
+    let mut rates = vec![0.0; shapes.len()];
+    if simulations > 0 {
+        for (rect_idx, &failed) in failures.iter().enumerate() {
+            rates[ids[rect_idx]] = failed as f64 / simulations as f64;
+        }
+    }
+    rates
+}
+
+/// Computes the standard error of each probability estimated from `samples` successful simulations.
+///
+/// Each simulation contributes an indicator of whether the cell was covered, so the sum of squares
+/// equals the sum of hits and the standard error reduces to `sqrt(p * (1 - p) / n)`.
+///
+/// # Arguments
+///
+/// * `probabilities` - A grid of estimated probabilities.
+/// * `samples` - The number of successful simulations the probabilities were estimated from.
+///
+/// # Returns
+///
+/// A grid of standard errors, all zero if there were no samples.
+fn to_std_error(probabilities: &Grid<f64>, samples: usize) -> Grid<f64> {
+    if samples == 0 {
+        return probabilities.map(|_| 0.0);
+    }
+    probabilities.map(|&p| {
+        let p = p.clamp(0.0, 1.0);
+        (p * (1.0 - p) / samples as f64).sqrt()
+    })
+}
+
+/// An exhaustive search over all the layouts of a set of rectangles.
+struct Enumeration<'a> {
+    /// The grid mask, updated as rectangles are placed and removed.
+    rect_mask: Grid<bool>,
+    /// The 1-based index of the rectangle covering each cell in the current partial layout, or 0.
+    covered: Grid<usize>,
+    /// The shapes to be placed.
+    shapes: &'a [Shape],
+    /// A grid where `true` marks a cell that must be covered, if any.
+    required: Option<&'a Grid<bool>>,
+    /// The range of rectangles identical to each rectangle, itself included.
+    groups: Vec<std::ops::Range<usize>>,
+    /// The distinct orientations of each shape.
+    orientations: Vec<Vec<Shape>>,
+    /// The candidate placements of each shape, as a position and an index into its orientations.
+    candidates: Vec<Vec<(Position, usize)>>,
+    /// The number of complete layouts in which each cell is covered.
+    coverage: Grid<f64>,
+    /// The number of complete layouts in which each rectangle covers each cell.
+    ///
+    /// Indexed by `(y * cols + x) * shapes.len() + rect_idx`.
+    occupants: Vec<f64>,
+    /// The number of complete layouts found.
+    layouts: usize,
+    /// The number of search nodes visited.
+    nodes: usize,
+    /// The maximum number of search nodes to visit.
+    node_budget: usize,
+}
+
+impl Enumeration<'_> {
+    /// Places the rectangles from `rect_idx` onwards in every possible way.
+    ///
+    /// Identical consecutive rectangles are only placed in increasing candidate order, so each layout
+    /// is counted once regardless of how the identical rectangles are permuted. A cell covered by one
+    /// of a group of identical rectangles is attributed to all of them evenly.
+    ///
+    /// Returns `false` if the node budget was exhausted.
+    fn search(&mut self, rect_idx: usize, first_candidate: usize) -> bool {
+        self.nodes += 1;
+        if self.nodes > self.node_budget {
+            return false;
+        }
+        if rect_idx == self.shapes.len() {
+            if let Some(required) = self.required
+                && !covers_required(&self.covered, required)
+            {
+                return true;
+            }
+            self.layouts += 1;
+            let n = self.shapes.len();
+            for (cell, (pos, &rect_id)) in self.covered.iter_positions().enumerate() {
+                if rect_id == 0 {
+                    continue;
+                }
+                self.coverage[&pos] += 1.0;
+                let group = self.groups[rect_id - 1].clone();
+                let share = 1.0 / group.len() as f64;
+                for member in group {
+                    self.occupants[cell * n + member] += share;
+                }
+            }
+            return true;
+        }
+
+        let is_repeated =
+            rect_idx + 1 < self.shapes.len() && self.shapes[rect_idx + 1] == self.shapes[rect_idx];
+        for candidate in first_candidate..self.candidates[rect_idx].len() {
+            let (pos, orientation) = self.candidates[rect_idx][candidate].clone();
+            let shape = &self.orientations[rect_idx][orientation];
+            if !self.rect_mask.all(&pos, shape, &false) {
+                continue;
+            }
+
+            mark_shape(
+                &mut self.rect_mask,
+                &mut self.covered,
+                &pos,
+                shape,
+                rect_idx + 1,
+            );
+            let next_first = if is_repeated { candidate + 1 } else { 0 };
+            let within_budget = self.search(rect_idx + 1, next_first);
+            let shape = &self.orientations[rect_idx][orientation];
+            mark_shape(&mut self.rect_mask, &mut self.covered, &pos, shape, 0);
+            if !within_budget {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Computes the exact probabilities of a grid from its rectangles.
+///
+/// Visits at most [`DEFAULT_NODE_BUDGET`] search nodes.
+///
+/// # Arguments
+///
+/// * `rect_mask` - A grid mask of rectangles.
+/// * `rectangles` - The rectangles to be placed.
+///
+/// # Returns
+///
+/// The true marginal occupancy probability of each cell, or `None` if the search space is too large.
+pub fn estimate_exact(rect_mask: &Grid<bool>, rectangles: &[Rectangle]) -> Option<Grid<f64>> {
+    estimate_exact_with(rect_mask, rectangles, None, DEFAULT_NODE_BUDGET)
+        .map(|estimate| estimate.probabilities)
+}
+
+/// Computes the exact probabilities of a grid from its rectangles by enumerating every layout.
+///
+/// Every valid layout is weighted equally, so pieces with [`Weights`](crate::types::Weights) are
+/// not supported. The standard error of an exact estimate is zero, and its feasibility is `1.0` if
+/// at least one layout exists and `0.0` otherwise.
+///
+/// # Arguments
+///
+/// * `rect_mask` - A grid mask of rectangles.
+/// * `rectangles` - The pieces to be placed, [`Rectangle`](crate::types::Rectangle)s or any other [`Shape`]s.
+/// * `required` - A grid where `true` marks a cell that must be covered, if any.
+/// * `node_budget` - The maximum number of search nodes to visit.
+///
+/// # Returns
+///
+/// The exact estimate, or `None` if a piece is weighted or the search visited more than
+/// `node_budget` nodes.
+pub fn estimate_exact_with<P: Clone + Into<Shape>>(
+    rect_mask: &Grid<bool>,
+    rectangles: &[P],
+    required: Option<&Grid<bool>>,
+    node_budget: usize,
+) -> Option<Estimate> {
+    let start = Instant::now();
+    let (rows, cols) = (rect_mask.rows(), rect_mask.cols());
+
+    let (shapes, ids) = sort_shapes(&to_shapes(rectangles));
+    if shapes.iter().any(|shape| shape.weights().is_some()) {
+        return None;
+    }
+    let groups = (0..shapes.len())
+        .map(|i| {
+            let start = (0..i)
+                .rev()
+                .take_while(|&j| shapes[j] == shapes[i])
+                .last()
+                .unwrap_or(i);
+            let end = (i..shapes.len())
+                .take_while(|&j| shapes[j] == shapes[i])
+                .last()
+                .unwrap_or(i);
+            start..end + 1
+        })
+        .collect();
+
+    // List the in-bounds placements of each shape in every orientation.
+    let orientations = to_orientations(&shapes);
+    let candidates = orientations
+        .iter()
+        .map(|shapes| {
+            let mut candidates = vec![];
+            for y in 0..rows {
+                for x in 0..cols {
+                    for (orientation, shape) in shapes.iter().enumerate() {
+                        if x + shape.width() <= cols && y + shape.height() <= rows {
+                            candidates.push((Position::new(x, y), orientation));
+                        }
+                    }
+                }
+            }
+            candidates
+        })
+        .collect();
+
+    // A board without any layout is settled by a single search, without enumerating.
+    let mut budget = node_budget;
+    let infeasible = place_rectangles_first_fit(
+        rect_mask,
+        &orientations,
+        required,
+        &mut Scratch::new(rect_mask),
+        &mut budget,
+    )
+    .is_none()
+        && budget > 0;
+
+    let mut enumeration = Enumeration {
+        rect_mask: rect_mask.clone(),
+        covered: Grid::new(rows, cols, 0),
+        shapes: &shapes,
+        required,
+        groups,
+        orientations,
+        candidates,
+        coverage: Grid::new(rows, cols, 0.0),
+        occupants: vec![0.0; rows * cols * shapes.len()],
+        layouts: 0,
+        nodes: 0,
+        node_budget,
+    };
+    if !infeasible && !enumeration.search(0, 0) {
+        return None;
+    }
+
+    let layouts = enumeration.layouts;
+    let probabilities = if layouts == 0 {
+        enumeration.coverage
+    } else {
+        enumeration.coverage / layouts as f64
+    };
+    Some(Estimate {
+        probabilities,
+        std_error: Grid::new(rows, cols, 0.0),
+        occupants: to_occupants(&enumeration.occupants, layouts, &ids, (rows, cols)),
+        expected_coverage: to_expected_coverage(
+            &enumeration.occupants,
+            layouts,
+            ids.len(),
+            (rows, cols),
+        ),
+        feasibility: if layouts == 0 { 0.0 } else { 1.0 },
+        stats: EstimateStats {
+            simulations_run: 0,
+            feasible_samples: layouts,
+            elapsed: start.elapsed(),
+        },
+    })
+}
+
+/// Computes the entropy of a grid from its probabilities.
+///
+/// # Arguments
+///
+/// * `probabilities` - A grid of probabilities.
+///
+/// # Returns
+///
+/// A grid of entropies computed from the probabilities element-wise, where a certain cell, of
+/// probability 0 or 1, has an entropy of exactly 0.
+pub fn to_entropy(probabilities: &Grid<f64>) -> Grid<f64> {
+    probabilities.map(|&p| {
+        if p <= 0.0 || p >= 1.0 {
+            0.0
+        } else {
+            (-p * p.log2() - (1.0 - p) * (1.0 - p).log2()).clamp(0.0, 1.0)
+        }
+    })
+}
+
+/// Picks the most informative cell to reveal next from an estimate.
+///
+/// The most informative cell is the unmasked cell first in the order of [`compare_suggestions`]:
+/// the highest entropy, then the smallest `y`, then the smallest `x`. The same estimate therefore
+/// always gives the same cell, even on symmetric boards where many cells tie.
+///
+/// # Arguments
+///
+/// * `rect_mask` - A grid mask of rectangles.
+/// * `estimate` - The estimate of the grid.
+///
+/// # Returns
+///
+/// The position of the chosen cell and its entropy, or None if every cell is masked or no
+/// feasible layout was found.
+pub fn suggest_from(rect_mask: &Grid<bool>, estimate: &Estimate) -> Option<(Position, f64)> {
+    if estimate.feasibility == 0.0 {
+        return None;
+    }
+
+    let entropy = to_entropy(&estimate.probabilities);
+    rect_mask
+        .iter_positions()
+        .filter(|&(_, &blocked)| !blocked)
+        .map(|(pos, _)| (pos.clone(), entropy[&pos]))
+        .max_by(compare_suggestions)
+}
+
+/// Orders candidate cells to suggest, the greater being the better suggestion.
+///
+/// A total order: the higher entropy is greater, with `NaN` below every other entropy, and among
+/// equal entropies the smaller `y`, then the smaller `x`, is greater.
+fn compare_suggestions(a: &(Position, f64), b: &(Position, f64)) -> Ordering {
+    let entropy = |&(_, entropy): &(Position, f64)| {
+        if entropy.is_nan() {
+            f64::NEG_INFINITY
+        } else {
+            entropy
+        }
+    };
+    entropy(a)
+        .total_cmp(&entropy(b))
+        .then_with(|| b.0.y().cmp(&a.0.y()))
+        .then_with(|| b.0.x().cmp(&a.0.x()))
+}
+
+/// Suggests the most informative cell to reveal next.
+///
+/// Runs [`DEFAULT_SIMULATIONS`] simulations and picks a cell with [`suggest_from`].
+///
+/// # Arguments
+///
+/// * `rect_mask` - A grid mask of rectangles.
+/// * `rectangles` - The rectangles to be placed.
+///
+/// # Returns
+///
+/// The position of the unmasked cell with the highest entropy, or None if every cell is masked or
+/// no feasible layout was found.
+pub fn suggest_next(rect_mask: &Grid<bool>, rectangles: &[Rectangle]) -> Option<Position> {
+    let estimate = estimate_with_options(rect_mask, rectangles, &EstimateOptions::default());
+    suggest_from(rect_mask, &estimate).map(|(pos, _)| pos)
+}
+
+/// Samples one layout of the pieces, a concrete arrangement that is consistent with the board.
+///
+/// Runs simulations like [`estimate_with_options`] until one places every piece covering every
+/// required cell, so layouts are drawn as the estimate counts them. The same inputs and seed always
+/// give the same layout.
+///
+/// # Arguments
+///
+/// * `rect_mask` - A grid mask of rectangles.
+/// * `rectangles` - The pieces to be placed, [`Rectangle`](crate::types::Rectangle)s or any other [`Shape`]s.
+/// * `required` - A grid where `true` marks a cell that must be covered, if any.
+/// * `seed` - The seed of the random number generator.
+/// * `attempts` - The maximum number of simulations to run.
+///
+/// # Returns
+///
+/// A grid of the piece covering each cell, identified by its 1-based index in `rectangles` or 0 for
+/// none, or `None` if no simulation succeeded within `attempts`.
+pub fn sample_layout<P: Clone + Into<Shape>>(
+    rect_mask: &Grid<bool>,
+    rectangles: &[P],
+    required: Option<&Grid<bool>>,
+    seed: u64,
+    attempts: usize,
+) -> Option<Grid<usize>> {
+    let (shapes, ids) = sort_shapes(&to_shapes(rectangles));
+    if !fits_free_area(rect_mask, &shapes) {
+        return None;
+    }
+    let orientations = to_orientations(&shapes);
+    let mut scratch = Scratch::new(rect_mask);
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..attempts).find_map(|_| {
+        place_rectangles(rect_mask, &orientations, required, &mut scratch, &mut rng)
+            .map(|layout| layout.map(|&id| if id == 0 { 0 } else { ids[id - 1] + 1 }))
+    })
+}
+
+/// The successful layouts of a set of simulations, kept to re-estimate the board after a reveal.
+///
+/// Revealing a cell only rules layouts out, so the layouts consistent with the reveal are a sample
+/// of the revealed board, and re-estimating it needs no new simulations. Each layout takes two bytes
+/// per cell, so keeping the layouts of `n` simulations on a grid of `c` cells costs up to `2 * n * c`
+/// bytes, in exchange for re-estimating in time linear in that size instead of re-simulating.
+#[derive(Clone, Debug)]
+pub struct LayoutSample {
+    /// The number of rows of the grid.
+    rows: usize,
+    /// The number of columns of the grid.
+    cols: usize,
+    /// The index in the caller's pieces of each placed piece, as sorted for the simulations.
+    ids: Vec<usize>,
+    /// The successful layouts consistent with every reveal so far, each the placed piece (0 for
+    /// empty and rect_id for the piece) of each cell in row-major order.
+    layouts: Vec<Box<[u16]>>,
+    /// The number of simulations run.
+    simulations: usize,
+}
+
+impl LayoutSample {
+    /// Returns the number of layouts kept.
+    pub fn len(&self) -> usize {
+        self.layouts.len()
+    }
+
+    /// Checks if no layout is kept.
+    pub fn is_empty(&self) -> bool {
+        self.layouts.is_empty()
+    }
+
+    /// Returns the number of simulations run.
+    pub fn simulations(&self) -> usize {
+        self.simulations
+    }
+
+    /// Keeps only the layouts consistent with a newly revealed cell.
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position of the revealed cell. Must be within the grid.
+    /// * `occupied` - Whether the cell was revealed to be covered by some piece.
+    ///
+    /// # Returns
+    ///
+    /// The sample of the revealed board, out of the same number of simulations.
+    pub fn reveal(&self, pos: &Position, occupied: bool) -> LayoutSample {
+        let cell = pos.y() * self.cols + pos.x();
+        LayoutSample {
+            layouts: self
+                .layouts
+                .iter()
+                .filter(|layout| (layout[cell] != 0) == occupied)
+                .cloned()
+                .collect(),
+            ids: self.ids.clone(),
+            ..*self
+        }
+    }
+
+    /// Estimates the probabilities of the board from the layouts kept.
+    ///
+    /// Unlike [`estimate_with_options`], the counts are not averaged over the symmetries of the
+    /// board, as a reveal generally breaks them. The feasibility is the fraction of all simulations
+    /// whose layout is kept.
+    pub fn to_estimate(&self) -> Estimate {
+        let start = Instant::now();
+        let mut tally = Tally::new(self.rows, self.cols, self.ids.len());
+        let mut positions = Grid::new(self.rows, self.cols, 0);
+        for layout in &self.layouts {
+            for ((_, rect_id), &id) in positions.iter_positions_mut().zip(layout.iter()) {
+                *rect_id = id as usize;
+            }
+            tally.record(&positions);
+        }
+        tally.to_estimate(self.simulations, start, &self.ids, &[], None)
+    }
+}
+
+/// Runs simulations like [`estimate_with_options`] and keeps their successful layouts.
+///
+/// Runs all `options.simulations` simulations, ignoring any tolerance or deadline, and every
+/// rectangle counts toward the estimates of the sample whatever `options.counted` is. The same inputs
+/// and seed always give the same layouts.
+///
+/// # Arguments
+///
+/// * `rect_mask` - A grid mask of rectangles.
+/// * `rectangles` - The pieces to be placed, [`Rectangle`](crate::types::Rectangle)s or any other [`Shape`]s.
+/// * `options` - The options of the simulation.
+///
+/// # Panics
+///
+/// Panics if there are more than [`u16::MAX`] pieces.
+pub fn sample_layouts<P: Clone + Into<Shape>>(
+    rect_mask: &Grid<bool>,
+    rectangles: &[P],
+    options: &EstimateOptions,
+) -> LayoutSample {
+    assert!(
+        rectangles.len() <= u16::MAX as usize,
+        "too many pieces to keep layouts of"
+    );
+    let (shapes, ids) = sort_shapes(&to_shapes(rectangles));
+    let mut sample = LayoutSample {
+        rows: rect_mask.rows(),
+        cols: rect_mask.cols(),
+        ids,
+        layouts: vec![],
+        simulations: 0,
+    };
+    if !fits_free_area(rect_mask, &shapes) {
+        return sample;
+    }
+    let orientations = to_orientations(&shapes);
+    let rect_mask = &block_unreachable(rect_mask, &to_reachable(rect_mask, &orientations));
+    let required = options.required.as_ref();
+    let seed = options.seed.unwrap_or_else(|| rng().random());
+    let simulations = options.simulations;
+
+    sample.layouts = (0..simulations.div_ceil(SIMULATIONS_PER_CHUNK))
+        .into_par_iter()
+        .map_init(
+            || Scratch::new(rect_mask).with_max_candidates(options.max_candidates),
+            |scratch, chunk| {
+                let mut rng = StdRng::seed_from_u64(chunk_seed(seed, chunk));
+                let start = chunk * SIMULATIONS_PER_CHUNK;
+                let end = (start + SIMULATIONS_PER_CHUNK).min(simulations);
+
+                let mut layouts = vec![];
+                for _ in start..end {
+                    if let Some(result) =
+                        place_rectangles(rect_mask, &orientations, required, scratch, &mut rng)
+                    {
+                        let layout = result.iter_positions().map(|(_, &id)| id as u16);
+                        layouts.push(layout.collect());
+                    }
+                }
+                layouts
+            },
+        )
+        .flatten()
+        .collect();
+    sample.simulations = simulations;
+    sample
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Weights;
+
+    /// Lists the orientations of each rectangle for [`place_rectangles`].
+    fn orientations_of(rectangles: &[Rectangle]) -> Vec<Vec<Shape>> {
+        to_orientations(&to_shapes(rectangles))
+    }
+
+    #[test]
+    fn test_place_rectangles_all_placed() {
+        let rect_mask = Grid::new(5, 9, false);
+        let rectangles = vec![
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+        ];
+        let mut scratch = Scratch::new(&rect_mask);
+        let result = place_rectangles(
+            &rect_mask,
+            &orientations_of(&rectangles),
+            None,
+            &mut scratch,
+            &mut rng(),
+        );
+        assert!(result.is_some());
+        let result = result.unwrap();
+        let mut count = 0;
+        for y in 0..result.rows() {
+            for x in 0..result.cols() {
+                let pos = Position::new(x, y);
+                if result[&pos] > 0 {
+                    count += 1;
+                }
+            }
+        }
+        assert!(count == rectangles.len());
+    }
+
+    #[test]
+    fn test_place_rectangles_no_placed() {
+        let mut rect_mask = Grid::new(5, 9, false);
+        for y in 0..rect_mask.rows() {
+            for x in 0..rect_mask.cols() {
+                let pos = Position::new(x, y);
+                rect_mask[&pos] = true;
+            }
+        }
+        let rectangles = vec![
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+        ];
+        let mut scratch = Scratch::new(&rect_mask);
+        let result = place_rectangles(
+            &rect_mask,
+            &orientations_of(&rectangles),
+            None,
+            &mut scratch,
+            &mut rng(),
+        );
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_find_free_positions() {
+        let rect_mask = Grid::from(&["#.#", ".#."][..]);
+
+        let mut positions = vec![Position::new(5, 5)];
+        find_free_positions(&rect_mask, &mut positions);
+        assert_eq!(
+            positions,
+            vec![
+                Position::new(1, 0),
+                Position::new(0, 1),
+                Position::new(2, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_filter_positions_fixed_orientation() {
+        let rect = Rectangle::new_unchecked(1, 2);
+        let positions = vec![Position::new(0, 0), Position::new(1, 0)];
+        let mut placements = vec![];
+        filter_positions(
+            &positions,
+            &Shape::from(&rect).orientations(),
+            (2, 1),
+            &mut placements,
+        );
+        assert_eq!(placements, vec![(Position::new(0, 0), 1)]);
+
+        let fixed = Shape::from(rect.with_rotatable(false));
+        filter_positions(&positions, &fixed.orientations(), (2, 1), &mut placements);
+        assert!(placements.is_empty());
+    }
+
+    #[test]
+    fn test_filter_positions_anchor() {
+        // A T tetromino pointing up has no cell in the top-left corner of its bounding box, so its
+        // placements are anchored at its top cell.
+        let tee = Shape::new(vec![
+            Position::new(1, 0),
+            Position::new(0, 1),
+            Position::new(1, 1),
+            Position::new(2, 1),
+        ])
+        .unwrap()
+        .with_rotatable(false);
+        let positions = vec![
+            Position::new(0, 0),
+            Position::new(1, 0),
+            Position::new(2, 0),
+        ];
+        let mut placements = vec![];
+        filter_positions(&positions, &tee.orientations(), (4, 2), &mut placements);
+        assert_eq!(
+            placements,
+            vec![(Position::new(0, 0), 0), (Position::new(1, 0), 0)]
+        );
+    }
+
+    #[test]
+    fn test_place_rectangles_fixed_orientation() {
+        // A vertical 1x2 only fits a 1x3 strip if it may be rotated.
+        let rect_mask = Grid::new(1, 3, false);
+        let mut scratch = Scratch::new(&rect_mask);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let rotatable = vec![Rectangle::new_unchecked(1, 2)];
+        assert!(
+            place_rectangles(
+                &rect_mask,
+                &orientations_of(&rotatable),
+                None,
+                &mut scratch,
+                &mut rng
+            )
+            .is_some()
+        );
+        let fixed = vec![Rectangle::new_unchecked(1, 2).with_rotatable(false)];
+        assert!(
+            place_rectangles(
+                &rect_mask,
+                &orientations_of(&fixed),
+                None,
+                &mut scratch,
+                &mut rng
+            )
+            .is_none()
+        );
+
+        // A fixed horizontal 2x1 and a 1x1 in a 2x2 grid have four layouts, three covering each cell.
+        let rect_mask = Grid::new(2, 2, false);
+        let fixed = vec![
+            Rectangle::new_unchecked(2, 1).with_rotatable(false),
+            Rectangle::new_unchecked(1, 1),
+        ];
+        let probabilities = estimate_exact(&rect_mask, &fixed).unwrap();
+        assert_eq!(probabilities[&Position::new(0, 0)], 0.75);
+        assert_eq!(probabilities[&Position::new(1, 1)], 0.75);
+    }
+
+    #[test]
+    fn test_place_rectangles_required() {
+        let rect_mask = Grid::new(1, 3, false);
+        let mut required = Grid::new(1, 3, false);
+        required[&Position::new(2, 0)] = true;
+        let rectangles = vec![Rectangle::new_unchecked(2, 1)];
+
+        let mut scratch = Scratch::new(&rect_mask);
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..20 {
+            let result = place_rectangles(
+                &rect_mask,
+                &orientations_of(&rectangles),
+                Some(&required),
+                &mut scratch,
+                &mut rng,
+            );
+            let result = result.unwrap();
+            assert_eq!(result[&Position::new(0, 0)], 0);
+            assert_eq!(result[&Position::new(2, 0)], 1);
+        }
+
+        // A required cell that is also masked can never be covered.
+        let mut rect_mask = Grid::new(1, 3, false);
+        rect_mask[&Position::new(2, 0)] = true;
+        let result = place_rectangles(
+            &rect_mask,
+            &orientations_of(&rectangles),
+            Some(&required),
+            &mut scratch,
+            &mut rng,
+        );
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_estimate_required() {
+        let rect_mask = Grid::new(2, 2, false);
+        let mut required = Grid::new(2, 2, false);
+        required[&Position::new(0, 0)] = true;
+        let rectangles = vec![Rectangle::new_unchecked(2, 1)];
+
+        // Of the four domino layouts, the two covering the top-left cell remain.
+        let exact = estimate_exact_with(
+            &rect_mask,
+            &rectangles,
+            Some(&required),
+            DEFAULT_NODE_BUDGET,
+        );
+        let exact = exact.unwrap();
+        assert_eq!(exact.probabilities[&Position::new(0, 0)], 1.0);
+        assert_eq!(exact.probabilities[&Position::new(1, 1)], 0.0);
+        assert_eq!(exact.probabilities[&Position::new(1, 0)], 0.5);
+
+        let options = EstimateOptions {
+            simulations: 1000,
+            seed: Some(0),
+            required: Some(required),
+            ..Default::default()
+        };
+        let estimate = estimate_with_options(&rect_mask, &rectangles, &options);
+        assert_eq!(estimate.feasibility, 1.0);
+        assert_eq!(estimate.probabilities[&Position::new(0, 0)], 1.0);
+        assert_eq!(estimate.probabilities[&Position::new(1, 1)], 0.0);
+    }
+
+    #[test]
+    fn test_place_rectangles_transposed() {
+        // Below a fixed 3x1 row, the 1x3 rectangle only fits transposed, and the fixed 2x1 after it
+        // must still be placed horizontally.
+        let rect_mask = Grid::new(3, 3, false);
+        let rectangles = vec![
+            Rectangle::new_unchecked(3, 1).with_rotatable(false),
+            Rectangle::new_unchecked(1, 3),
+            Rectangle::new_unchecked(2, 1).with_rotatable(false),
+        ];
+        let mut scratch = Scratch::new(&rect_mask);
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..20 {
+            let result = place_rectangles(
+                &rect_mask,
+                &orientations_of(&rectangles),
+                None,
+                &mut scratch,
+                &mut rng,
+            );
+            let result = result.unwrap();
+            let count = |rect_id| result.count(|&id| id == rect_id);
+            assert_eq!(count(1), 3);
+            assert_eq!(count(2), 3);
+            assert_eq!(count(3), 2);
+            for (pos, &rect_id) in result.iter_positions() {
+                if rect_id == 3 {
+                    let left = Position::new(pos.x().wrapping_sub(1), pos.y());
+                    let right = Position::new(pos.x() + 1, pos.y());
+                    assert!(result.get(&left) == Some(&3) || result.get(&right) == Some(&3));
+                }
+            }
+        }
+        assert_eq!(rectangles[1], Rectangle::new_unchecked(1, 3));
+    }
+
+    #[test]
+    fn test_place_rectangles_backtracks() {
+        // Four dominoes tiling the ring around a blocked center. Greedy placement often leaves an
+        // odd gap, but backtracking always finds a tiling.
+        let mut rect_mask = Grid::new(3, 3, false);
+        rect_mask[&Position::new(1, 1)] = true;
+        let rectangles = vec![Rectangle::new_unchecked(2, 1); 4];
+
+        let mut scratch = Scratch::new(&rect_mask);
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..50 {
+            let result = place_rectangles(
+                &rect_mask,
+                &orientations_of(&rectangles),
+                None,
+                &mut scratch,
+                &mut rng,
+            );
+            let result = result.unwrap();
+            for y in 0..3 {
+                for x in 0..3 {
+                    let pos = Position::new(x, y);
+                    assert_eq!(result[&pos] == 0, pos == Position::new(1, 1));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_place_rectangles_first_fit() {
+        let rect_mask = Grid::new(1, 4, false);
+        let rectangles = vec![Rectangle::new_unchecked(1, 1); 2];
+        let mut scratch = Scratch::new(&rect_mask);
+        for _ in 0..2 {
+            let mut budget = BACKTRACK_BUDGET;
+            let result = place_rectangles_first_fit(
+                &rect_mask,
+                &orientations_of(&rectangles),
+                None,
+                &mut scratch,
+                &mut budget,
+            );
+            let result = result.unwrap();
+            let row: Vec<usize> = (0..4).map(|x| result[&Position::new(x, 0)]).collect();
+            assert_eq!(row, vec![1, 2, 0, 0]);
+        }
+
+        // Too many rectangles: the search ends with budget left, so no layout exists.
+        let too_many = vec![Rectangle::new_unchecked(2, 1); 3];
+        let mut budget = BACKTRACK_BUDGET;
+        let result = place_rectangles_first_fit(
+            &rect_mask,
+            &orientations_of(&too_many),
+            None,
+            &mut scratch,
+            &mut budget,
+        );
+        assert!(result.is_none());
+        assert!(budget > 0);
+    }
+
+    #[test]
+    fn test_place_rectangles_first_fit_agrees() {
+        // On random boards, the deterministic search finds a layout whenever sampling does.
+        let mut rng = StdRng::seed_from_u64(11);
+        let pieces = [
+            Rectangle::new_unchecked(2, 1),
+            Rectangle::new_unchecked(3, 1),
+            Rectangle::new_unchecked(2, 2),
+        ];
+        for _ in 0..200 {
+            let mut rect_mask = Grid::new(3, 4, false);
+            for (_, blocked) in rect_mask.iter_positions_mut() {
+                *blocked = rng.random_bool(0.25);
+            }
+            let count = rng.random_range(1..=4);
+            let rectangles: Vec<Rectangle> = (0..count)
+                .map(|_| pieces[rng.random_range(0..pieces.len())].clone())
+                .collect();
+            let orientations = orientations_of(&rectangles);
+
+            let mut scratch = Scratch::new(&rect_mask);
+            let sampled =
+                place_rectangles(&rect_mask, &orientations, None, &mut scratch, &mut rng).is_some();
+            let mut budget = BACKTRACK_BUDGET;
+            let found = place_rectangles_first_fit(
+                &rect_mask,
+                &orientations,
+                None,
+                &mut scratch,
+                &mut budget,
+            )
+            .is_some();
+            assert!(!sampled || found);
+            if !found && budget > 0 {
+                assert_eq!(
+                    estimate_exact(&rect_mask, &rectangles),
+                    Some(Grid::new(3, 4, 0.0))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_place_rectangles_grouped() {
+        // Placing copies from the candidates of the previous copy samples the same layouts as
+        // listing the candidates of each copy anew, so the dominoes cover each cell as often.
+        let mut rect_mask = Grid::new(3, 4, false);
+        rect_mask[&Position::new(1, 1)] = true;
+        let mut rectangles = vec![Rectangle::new_unchecked(3, 1)];
+        rectangles.extend(vec![Rectangle::new_unchecked(2, 1); 3]);
+        let orientations = orientations_of(&rectangles);
+        let simulations = 20_000;
+
+        let mut scratch = Scratch::new(&rect_mask);
+        let mut tally = |grouped: bool, rng: &mut StdRng| {
+            let mut hits = Grid::new(3, 4, 0.0);
+            for _ in 0..simulations {
+                scratch.reset(&rect_mask, &orientations);
+                assert_eq!(scratch.repeats, vec![false, false, true, true]);
+                if !grouped {
+                    scratch.repeats.fill(false);
+                }
+                let mut budget = BACKTRACK_BUDGET;
+                let placed = place_from(
+                    &mut scratch,
+                    &orientations,
+                    None,
+                    0,
+                    &[],
+                    Some(rng),
+                    &mut budget,
+                );
+                assert!(placed);
+                for (pos, hit) in hits.iter_positions_mut() {
+                    if scratch.positions[&pos] > 1 {
+                        *hit += 1.0 / simulations as f64;
+                    }
+                }
+            }
+            hits
+        };
+        let mut rng = StdRng::seed_from_u64(0);
+        let grouped = tally(true, &mut rng);
+        let ungrouped = tally(false, &mut rng);
+        assert!(grouped.max_abs_diff(&ungrouped).unwrap() < 0.02);
+    }
+
+    #[test]
+    fn test_place_rectangles_reuses_scratch() {
+        let rect_mask = Grid::new(2, 2, false);
+        let mut scratch = Scratch::new(&rect_mask);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        // A failed simulation must not leave anything behind for the next one.
+        let too_many = vec![Rectangle::new_unchecked(2, 1); 3];
+        assert!(
+            place_rectangles(
+                &rect_mask,
+                &orientations_of(&too_many),
+                None,
+                &mut scratch,
+                &mut rng
+            )
+            .is_none()
+        );
+
+        let result = place_rectangles(
+            &rect_mask,
+            &orientations_of(&[Rectangle::new_unchecked(1, 1)]),
+            None,
+            &mut scratch,
+            &mut rng,
+        );
+        let result = result.unwrap();
+        let mut count = 0;
+        for y in 0..2 {
+            for x in 0..2 {
+                count += result[&Position::new(x, y)];
+            }
+        }
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_chunk_seed() {
+        // Adjacent seeds must not shift each other's chunk streams by one.
+        assert_ne!(chunk_seed(1, 1), chunk_seed(2, 0));
+        assert_ne!(chunk_seed(1, 0), chunk_seed(1, 1));
+        assert_eq!(chunk_seed(7, 3), chunk_seed(7, 3));
+    }
+
+    #[test]
+    fn test_tally_merge() {
+        let mut positions = Grid::new(1, 2, 0);
+        positions[&Position::new(0, 0)] = 1;
+
+        let mut first = Tally::new(1, 2, 1);
+        first.record(&positions);
+        let mut second = Tally::new(1, 2, 1);
+        second.record(&positions);
+        second.record(&Grid::new(1, 2, 0));
+
+        let tally = first.merge(second);
+        assert_eq!(tally.placed, 3);
+        assert_eq!(tally.hits[&Position::new(0, 0)], 2.0);
+        assert_eq!(tally.hits[&Position::new(1, 0)], 0.0);
+        assert_eq!(tally.occupants, vec![2.0, 0.0]);
+    }
+
+    #[test]
+    fn test_tally_probabilities() {
+        let tally = Tally::new(1, 2, 1);
+        assert_eq!(tally.probabilities(), Grid::new(1, 2, 0.0));
+
+        // A cell covered in the only successful simulation is certain, not just below 1.
+        let mut positions = Grid::new(1, 2, 0);
+        positions[&Position::new(0, 0)] = 1;
+        let mut tally = Tally::new(1, 2, 1);
+        tally.record(&positions);
+        assert_eq!(tally.probabilities()[&Position::new(0, 0)], 1.0);
+        assert_eq!(tally.probabilities()[&Position::new(1, 0)], 0.0);
+    }
+
+    #[test]
+    fn test_sort_shapes() {
+        let shapes = to_shapes(&[
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(2, 1),
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 2),
+        ]);
+        let (sorted, ids) = sort_shapes(&shapes);
+        assert_eq!(ids, vec![3, 1, 0, 2]);
+        for (shape, id) in sorted.iter().zip(ids) {
+            assert_eq!(*shape, shapes[id]);
+        }
+    }
+
+    #[test]
+    fn test_estimate_with_options_occupants() {
+        // The 2x1 always covers the middle cell of a 1x3 strip, whichever end the 1x1 takes.
+        let rect_mask = Grid::new(1, 3, false);
+        let rectangles = vec![
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(2, 1),
+        ];
+        let options = EstimateOptions {
+            simulations: 1000,
+            seed: Some(0),
+            ..Default::default()
+        };
+        let estimate = estimate_with_options(&rect_mask, &rectangles, &options);
+        assert!(estimate.occupants[&Position::new(1, 0)] == (2, 1.0));
+        for x in [0, 2] {
+            let (rect_id, probability) = estimate.occupants[&Position::new(x, 0)];
+            assert!(rect_id == 1 || rect_id == 2);
+            assert!(probability >= 0.5);
+        }
+
+        let estimate = estimate_with_options(&Grid::new(1, 3, true), &rectangles, &options);
+        assert!(estimate.occupants[&Position::new(1, 0)] == (0, 0.0));
+    }
+
+    #[test]
+    fn test_estimate_exact_occupants() {
+        let rect_mask = Grid::new(1, 3, false);
+        let rectangles = vec![
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(2, 1),
+        ];
+        let estimate =
+            estimate_exact_with(&rect_mask, &rectangles, None, DEFAULT_NODE_BUDGET).unwrap();
+        assert!(estimate.occupants[&Position::new(0, 0)] == (1, 0.5));
+        assert!(estimate.occupants[&Position::new(1, 0)] == (2, 1.0));
+        assert!(estimate.occupants[&Position::new(2, 0)] == (1, 0.5));
+
+        // Identical rectangles share each covered cell evenly.
+        let rectangles = vec![
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+        ];
+        let estimate =
+            estimate_exact_with(&rect_mask, &rectangles, None, DEFAULT_NODE_BUDGET).unwrap();
+        for x in 0..3 {
+            assert!(estimate.occupants[&Position::new(x, 0)] == (1, 1.0 / 3.0));
+        }
+    }
+
+    #[test]
+    fn test_estimate_probabilities() {
+        let rect_mask = Grid::from(
+            &[
+                ".#.......",
+                "......#..",
+                "...##....",
+                "..#......",
+                "..####..#",
+            ][..],
+        );
+
+        let rectangles = vec![
+            Rectangle::new_unchecked(2, 1),
+            Rectangle::new_unchecked(2, 1),
+            Rectangle::new_unchecked(2, 1),
+            Rectangle::new_unchecked(2, 1),
+            Rectangle::new_unchecked(2, 1),
+            Rectangle::new_unchecked(2, 1),
+            Rectangle::new_unchecked(3, 1),
+            Rectangle::new_unchecked(3, 1),
+            Rectangle::new_unchecked(3, 1),
+            Rectangle::new_unchecked(3, 1),
+            Rectangle::new_unchecked(4, 1),
+            Rectangle::new_unchecked(4, 1),
+        ];
+        let probabilities = estimate_probabilities(&rect_mask, &rectangles);
+        assert_eq!(probabilities.rows(), rect_mask.rows());
+        assert_eq!(probabilities.cols(), rect_mask.cols());
+        // Check if all values are between 0 and 1
+        for y in 0..rect_mask.rows() {
+            for x in 0..rect_mask.cols() {
+                let pos = Position::new(x, y);
+                assert!(probabilities[&pos] >= 0.0 && probabilities[&pos] <= 1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_estimate_probabilities_open() {
+        let rect_mask = Grid::new(4, 4, false);
+        let rectangles = vec![
+            Rectangle::new_unchecked(3, 1),
+            Rectangle::new_unchecked(2, 1),
+            Rectangle::new_unchecked(2, 2),
+        ];
+        let shortcut = estimate_probabilities(&rect_mask, &rectangles);
+        let exact = estimate_exact(&rect_mask, &rectangles).unwrap();
+        assert_eq!(shortcut, exact);
+
+        let sampled = estimate_with_options(
+            &rect_mask,
+            &rectangles,
+            &EstimateOptions {
+                seed: Some(2),
+                ..Default::default()
+            },
+        );
+        // Placing the pieces one after another is not quite uniform over the layouts, so the
+        // general path only agrees within a few hundredths.
+        let diff = shortcut.max_abs_diff(&sampled.probabilities).unwrap();
+        assert!(diff < 0.03, "diff = {diff}");
+    }
+
+    #[test]
+    fn test_estimate_probabilities_seeded() {
+        let mut rect_mask = Grid::new(3, 3, false);
+        rect_mask[&Position::new(1, 1)] = true;
+        let rectangles = vec![
+            Rectangle::new_unchecked(2, 1),
+            Rectangle::new_unchecked(1, 1),
+        ];
+
+        let first = estimate_probabilities_seeded(&rect_mask, &rectangles, 42);
+        let second = estimate_probabilities_seeded(&rect_mask, &rectangles, 42);
+        assert!(first == second);
+    }
+
+    /// Checks if two estimates agree on everything but the time they took.
+    fn same_samples(a: &Estimate, b: &Estimate) -> bool {
+        a.probabilities == b.probabilities
+            && a.std_error == b.std_error
+            && a.occupants == b.occupants
+            && a.feasibility == b.feasibility
+            && a.stats.simulations_run == b.stats.simulations_run
+            && a.stats.feasible_samples == b.stats.feasible_samples
+    }
+
+    #[test]
+    fn test_estimate_with_options_seed() {
+        let rect_mask = Grid::new(5, 9, false);
+        let rectangles = vec![
+            Rectangle::new_unchecked(3, 1),
+            Rectangle::new_unchecked(2, 2),
+            Rectangle::new_unchecked(2, 1),
+        ];
+        let options = |seed| EstimateOptions {
+            simulations: 2500,
+            seed: Some(seed),
+            ..Default::default()
+        };
+
+        let first = estimate_with_options(&rect_mask, &rectangles, &options(7));
+        let second = estimate_with_options(&rect_mask, &rectangles, &options(7));
+        let other = estimate_with_options(&rect_mask, &rectangles, &options(8));
+        assert!(same_samples(&first, &second));
+        assert!(!same_samples(&first, &other));
+    }
+
+    #[test]
+    fn test_estimate_with_options_feasibility() {
+        let options = EstimateOptions {
+            simulations: 100,
+            seed: Some(0),
+            ..Default::default()
+        };
+
+        let rect_mask = Grid::new(3, 3, false);
+        let estimate =
+            estimate_with_options(&rect_mask, &[Rectangle::new_unchecked(3, 3)], &options);
+        assert_eq!(estimate.feasibility, 1.0);
+
+        let mut rect_mask = Grid::new(3, 3, false);
+        rect_mask[&Position::new(1, 1)] = true;
+        let estimate =
+            estimate_with_options(&rect_mask, &[Rectangle::new_unchecked(3, 3)], &options);
+        assert_eq!(estimate.feasibility, 0.0);
+    }
+
+    #[test]
+    fn test_estimate_until_converged() {
+        // A single 3x3 in a 3x3 grid is fully determined, so the probabilities never change.
+        let rect_mask = Grid::new(3, 3, false);
+        let estimate =
+            estimate_until_converged(&rect_mask, &[Rectangle::new_unchecked(3, 3)], 1e-3, 100000);
+        assert_eq!(
+            estimate.stats.simulations_run,
+            2 * CHUNKS_PER_BATCH * SIMULATIONS_PER_CHUNK
+        );
+        assert_eq!(estimate.probabilities[&Position::new(1, 1)], 1.0);
+    }
+
+    #[test]
+    fn test_estimate_until_converged_bounded() {
+        // A zero tolerance never converges, so the maximum number of simulations is run.
+        let rect_mask = Grid::new(3, 3, false);
+        let rectangles = vec![Rectangle::new_unchecked(2, 1)];
+        let estimate = estimate_until_converged(&rect_mask, &rectangles, 0.0, 25000);
+        assert_eq!(estimate.stats.simulations_run, 25000);
+    }
+
+    #[test]
+    fn test_estimate_probabilities_within() {
+        // A zero deadline has passed by the end of the first batch, which is all that is run.
+        let rect_mask = Grid::new(3, 3, false);
+        let rectangles = vec![Rectangle::new_unchecked(2, 1)];
+        let estimate = estimate_probabilities_within(&rect_mask, &rectangles, Duration::ZERO);
+        let batch = CHUNKS_PER_BATCH * SIMULATIONS_PER_CHUNK;
+        assert_eq!(estimate.stats.simulations_run, batch);
+        assert_eq!(estimate.stats.feasible_samples, batch);
+        assert_eq!(estimate.feasibility, 1.0);
+        // The partial estimate is still valid: the domino covers two cells, and every cell sometimes.
+        assert!((estimate.probabilities.sum() - 2.0).abs() < 1e-9);
+        for (pos, p) in estimate.probabilities.iter_positions() {
+            assert!((0.0..=1.0).contains(p));
+            assert!(estimate.std_error[&pos] > 0.0);
+        }
+
+        let estimate = estimate_probabilities_within(&rect_mask, &rectangles, Duration::MAX);
+        assert_eq!(estimate.stats.simulations_run, DEFAULT_SIMULATIONS);
+    }
+
+    #[test]
+    fn test_estimate_counted() {
+        let rect_mask = Grid::new(4, 4, false);
+        let rectangles = vec![
+            Rectangle::new_unchecked(3, 1),
+            Rectangle::new_unchecked(1, 1),
+        ];
+        let estimate = |counted: Option<Vec<usize>>| {
+            let options = EstimateOptions {
+                simulations: 5_000,
+                seed: Some(2),
+                counted,
+                ..Default::default()
+            };
+            estimate_with_options(&rect_mask, &rectangles, &options)
+        };
+
+        let all = estimate(None);
+        let long = estimate(Some(vec![0]));
+        let single = estimate(Some(vec![1]));
+        assert_eq!(all.feasibility, 1.0);
+        // Each piece covers its own area in every layout, and together they cover every cell
+        // covered at all.
+        assert!((long.probabilities.sum() - 3.0).abs() < 1e-9);
+        assert!((single.probabilities.sum() - 1.0).abs() < 1e-9);
+        assert_ne!(long.probabilities, all.probabilities);
+        let both = estimate(Some(vec![0, 1]));
+        assert!(
+            both.probabilities
+                .max_abs_diff(&all.probabilities)
+                .is_some_and(|diff| diff < 1e-12)
+        );
+        for (pos, &p) in all.probabilities.iter_positions() {
+            let sum = long.probabilities[&pos] + single.probabilities[&pos];
+            assert!((p - sum).abs() < 1e-12);
+        }
+        // The occupants still count every rectangle.
+        assert_eq!(long.occupants, all.occupants);
+    }
+
+    #[test]
+    fn test_antithetic_fill_bytes() {
+        let mut plain = StdRng::seed_from_u64(1);
+        let mut antithetic = Antithetic(plain.clone());
+        let mut bytes = [0; 12];
+        antithetic.fill_bytes(&mut bytes);
+        let first = (plain.next_u64() ^ (1 << 63)).to_le_bytes();
+        let second = (plain.next_u64() ^ (1 << 63)).to_le_bytes();
+        assert_eq!(bytes[..8], first);
+        assert_eq!(bytes[8..], second[..4]);
+    }
+
+    #[test]
+    fn test_estimate_antithetic() {
+        // On a single row, the candidates half the list away lie on the other half of the row.
+        let rect_mask = Grid::new(1, 10, false);
+        let rectangles = vec![
+            Rectangle::new_unchecked(3, 1),
+            Rectangle::new_unchecked(2, 1),
+        ];
+        let exact = estimate_exact(&rect_mask, &rectangles).unwrap();
+        // The mean squared error of the probabilities over many small estimates, each seeded.
+        let mean_squared_error = |antithetic: bool| {
+            let seeds = 40;
+            let total: f64 = (0..seeds)
+                .map(|seed| {
+                    let options = EstimateOptions {
+                        simulations: 200,
+                        seed: Some(seed),
+                        antithetic,
+                        ..Default::default()
+                    };
+                    let estimate = estimate_with_options(&rect_mask, &rectangles, &options);
+                    estimate
+                        .probabilities
+                        .iter_positions()
+                        .map(|(pos, &p)| (p - exact[&pos]).powi(2))
+                        .sum::<f64>()
+                })
+                .sum();
+            total / seeds as f64
+        };
+
+        let plain = mean_squared_error(false);
+        let antithetic = mean_squared_error(true);
+        assert!(
+            antithetic < plain,
+            "antithetic {antithetic} >= plain {plain}"
+        );
+
+        // Pairing simulations leaves the number run and the default unchanged.
+        let options = EstimateOptions {
+            simulations: 501,
+            seed: Some(1),
+            antithetic: true,
+            ..Default::default()
+        };
+        let estimate = estimate_with_options(&rect_mask, &rectangles, &options);
+        assert_eq!(estimate.stats.simulations_run, 501);
+        assert_eq!(estimate.feasibility, 1.0);
+        assert!(!EstimateOptions::default().antithetic);
+    }
+
+    #[test]
+    fn test_try_estimate() {
+        let rect_mask = Grid::new(2, 3, false);
+        let domino = Rectangle::new_unchecked(2, 1);
+        let dominoes = vec![domino.clone()];
+        let options = EstimateOptions {
+            simulations: 1_000,
+            seed: Some(1),
+            ..Default::default()
+        };
+        let estimate = try_estimate(&rect_mask, &dominoes, &options).unwrap();
+        assert_eq!(estimate.feasibility, 1.0);
+
+        assert_eq!(
+            try_estimate(&Grid::new(0, 3, false), &dominoes, &options).err(),
+            Some(Error::ZeroDimension)
+        );
+        let required = EstimateOptions {
+            required: Some(Grid::new(3, 2, false)),
+            ..options.clone()
+        };
+        assert_eq!(
+            try_estimate(&rect_mask, &dominoes, &required).err(),
+            Some(Error::GridDimensionMismatch {
+                grid: BoardGrid::Required,
+                expected: (2, 3),
+                found: (3, 2),
+            })
+        );
+        let weighted = domino
+            .clone()
+            .with_weights(Weights::new(Grid::new(2, 2, 1.0)).unwrap());
+        assert_eq!(
+            try_estimate(&rect_mask, &[domino.clone(), weighted], &options).err(),
+            Some(Error::GridDimensionMismatch {
+                grid: BoardGrid::Weights { rectangle: 1 },
+                expected: (2, 3),
+                found: (2, 2),
+            })
+        );
+        assert_eq!(
+            try_estimate(&rect_mask, &[Rectangle::new_unchecked(4, 1)], &options).err(),
+            Some(Error::OversizedRectangle { rectangle: 0 })
+        );
+        let counted = EstimateOptions {
+            counted: Some(vec![1]),
+            ..options.clone()
+        };
+        assert_eq!(
+            try_estimate(&rect_mask, &dominoes, &counted).err(),
+            Some(Error::InvalidRectangleIndex {
+                index: 1,
+                rectangles: 1,
+            })
+        );
+        // Four dominoes cannot fit six cells.
+        assert_eq!(
+            try_estimate(&rect_mask, &vec![domino; 4], &options).err(),
+            Some(Error::Infeasible)
+        );
+    }
+
+    #[test]
+    fn test_check_rectangles() {
+        let rect_mask = Grid::new(2, 3, false);
+        let domino = Rectangle::new_unchecked(2, 1);
+        assert_eq!(
+            check_rectangles(&rect_mask, std::slice::from_ref(&domino)),
+            Ok(())
+        );
+        assert_eq!(
+            check_rectangles(&Grid::new(0, 3, false), std::slice::from_ref(&domino)),
+            Err(Error::ZeroDimension)
+        );
+        // A rectangle far too large to list the cells of is still checked.
+        assert_eq!(
+            check_rectangles(
+                &rect_mask,
+                &[domino.clone(), Rectangle::new_unchecked(usize::MAX, 1)]
+            ),
+            Err(Error::OversizedRectangle { rectangle: 1 })
+        );
+
+        let options = EstimateOptions::default();
+        let shapes = to_checked_shapes(&rect_mask, std::slice::from_ref(&domino), &options);
+        assert_eq!(shapes, Ok(vec![Shape::from(&domino)]));
+        let counted = EstimateOptions {
+            counted: Some(vec![5]),
+            ..options
+        };
+        assert_eq!(
+            to_checked_shapes(&rect_mask, std::slice::from_ref(&domino), &counted),
+            Err(Error::InvalidRectangleIndex {
+                index: 5,
+                rectangles: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn test_to_reachable() {
+        // The top-right cell is free but walled in, so no domino can cover it.
+        let rect_mask = Grid::from(&["..#.", "..##", "...."][..]);
+        let rectangles = vec![Rectangle::new_unchecked(2, 1)];
+        let isolated = Position::new(3, 0);
+        let reachable = to_reachable(&rect_mask, &orientations_of(&rectangles));
+        for (pos, &reachable) in reachable.iter_positions() {
+            assert_eq!(reachable, !rect_mask[&pos] && pos != isolated, "{pos:?}");
+        }
+
+        // Blocked, it is never an anchor of the candidate placements.
+        let pruned = block_unreachable(&rect_mask, &reachable);
+        assert!(pruned[&isolated]);
+        let mut free = vec![];
+        find_free_positions(&pruned, &mut free);
+        assert!(!free.contains(&isolated));
+        assert_eq!(free.len(), rect_mask.count(|&blocked| !blocked) - 1);
+
+        let options = EstimateOptions {
+            simulations: 2_000,
+            seed: Some(1),
+            ..Default::default()
+        };
+        let estimate = estimate_with_options(&rect_mask, &rectangles, &options);
+        assert_eq!(estimate.feasibility, 1.0);
+        assert_eq!(estimate.probabilities[&isolated], 0.0);
+        assert_eq!(estimate.occupants[&isolated], (0, 0.0));
+        assert!((estimate.probabilities.sum() - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_failure_rates() {
+        let rect_mask = Grid::new(3, 3, false);
+        let options = EstimateOptions {
+            simulations: 2_000,
+            seed: Some(1),
+            ..Default::default()
+        };
+        let fits = [
+            Rectangle::new_unchecked(2, 1),
+            Rectangle::new_unchecked(1, 1),
+        ];
+        assert_eq!(failure_rates(&rect_mask, &fits, &options), [0.0, 0.0]);
+
+        // The 1x4 piece fits the 3x3 board in no orientation, so it fails every simulation.
+        let oversized = [
+            Rectangle::new_unchecked(2, 1),
+            Rectangle::new_unchecked(1, 4),
+            Rectangle::new_unchecked(1, 1),
+        ];
+        assert_eq!(
+            failure_rates(&rect_mask, &oversized, &options),
+            [0.0, 1.0, 0.0]
+        );
+
+        // Every piece fits, but a required cell is left uncovered, which no piece is blamed for.
+        let mut required = Grid::new(3, 3, false);
+        required[&Position::new(0, 0)] = true;
+        let blocked = Grid::from(&["#..", "...", "..."][..]);
+        let options = EstimateOptions {
+            required: Some(required),
+            ..options
+        };
+        assert_eq!(failure_rates(&blocked, &fits, &options), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_estimate_max_candidates() {
+        // The first domino on a 1x4 strip has 3 placements, and only the middle one leaves no room
+        // for the second.
+        let rect_mask = Grid::new(1, 4, false);
+        let rectangles = vec![Rectangle::new_unchecked(2, 1); 2];
+        let options = EstimateOptions {
+            simulations: 10_000,
+            seed: Some(1),
+            ..Default::default()
+        };
+        let estimate = estimate_with_options(&rect_mask, &rectangles, &options);
+        assert_eq!(estimate.feasibility, 1.0);
+
+        // Capped to one candidate, a simulation cannot backtrack out of the middle placement.
+        let capped = EstimateOptions {
+            max_candidates: Some(1),
+            ..options.clone()
+        };
+        let estimate = estimate_with_options(&rect_mask, &rectangles, &capped);
+        assert!((estimate.feasibility - 2.0 / 3.0).abs() < 0.03);
+        assert_eq!(estimate.probabilities, Grid::new(1, 4, 1.0));
+        let sample = sample_layouts(&rect_mask, &rectangles, &capped);
+        assert_eq!(sample.len(), estimate.stats.feasible_samples);
+
+        let none = EstimateOptions {
+            max_candidates: Some(0),
+            ..options
+        };
+        assert_eq!(
+            try_estimate(&rect_mask, &rectangles, &none).err(),
+            Some(Error::ZeroMaxCandidates)
+        );
+    }
+
+    #[test]
+    fn test_estimate_with_options_simulations() {
+        let rect_mask = Grid::new(3, 3, false);
+        let options = EstimateOptions {
+            simulations: 1234,
+            ..Default::default()
+        };
+        let estimate =
+            estimate_with_options(&rect_mask, &[Rectangle::new_unchecked(2, 1)], &options);
+        assert_eq!(estimate.stats.simulations_run, 1234);
+        assert_eq!(estimate.stats.feasible_samples, 1234);
+    }
+
+    #[test]
+    fn test_to_std_error() {
+        let mut probabilities = Grid::new(1, 3, 0.5);
+        probabilities[&Position::new(1, 0)] = 0.0;
+        probabilities[&Position::new(2, 0)] = 1.0;
+
+        let std_error = to_std_error(&probabilities, 100);
+        assert_eq!(std_error[&Position::new(0, 0)], 0.05);
+        assert_eq!(std_error[&Position::new(1, 0)], 0.0);
+        assert_eq!(std_error[&Position::new(2, 0)], 0.0);
+
+        let std_error = to_std_error(&probabilities, 0);
+        assert_eq!(std_error[&Position::new(0, 0)], 0.0);
+    }
+
+    #[test]
+    fn test_detect_symmetries() {
+        let domino = [Rectangle::new_unchecked(2, 1)];
+        let square = Grid::new(3, 3, false);
+        assert_eq!(
+            detect_symmetries(&square, &domino, None),
+            Symmetry::ALL.to_vec()
+        );
+
+        // Only the symmetries keeping the size of a non-square board are checked.
+        let wide = Grid::new(3, 4, false);
+        assert_eq!(
+            detect_symmetries(&wide, &domino, None),
+            vec![Symmetry::MirrorX, Symmetry::MirrorY, Symmetry::Rotate180]
+        );
+
+        // A horizontal-only domino is mapped onto a vertical one by the diagonal symmetries.
+        let fixed = [Rectangle::new_unchecked(2, 1).with_rotatable(false)];
+        assert_eq!(
+            detect_symmetries(&square, &fixed, None),
+            vec![Symmetry::MirrorX, Symmetry::MirrorY, Symmetry::Rotate180]
+        );
+
+        // A blocked corner is only kept by the reflection across its diagonal.
+        let mut corner = Grid::new(3, 3, false);
+        corner[&Position::new(0, 0)] = true;
+        assert_eq!(
+            detect_symmetries(&corner, &domino, None),
+            vec![Symmetry::Transpose]
+        );
+        let mut required = Grid::new(3, 3, false);
+        required[&Position::new(1, 0)] = true;
+        assert_eq!(
+            detect_symmetries(&square, &domino, Some(&required)),
+            vec![Symmetry::MirrorX]
+        );
+
+        // Mirroring an L tromino gives one of its rotations, but an S tetromino that may not be
+        // mirrored only keeps the rotations.
+        assert_eq!(
+            detect_symmetries(&square, &[l_tromino()], None),
+            Symmetry::ALL.to_vec()
+        );
+        let s_tetromino = Shape::new(vec![
+            Position::new(1, 0),
+            Position::new(2, 0),
+            Position::new(0, 1),
+            Position::new(1, 1),
+        ])
+        .unwrap();
+        assert_eq!(
+            detect_symmetries(&square, &[s_tetromino], None),
+            vec![Symmetry::Rotate180, Symmetry::Rotate90, Symmetry::Rotate270]
+        );
+    }
+
+    #[test]
+    fn test_apply_symmetry() {
+        let grid = Grid::from_rows(vec![vec![1.0, 0.0, 0.0], vec![0.0, 0.0, 0.5]]).unwrap();
+        assert_eq!(apply_symmetry(&grid, &[]), grid);
+        assert_eq!(
+            apply_symmetry(&grid, &[Symmetry::MirrorX]),
+            Grid::from_rows(vec![vec![0.5, 0.0, 0.5], vec![0.25, 0.0, 0.25]]).unwrap()
+        );
+        assert_eq!(
+            apply_symmetry(
+                &grid,
+                &[Symmetry::MirrorX, Symmetry::MirrorY, Symmetry::Rotate180]
+            ),
+            Grid::from_rows(vec![vec![0.375, 0.0, 0.375], vec![0.375, 0.0, 0.375]]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_estimate_symmetric() {
+        // An empty square board is invariant under every symmetry, and so is the estimate.
+        let rect_mask = Grid::new(4, 4, false);
+        let rectangles = vec![
+            Rectangle::new_unchecked(3, 1),
+            Rectangle::new_unchecked(2, 2),
+        ];
+        let options = EstimateOptions {
+            simulations: 20000,
+            seed: Some(5),
+            ..Default::default()
+        };
+        let estimate = estimate_with_options(&rect_mask, &rectangles, &options);
+        let exact = estimate_exact(&rect_mask, &rectangles).unwrap();
+        for (pos, &p) in estimate.probabilities.iter_positions() {
+            for symmetry in Symmetry::ALL {
+                let image = symmetry.apply(&pos, 4, 4);
+                assert!((estimate.probabilities[&image] - p).abs() < 1e-12);
+                assert_eq!(estimate.occupants[&image].0, estimate.occupants[&pos].0);
+            }
+            assert!((p - exact[&pos]).abs() < 0.02);
+        }
+    }
+
+    #[test]
+    fn test_estimate_exact() {
+        let rect_mask = Grid::new(2, 2, false);
+        let probabilities = estimate_exact(&rect_mask, &[Rectangle::new_unchecked(2, 1)]).unwrap();
+        for y in 0..2 {
+            for x in 0..2 {
+                assert_eq!(probabilities[&Position::new(x, y)], 0.5);
+            }
+        }
+
+        // A 1x1 and a 2x1 in a 1x3 strip: the 1x1 is at either end, the 2x1 fills the rest.
+        let rect_mask = Grid::new(1, 3, false);
+        let rectangles = vec![
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(2, 1),
+        ];
+        let probabilities = estimate_exact(&rect_mask, &rectangles).unwrap();
+        for x in 0..3 {
+            assert_eq!(probabilities[&Position::new(x, 0)], 1.0);
+        }
+    }
+
+    #[test]
+    fn test_estimate_exact_identical_rectangles() {
+        // Two 1x1s in a 1x3 strip: three layouts, each cell is covered in two of them.
+        let rect_mask = Grid::new(1, 3, false);
+        let rectangles = vec![
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(1, 1),
+        ];
+        let probabilities = estimate_exact(&rect_mask, &rectangles).unwrap();
+        for x in 0..3 {
+            assert_eq!(probabilities[&Position::new(x, 0)], 2.0 / 3.0);
+        }
+    }
+
+    #[test]
+    fn test_expected_coverage() {
+        // The rectangles of a layout never overlap, so the coverage equals the occupancy.
+        let rect_mask = Grid::new(3, 3, false);
+        let rectangles = vec![
+            Rectangle::new_unchecked(2, 1),
+            Rectangle::new_unchecked(1, 1),
+        ];
+        let options = EstimateOptions {
+            simulations: 2_000,
+            seed: Some(1),
+            ..Default::default()
+        };
+        let sampled = estimate_with_options(&rect_mask, &rectangles, &options);
+        let exact =
+            estimate_exact_with(&rect_mask, &rectangles, None, DEFAULT_NODE_BUDGET).unwrap();
+        for estimate in [sampled, exact] {
+            for (pos, coverage) in estimate.expected_coverage.iter_positions() {
+                assert!((coverage - estimate.probabilities[&pos]).abs() < 1e-9);
+            }
+        }
+
+        assert_eq!(
+            to_expected_coverage(&[], 0, 2, (1, 1))[&Position::new(0, 0)],
+            0.0
+        );
+    }
+
+    /// Creates an L-shaped tromino covering all but the top-right cell of a 2x2 square.
+    fn l_tromino() -> Shape {
+        Shape::new(vec![
+            Position::new(0, 0),
+            Position::new(0, 1),
+            Position::new(1, 1),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_estimate_shapes() {
+        // Each of the four rotations of the L leaves a different cell of a 2x2 grid uncovered.
+        let rect_mask = Grid::new(2, 2, false);
+        let exact = estimate_exact_with(&rect_mask, &[l_tromino()], None, DEFAULT_NODE_BUDGET);
+        assert_eq!(exact.unwrap().probabilities, Grid::new(2, 2, 0.75));
+
+        let options = EstimateOptions {
+            simulations: 20_000,
+            seed: Some(5),
+            ..Default::default()
+        };
+        let estimate = estimate_with_options(&rect_mask, &[l_tromino()], &options);
+        for (_, &p) in estimate.probabilities.iter_positions() {
+            assert!((p - 0.75).abs() < 0.02);
+        }
+
+        // Without rotations, the L only fits one way.
+        let fixed =
+            estimate_with_options(&rect_mask, &[l_tromino().with_rotatable(false)], &options);
+        assert_eq!(fixed.probabilities[&Position::new(1, 0)], 0.0);
+        assert_eq!(fixed.probabilities[&Position::new(1, 1)], 1.0);
+    }
+
+    #[test]
+    fn test_estimate_shapes_reflected() {
+        // An S tetromino fits a 2x3 grid lying down; its mirror image, a Z, needs a reflection.
+        let s_tetromino = Shape::new(vec![
+            Position::new(1, 0),
+            Position::new(2, 0),
+            Position::new(0, 1),
+            Position::new(1, 1),
+        ])
+        .unwrap()
+        .with_rotatable(false);
+        let rect_mask = Grid::new(2, 3, false);
+
+        let exact = |shape: Shape| {
+            estimate_exact_with(&rect_mask, &[shape], None, DEFAULT_NODE_BUDGET)
+                .unwrap()
+                .probabilities
+        };
+        let fixed = exact(s_tetromino.clone());
+        assert_eq!(fixed[&Position::new(0, 0)], 0.0);
+
+        let reflectable = exact(s_tetromino.with_reflectable(true));
+        assert_eq!(reflectable[&Position::new(0, 0)], 0.5);
+        assert_eq!(reflectable[&Position::new(1, 0)], 1.0);
+    }
+
+    #[test]
+    fn test_estimate_exact_infeasible() {
+        let rect_mask = Grid::new(2, 2, true);
+        let estimate =
+            estimate_exact_with(&rect_mask, &[Rectangle::new_unchecked(1, 1)], None, 100);
+        let estimate = estimate.unwrap();
+        assert_eq!(estimate.feasibility, 0.0);
+        assert_eq!(estimate.probabilities[&Position::new(0, 0)], 0.0);
+    }
+
+    #[test]
+    fn test_estimate_exact_over_budget() {
+        let rect_mask = Grid::new(9, 9, false);
+        let rectangles = vec![Rectangle::new_unchecked(1, 1); 10];
+        assert!(estimate_exact_with(&rect_mask, &rectangles, None, 1000).is_none());
+    }
+
+    #[test]
+    fn test_to_entropy() {
+        let probabilities = Grid::new(5, 9, 0.5);
+        let entropy = to_entropy(&probabilities);
+        assert_eq!(entropy.rows(), probabilities.rows());
+        assert_eq!(entropy.cols(), probabilities.cols());
+        // Check if all values are between 0 and 1
+        for y in 0..probabilities.rows() {
+            for x in 0..probabilities.cols() {
+                let pos = Position::new(x, y);
+                assert!(entropy[&pos] >= 0.0 && entropy[&pos] <= 1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_to_entropy_exact() {
+        let mut probabilities = Grid::new(1, 3, 0.5);
+        probabilities[&Position::new(1, 0)] = 0.0;
+        probabilities[&Position::new(2, 0)] = 1.0;
+        let entropy = to_entropy(&probabilities);
+        assert_eq!(entropy[&Position::new(0, 0)], 1.0);
+        assert_eq!(entropy[&Position::new(1, 0)], 0.0);
+        assert_eq!(entropy[&Position::new(2, 0)], 0.0);
+    }
+
+    #[test]
+    fn test_estimate_with_progress() {
+        let rect_mask = Grid::new(3, 3, false);
+        let rectangles = vec![Rectangle::new_unchecked(2, 1)];
+        let options = EstimateOptions {
+            simulations: 25_000,
+            seed: Some(3),
+            ..Default::default()
+        };
+
+        let mut reported = vec![];
+        let estimate = estimate_with_progress(&rect_mask, &rectangles, &options, |estimate| {
+            reported.push(estimate.stats.simulations_run);
+            ControlFlow::Continue(())
+        });
+        assert_eq!(reported, vec![10_000, 20_000]);
+        assert_eq!(estimate.stats.simulations_run, 25_000);
+        assert!(same_samples(
+            &estimate,
+            &estimate_with_options(&rect_mask, &rectangles, &options)
+        ));
+
+        let estimate =
+            estimate_with_progress(
+                &rect_mask,
+                &rectangles,
+                &options,
+                |_| ControlFlow::Break(()),
+            );
+        assert_eq!(estimate.stats.simulations_run, 10_000);
+    }
+
+    #[test]
+    fn test_estimate_overfull() {
+        let mut rect_mask = Grid::new(2, 3, false);
+        rect_mask[&Position::new(0, 0)] = true;
+        let rectangles = vec![
+            Rectangle::new_unchecked(3, 1),
+            Rectangle::new_unchecked(3, 1),
+        ];
+        assert!(!fits_free_area(&rect_mask, &to_shapes(&rectangles)));
+
+        let estimate = estimate_with_options(&rect_mask, &rectangles, &EstimateOptions::default());
+        assert_eq!(estimate.feasibility, 0.0);
+        assert_eq!(estimate.stats.simulations_run, 0);
+        assert!(estimate.probabilities == Grid::new(2, 3, 0.0));
+
+        rect_mask[&Position::new(0, 0)] = false;
+        assert!(fits_free_area(&rect_mask, &to_shapes(&rectangles)));
+    }
+
+    #[test]
+    fn test_estimate_large_grid() {
+        // A 12x12 board with a blocked diagonal, two thirds covered by rectangles.
+        let mut rect_mask = Grid::new(12, 12, false);
+        for i in 0..12 {
+            rect_mask[&Position::new(i, i)] = true;
+        }
+        let mut rectangles = vec![
+            Rectangle::new_unchecked(5, 3),
+            Rectangle::new_unchecked(4, 4),
+            Rectangle::new_unchecked(6, 2),
+            Rectangle::new_unchecked(3, 3),
+            Rectangle::new_unchecked(7, 1),
+        ];
+        rectangles.extend(vec![Rectangle::new_unchecked(2, 2); 4]);
+        rectangles.extend(vec![Rectangle::new_unchecked(3, 1); 4]);
+        assert!(is_large(&rect_mask));
+
+        let options = EstimateOptions {
+            simulations: 500,
+            seed: Some(5),
+            ..Default::default()
+        };
+        let estimate = estimate_with_options(&rect_mask, &rectangles, &options);
+        assert!(estimate.feasibility > 0.9, "{}", estimate.feasibility);
+        let total = estimate.probabilities.sum();
+        let area = rectangles.iter().map(Rectangle::area).sum::<usize>() as f64;
+        assert!((total - area).abs() < 1e-6, "{total}");
+        assert_eq!(estimate.probabilities[&Position::new(3, 3)], 0.0);
+    }
+
+    #[test]
+    fn test_estimate_weighted() {
+        // A 1x1 piece on a 1x6 strip, weighted 100 on the last cell and 1 elsewhere.
+        let rect_mask = Grid::new(1, 6, false);
+        let mut weights = Grid::new(1, 6, 1.0);
+        weights[&Position::new(5, 0)] = 100.0;
+        let rectangles =
+            vec![Rectangle::new_unchecked(1, 1).with_weights(Weights::new(weights).unwrap())];
+        assert!(estimate_exact(&rect_mask, &rectangles).is_none());
+        let symmetries = detect_symmetries(&rect_mask, &rectangles, None);
+        assert!(!symmetries.contains(&Symmetry::MirrorX));
+
+        let options = EstimateOptions {
+            simulations: 20_000,
+            seed: Some(7),
+            ..Default::default()
+        };
+        let estimate = estimate_with_options(&rect_mask, &rectangles, &options);
+        assert_eq!(estimate.feasibility, 1.0);
+        let p = estimate.probabilities[&Position::new(5, 0)];
+        assert!((p - 100.0 / 105.0).abs() < 0.01, "p = {p}");
+        for x in 0..5 {
+            let p = estimate.probabilities[&Position::new(x, 0)];
+            assert!((p - 1.0 / 105.0).abs() < 0.005, "p = {p}");
+        }
+    }
+
+    #[test]
+    fn test_estimate_weighted_zero() {
+        // Placements covering only cells of weight 0 are never drawn, and the others are drawn in
+        // proportion to their mean weights of 0.5 and 1.
+        let rect_mask = Grid::new(1, 4, false);
+        let weights = Grid::from_rows(vec![vec![0.0, 0.0, 1.0, 1.0]]).unwrap();
+        let rectangles =
+            vec![Rectangle::new_unchecked(2, 1).with_weights(Weights::new(weights).unwrap())];
+        let options = EstimateOptions {
+            simulations: 2_000,
+            seed: Some(3),
+            ..Default::default()
+        };
+        let estimate = estimate_with_options(&rect_mask, &rectangles, &options);
+        assert_eq!(estimate.probabilities[&Position::new(0, 0)], 0.0);
+        let p = estimate.probabilities[&Position::new(1, 0)];
+        assert!((p - 1.0 / 3.0).abs() < 0.05, "p = {p}");
+    }
+
+    #[test]
+    fn test_draw_weighted() {
+        let mut rng = StdRng::seed_from_u64(1);
+        assert_eq!(draw_weighted(&[0.0, 0.0], &mut rng), None);
+        assert_eq!(draw_weighted(&[], &mut rng), None);
+        for _ in 0..100 {
+            assert_eq!(draw_weighted(&[0.0, 2.0, 0.0], &mut rng), Some(1));
+        }
+    }
+
+    #[test]
+    fn test_suggest_next() {
+        // A 1x2 domino on a 1x3 row always covers the middle cell, so the ends are the most uncertain.
+        let rect_mask = Grid::new(1, 3, false);
+        let rectangles = vec![Rectangle::new_unchecked(2, 1)];
+        let estimate = estimate_exact_with(&rect_mask, &rectangles, None, DEFAULT_NODE_BUDGET);
+        let (pos, entropy) = suggest_from(&rect_mask, &estimate.unwrap()).unwrap();
+        assert_eq!(pos, Position::new(0, 0));
+        assert!((entropy - 1.0).abs() < 1e-9);
+
+        // Masked cells are never suggested.
+        let mut rect_mask = Grid::new(1, 4, false);
+        rect_mask[&Position::new(0, 0)] = true;
+        let rectangles = vec![Rectangle::new_unchecked(1, 1)];
+        let pos = suggest_next(&rect_mask, &rectangles).unwrap();
+        assert_ne!(pos, Position::new(0, 0));
+    }
+
+    #[test]
+    fn test_sample_layout() {
+        let rect_mask = Grid::from(&["#...", "....", "..#."][..]);
+        let rectangles = vec![
+            Rectangle::new_unchecked(1, 1),
+            Rectangle::new_unchecked(3, 1),
+            Rectangle::new_unchecked(2, 2),
+        ];
+        let layout = sample_layout(&rect_mask, &rectangles, None, 3, DEFAULT_LAYOUT_ATTEMPTS);
+        let layout = layout.unwrap();
+        for (id, rect) in rectangles.iter().enumerate() {
+            // The cells of each rectangle fill a box of its size in either orientation.
+            let cells: Vec<Position> = layout
+                .iter_positions()
+                .filter(|&(_, &cell)| cell == id + 1)
+                .map(|(pos, _)| pos)
+                .collect();
+            let width = cells.iter().map(Position::x).max().unwrap() + 1
+                - cells.iter().map(Position::x).min().unwrap();
+            let height = cells.iter().map(Position::y).max().unwrap() + 1
+                - cells.iter().map(Position::y).min().unwrap();
+            assert_eq!(cells.len(), rect.area());
+            assert!(
+                rect.orientations()
+                    .any(|rect| (rect.width(), rect.height()) == (width, height))
+            );
+            assert!(cells.iter().all(|pos| !rect_mask[pos]));
+        }
+        let again = sample_layout(&rect_mask, &rectangles, None, 3, DEFAULT_LAYOUT_ATTEMPTS);
+        assert_eq!(again.unwrap(), layout);
+
+        // A required cell is always covered.
+        let mut required = Grid::new(3, 4, false);
+        required[&Position::new(3, 0)] = true;
+        let layout = sample_layout(&rect_mask, &rectangles, Some(&required), 3, 100).unwrap();
+        assert_ne!(layout[&Position::new(3, 0)], 0);
+
+        let rectangles = vec![Rectangle::new_unchecked(4, 4)];
+        assert_eq!(sample_layout(&rect_mask, &rectangles, None, 3, 100), None);
+    }
+
+    #[test]
+    fn test_layout_sample_reveal() {
+        let rect_mask = Grid::new(4, 4, false);
+        let rectangles = vec![
+            Rectangle::new_unchecked(2, 1),
+            Rectangle::new_unchecked(3, 1),
+        ];
+        let options = EstimateOptions {
+            simulations: 20_000,
+            seed: Some(2),
+            ..Default::default()
+        };
+        let sample = sample_layouts(&rect_mask, &rectangles, &options);
+        assert_eq!(sample.simulations(), 20_000);
+        assert_eq!(sample.len(), 20_000);
+
+        // The layouts covering a hit are a sample of the revealed board, matching a fresh exact
+        // estimate of it.
+        let hit = Position::new(1, 0);
+        let revealed = sample.reveal(&hit, true);
+        assert!(revealed.len() < sample.len());
+        let incremental = revealed.to_estimate();
+        assert_eq!(incremental.probabilities[&hit], 1.0);
+        assert_eq!(
+            incremental.feasibility,
+            revealed.len() as f64 / sample.len() as f64
+        );
+        let mut required = Grid::new(4, 4, false);
+        required[&hit] = true;
+        let fresh = estimate_exact_with(
+            &rect_mask,
+            &rectangles,
+            Some(&required),
+            DEFAULT_NODE_BUDGET,
+        );
+        let fresh = fresh.unwrap();
+        for (pos, p) in incremental.probabilities.iter_positions() {
+            assert!((p - fresh.probabilities[&pos]).abs() < 0.04, "{pos:?}");
+        }
+        assert_eq!(incremental.occupants.rows(), 4);
+
+        // A miss rules out every layout covering the cell.
+        let rect_mask = Grid::new(1, 4, false);
+        let rectangles = vec![Rectangle::new_unchecked(2, 1)];
+        let options = EstimateOptions {
+            simulations: 3000,
+            seed: Some(4),
+            ..Default::default()
+        };
+        let sample = sample_layouts(&rect_mask, &rectangles, &options);
+        let estimate = sample.reveal(&Position::new(1, 0), false).to_estimate();
+        assert_eq!(estimate.probabilities.to_string(), "0.00 0.00 1.00 1.00");
+        assert!((estimate.feasibility - 1.0 / 3.0).abs() < 0.03);
+        assert!(
+            sample
+                .reveal(&Position::new(1, 0), false)
+                .reveal(&Position::new(2, 0), false)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_suggest_next_tie_break() {
+        // A 1x1 is as likely in every cell of an empty 3x3 board, so all cells tie.
+        let rect_mask = Grid::new(3, 3, false);
+        let rectangles = vec![Rectangle::new_unchecked(1, 1)];
+        let estimate = estimate_exact_with(&rect_mask, &rectangles, None, DEFAULT_NODE_BUDGET);
+        let estimate = estimate.unwrap();
+        let (pos, _) = suggest_from(&rect_mask, &estimate).unwrap();
+        assert_eq!(pos, Position::new(0, 0));
+
+        let revealed = Grid::from(&["#..", "...", "..."][..]);
+        let (pos, _) = suggest_from(&revealed, &estimate).unwrap();
+        assert_eq!(pos, Position::new(1, 0));
+        let revealed = Grid::from(&["###", "...", "..."][..]);
+        let (pos, _) = suggest_from(&revealed, &estimate).unwrap();
+        assert_eq!(pos, Position::new(0, 1));
+    }
+
+    #[test]
+    fn test_compare_suggestions() {
+        let cell = |x, y, entropy| (Position::new(x, y), entropy);
+        let mut cells = vec![
+            cell(0, 0, f64::NAN),
+            cell(1, 1, 0.5),
+            cell(0, 1, 0.5),
+            cell(2, 0, 0.5),
+            cell(1, 0, 0.9),
+            cell(0, 2, 0.0),
+        ];
+        cells.sort_by(|a, b| compare_suggestions(b, a));
+        let positions: Vec<Position> = cells.into_iter().map(|(pos, _)| pos).collect();
+        assert_eq!(
+            positions,
+            vec![
+                Position::new(1, 0),
+                Position::new(2, 0),
+                Position::new(0, 1),
+                Position::new(1, 1),
+                Position::new(0, 2),
+                Position::new(0, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_suggest_next_none() {
+        let rect_mask = Grid::new(2, 2, true);
+        assert!(suggest_next(&rect_mask, &[]).is_none());
+
+        let rect_mask = Grid::new(2, 2, false);
+        let rectangles = vec![Rectangle::new_unchecked(3, 1)];
+        assert!(suggest_next(&rect_mask, &rectangles).is_none());
     }
 }
@@ -0,0 +1,32 @@
+//! Estimates where the hidden rectangles of a panel game are, from the cells revealed so far.
+//!
+//! The [`estimator`] samples or enumerates the layouts of the rectangles within a [`Grid`] mask
+//! and reports the probability that each cell is covered, and [`colors`] turns the probabilities
+//! into heatmaps. [`battleship`] estimates the ships still afloat once some are sunk. Boards that
+//! cannot be estimated are reported as an [`Error`].
+//!
+//! ```
+//! use panel_game::{Grid, Position, Rectangle, estimate_probabilities};
+//!
+//! // A 2x1 rectangle in a 1x3 strip always covers the middle cell.
+//! let mask = Grid::new(1, 3, false);
+//! let probabilities = estimate_probabilities(&mask, &[Rectangle::new(2, 1).unwrap()]);
+//! assert_eq!(probabilities[&Position::new(1, 0)], 1.0);
+//! assert!((probabilities[&Position::new(0, 0)] - 0.5).abs() < 0.05);
+//! ```
+
+pub mod battleship;
+pub mod colors;
+pub mod error;
+pub mod estimator;
+pub mod notation;
+pub mod types;
+pub mod wasm;
+
+pub use colors::ColorMap;
+pub use error::Error;
+pub use estimator::{
+    Estimate, EstimateOptions, estimate_exact, estimate_probabilities, estimate_with_options,
+    try_estimate,
+};
+pub use types::{Grid, Position, Rectangle, Shape, Weights};
@@ -1,19 +1,87 @@
 mod api;
-mod colors;
-mod estimator;
-mod types;
 
-use crate::api::{estimate::estimate, index::index};
-use actix_files::Files;
-use actix_web::web::ServiceConfig;
+use panel_game::{battleship, colors, error, estimator, notation, types};
+
+use crate::api::{
+    batch::estimate_batch,
+    cors::{ALLOWED_ORIGINS, cors},
+    error::{json_config, query_config},
+    estimate::{EstimateCache, estimate, estimate_query},
+    export::estimate_csv,
+    healthz::healthz,
+    heatmap::estimate_png,
+    index::{StaticRoot, configure_frontend},
+    layout::estimate_sample_layout,
+    limits::GridLimits,
+    pool::{EstimatorPool, default_threads},
+    reveal::{LayoutCache, estimate_reveal},
+    schema::schema,
+    stream::estimate_stream,
+    suggest::suggest,
+    validate::validate,
+    version::version,
+};
+use actix_web::web::{self, ServiceConfig};
 use shuttle_actix_web::ShuttleActixWeb;
+use tracing_subscriber::{EnvFilter, fmt, prelude::*};
+
+/// The log filter used unless `RUST_LOG` is set: the service describes every request, and the
+/// estimator every estimate.
+const DEFAULT_LOG_FILTER: &str = "info,panel_game=debug";
+
+/// Logs structured events to standard output, filtered by `RUST_LOG` or [`DEFAULT_LOG_FILTER`].
+fn init_tracing() {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_FILTER));
+    tracing_subscriber::registry()
+        .with(fmt::layer().without_time())
+        .with(filter)
+        .init();
+}
 
 #[shuttle_runtime::main]
 async fn main() -> ShuttleActixWeb<impl FnOnce(&mut ServiceConfig) + Send + Clone + 'static> {
+    init_tracing();
+    let root = StaticRoot::from_env();
+    if !root.is_built() {
+        tracing::warn!(
+            static_root = %root.path().display(),
+            "the frontend is not built, serving a fallback page at /",
+        );
+    }
+    let pool = EstimatorPool::new(default_threads())
+        .map_err(|error| shuttle_runtime::Error::Custom(error.into()))?;
+    let limits =
+        GridLimits::from_env().map_err(|error| shuttle_runtime::Error::Custom(error.into()))?;
+    let cache = LayoutCache::default();
+    let estimates = EstimateCache::default();
     let config = move |cfg: &mut ServiceConfig| {
-        cfg.service(index)
-            .service(estimate)
-            .service(Files::new("/", "frontend/.output/public"));
+        cfg.app_data(web::Data::new(root.clone()))
+            .app_data(web::Data::new(pool.clone()))
+            .app_data(web::Data::new(limits))
+            .app_data(web::Data::new(cache.clone()))
+            .app_data(web::Data::new(estimates.clone()))
+            .app_data(json_config())
+            .app_data(query_config())
+            // Middleware can only wrap a scope here, so every route lives in a root scope.
+            .service(
+                web::scope("")
+                    .wrap(cors(ALLOWED_ORIGINS))
+                    .service(estimate)
+                    .service(estimate_query)
+                    .service(estimate_stream)
+                    .service(estimate_batch)
+                    .service(estimate_png)
+                    .service(estimate_csv)
+                    .service(estimate_sample_layout)
+                    .service(estimate_reveal)
+                    .service(suggest)
+                    .service(validate)
+                    .service(schema)
+                    .service(healthz)
+                    .service(version)
+                    .configure(|cfg| configure_frontend(cfg, &root)),
+            );
     };
 
     Ok(config.into())
@@ -0,0 +1,129 @@
+//! The compact text notation of boards, as used in query strings and by the command line.
+//!
+//! A mask is written as rows of `.` (free) and `#` (masked) cells and the rectangles as
+//! `<width>x<height>`, e.g. `..#,...` and `2x1,3x1`.
+
+use crate::types::{Grid, Rectangle};
+
+/// Parses a mask from rows of `.` (free) and `#` (masked) separated by commas, e.g. `..#,...`.
+pub fn parse_mask(text: &str) -> Result<Grid<bool>, String> {
+    let rows = text
+        .split(',')
+        .enumerate()
+        .map(|(y, row)| {
+            row.chars()
+                .map(|cell| match cell {
+                    '.' => Ok(false),
+                    '#' => Ok(true),
+                    _ => Err(format!("invalid mask cell {cell:?} in row {y}")),
+                })
+                .collect()
+        })
+        .collect::<Result<Vec<Vec<bool>>, String>>()?;
+    Grid::try_from(rows).map_err(|error| format!("invalid mask: {error}"))
+}
+
+/// Parses rectangles written as `<width>x<height>` and separated by commas, e.g. `2x1,3x1`.
+pub fn parse_rectangles(text: &str) -> Result<Vec<Rectangle>, String> {
+    if text.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    text.split(',')
+        .map(|item| {
+            let (width, height) = item
+                .split_once('x')
+                .ok_or_else(|| format!("invalid rectangle {item:?}"))?;
+            let width = width
+                .parse()
+                .map_err(|_| format!("invalid rectangle width in {item:?}"))?;
+            let height = height
+                .parse()
+                .map_err(|_| format!("invalid rectangle height in {item:?}"))?;
+            Rectangle::new(width, height)
+                .ok_or_else(|| format!("rectangle dimensions must be non-zero in {item:?}"))
+        })
+        .collect()
+}
+
+/// Parses a board from the rows of its mask, one per line, followed by a line of its rectangles.
+///
+/// Blank lines and surrounding whitespace are ignored, so
+///
+/// ```text
+/// ..#
+/// ...
+/// 2x1,3x1
+/// ```
+///
+/// is a 3 by 2 board with a masked corner, a 2x1 rectangle and a 3x1 rectangle.
+///
+/// # Returns
+///
+/// The mask and the rectangles of the board, or a description of the first malformed line.
+pub fn parse_board(text: &str) -> Result<(Grid<bool>, Vec<Rectangle>), String> {
+    let mut lines: Vec<&str> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+    let rectangles = lines
+        .pop()
+        .ok_or_else(|| "the board is empty".to_string())?;
+    let mask = parse_mask(&lines.join(","))?;
+    Ok((mask, parse_rectangles(rectangles)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Position;
+
+    #[test]
+    fn test_parse_mask() {
+        let mask = parse_mask("..#,#..").unwrap();
+        assert_eq!(mask.rows(), 2);
+        assert_eq!(mask.cols(), 3);
+        assert!(mask[&Position::new(2, 0)]);
+        assert!(mask[&Position::new(0, 1)]);
+        assert!(!mask[&Position::new(1, 1)]);
+
+        assert!(parse_mask("").is_err());
+        assert!(parse_mask("..,...").is_err());
+        assert!(parse_mask("..x").is_err());
+    }
+
+    #[test]
+    fn test_parse_rectangles() {
+        let rectangles = parse_rectangles("2x1,3x1").unwrap();
+        assert_eq!(
+            rectangles,
+            vec![
+                Rectangle::new_unchecked(2, 1),
+                Rectangle::new_unchecked(3, 1)
+            ]
+        );
+        assert!(parse_rectangles("").unwrap().is_empty());
+
+        assert!(parse_rectangles("2x").is_err());
+        assert!(parse_rectangles("2-1").is_err());
+        assert!(parse_rectangles("0x1").is_err());
+    }
+
+    #[test]
+    fn test_parse_board() {
+        let (mask, rectangles) = parse_board("..#\n...\n\n  2x1,3x1\n").unwrap();
+        assert_eq!(mask.to_string(), "..#\n...");
+        assert_eq!(
+            rectangles,
+            vec![
+                Rectangle::new_unchecked(2, 1),
+                Rectangle::new_unchecked(3, 1)
+            ]
+        );
+
+        assert!(parse_board("").is_err());
+        assert!(parse_board("2x1").is_err());
+        assert!(parse_board("...\n..").is_err());
+    }
+}
@@ -1,19 +1,96 @@
+use rand::{SeedableRng, rngs::StdRng};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
 use crate::colors::{Color, ColorMap, to_rgb};
 
 /// A rectangle.
 #[derive(Serialize, Deserialize, Clone, Hash, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(try_from = "RectangleDims")]
 pub struct Rectangle {
     width: usize,
     height: usize,
+    /// Whether the rectangle may be placed rotated by 90 degrees.
+    rotatable: bool,
+    /// The weights biasing where the rectangle is placed, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    weights: Option<Weights>,
+}
+
+/// The raw dimensions of a [`Rectangle`] before validation.
+#[derive(Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+struct RectangleDims {
+    width: usize,
+    height: usize,
+    #[serde(default = "default_rotatable")]
+    rotatable: bool,
+    #[serde(default)]
+    weights: Option<Grid<f64>>,
+}
+
+/// Rectangles are rotatable unless stated otherwise.
+fn default_rotatable() -> bool {
+    true
+}
+
+impl TryFrom<RectangleDims> for Rectangle {
+    type Error = &'static str;
+
+    fn try_from(dims: RectangleDims) -> Result<Self, Self::Error> {
+        let rect = Rectangle::new(dims.width, dims.height)
+            .ok_or("rectangle dimensions must be non-zero")?
+            .with_rotatable(dims.rotatable);
+        match dims.weights {
+            Some(weights) => Weights::new(weights)
+                .map(|weights| rect.with_weights(weights))
+                .ok_or("rectangle weights must be finite, non-negative and not all zero"),
+            None => Ok(rect),
+        }
+    }
 }
 
 impl Rectangle {
     /// Creates a new [`Rectangle`].
+    ///
+    /// Returns `None` if either the width or the height is zero.
+    pub fn new(width: usize, height: usize) -> Option<Rectangle> {
+        if width == 0 || height == 0 {
+            return None;
+        }
+        Some(Rectangle {
+            width,
+            height,
+            rotatable: true,
+            weights: None,
+        })
+    }
+
+    /// Creates a new [`Rectangle`] without validating its dimensions, for tests.
+    ///
+    /// Both dimensions must be non-zero, as checked by [`Rectangle::new`]: a rectangle without
+    /// cells has no first cell to anchor its placements at.
     #[cfg(test)]
-    pub fn new(width: usize, height: usize) -> Rectangle {
-        Rectangle { width, height }
+    pub(crate) fn new_unchecked(width: usize, height: usize) -> Rectangle {
+        Rectangle {
+            width,
+            height,
+            rotatable: true,
+            weights: None,
+        }
+    }
+
+    /// Returns this [`Rectangle`] with the given rotatability.
+    pub fn with_rotatable(mut self, rotatable: bool) -> Rectangle {
+        self.rotatable = rotatable;
+        self
+    }
+
+    /// Returns this [`Rectangle`] placed according to the given [`Weights`].
+    pub fn with_weights(mut self, weights: Weights) -> Rectangle {
+        self.weights = Some(weights);
+        self
     }
 
     /// Returns the width of this [`Rectangle`].
@@ -26,9 +103,44 @@ impl Rectangle {
         self.height
     }
 
-    /// Swaps the width and height of this [`Rectangle`].
-    pub fn transpose(&mut self) {
-        std::mem::swap(&mut self.width, &mut self.height);
+    /// Returns `true` if this [`Rectangle`] may be placed rotated by 90 degrees.
+    pub fn is_rotatable(&self) -> bool {
+        self.rotatable
+    }
+
+    /// Returns the [`Weights`] biasing where this [`Rectangle`] is placed, if any.
+    pub fn weights(&self) -> Option<&Weights> {
+        self.weights.as_ref()
+    }
+
+    /// Returns this [`Rectangle`] with its width and height swapped.
+    pub fn transposed(&self) -> Rectangle {
+        Rectangle {
+            width: self.height,
+            height: self.width,
+            rotatable: self.rotatable,
+            weights: self.weights.clone(),
+        }
+    }
+
+    /// Returns the distinct orientations this [`Rectangle`] may be placed in.
+    ///
+    /// The first orientation is always the rectangle as given. The transposed rectangle follows if
+    /// the rectangle is rotatable and not a square.
+    pub fn orientations(&self) -> impl Iterator<Item = Rectangle> {
+        let transposed = self.transposed();
+        let rotated = (self.rotatable && transposed != *self).then_some(transposed);
+        std::iter::once(self.clone()).chain(rotated)
+    }
+
+    /// Returns `true` if this [`Rectangle`] fits within a grid of the given size in some
+    /// orientation it may be placed in.
+    ///
+    /// Only the dimensions are compared, so this is cheap even for a rectangle far too large to
+    /// list the cells of.
+    pub fn fits_within(&self, rows: usize, cols: usize) -> bool {
+        (self.width <= cols && self.height <= rows)
+            || (self.rotatable && self.height <= cols && self.width <= rows)
     }
 
     /// Calculates the area of this [`Rectangle`].
@@ -37,8 +149,92 @@ impl Rectangle {
     }
 }
 
+/// The relative weight of each cell of a grid, biasing where a piece is placed.
+///
+/// Only the ratios between the weights matter, so they need not sum to 1: a placement is drawn
+/// with a probability proportional to the mean weight of the cells it covers, among the placements
+/// still possible. Placements covering only cells of weight 0, or cells outside the weight grid,
+/// are never drawn.
+///
+/// Two [`Weights`] are equal if their grids are equal bit for bit.
+#[derive(Clone, Debug)]
+pub struct Weights(Arc<Grid<f64>>);
+
+impl Weights {
+    /// Creates [`Weights`] from a grid of the weight of each cell.
+    ///
+    /// Returns `None` if a weight is negative or not finite, or if every weight is zero.
+    pub fn new(grid: Grid<f64>) -> Option<Weights> {
+        let valid = grid.data.iter().all(|w| w.is_finite() && *w >= 0.0);
+        (valid && grid.sum() > 0.0).then(|| Weights(Arc::new(grid)))
+    }
+
+    /// Returns the grid of the weight of each cell.
+    pub fn grid(&self) -> &Grid<f64> {
+        &self.0
+    }
+
+    /// Returns the weight of placing `shape` with the top-left corner of its bounding box at `pos`,
+    /// the mean weight of the cells it covers.
+    pub fn placement_weight(&self, pos: &Position, shape: &Shape) -> f64 {
+        let total: f64 = shape
+            .cells()
+            .iter()
+            .map(|cell| {
+                pos.offset(cell.x() as isize, cell.y() as isize)
+                    .and_then(|pos| self.0.get(&pos).copied())
+                    .unwrap_or(0.0)
+            })
+            .sum();
+        total / shape.area() as f64
+    }
+}
+
+impl PartialEq for Weights {
+    fn eq(&self, other: &Weights) -> bool {
+        let (a, b) = (self.grid(), other.grid());
+        (a.rows, a.cols) == (b.rows, b.cols)
+            && a.data
+                .iter()
+                .zip(&b.data)
+                .all(|(x, y)| x.to_bits() == y.to_bits())
+    }
+}
+
+impl Eq for Weights {}
+
+impl std::hash::Hash for Weights {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        let grid = self.grid();
+        (grid.rows, grid.cols).hash(state);
+        grid.data.iter().for_each(|w| w.to_bits().hash(state));
+    }
+}
+
+impl Serialize for Weights {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.grid().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for Weights {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        Grid::<f64>::schema_name()
+    }
+
+    fn schema_id() -> std::borrow::Cow<'static, str> {
+        Grid::<f64>::schema_id()
+    }
+
+    fn json_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        Grid::<f64>::json_schema(generator)
+    }
+}
+
 /// A two-dimensional position.
-#[derive(Clone, Hash, Eq, PartialEq, Debug)]
+#[derive(Serialize, Deserialize, Clone, Hash, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Position {
     x: usize,
     y: usize,
@@ -59,27 +255,515 @@ impl Position {
     pub fn y(&self) -> usize {
         self.y
     }
+
+    /// Returns this [`Position`] moved by `dx` columns and `dy` rows, or `None` if either
+    /// coordinate would go below 0 or overflow.
+    pub fn offset(&self, dx: isize, dy: isize) -> Option<Position> {
+        Some(Position::new(
+            self.x.checked_add_signed(dx)?,
+            self.y.checked_add_signed(dy)?,
+        ))
+    }
+
+    /// Returns the positions sharing an edge with this [`Position`], in row-major order.
+    ///
+    /// Positions below 0 are left out, but positions past the far edge of a grid are not, as a
+    /// [`Position`] does not know the size of its grid.
+    pub fn neighbors4(&self) -> impl Iterator<Item = Position> + '_ {
+        [(0, -1), (-1, 0), (1, 0), (0, 1)]
+            .into_iter()
+            .filter_map(|(dx, dy)| self.offset(dx, dy))
+    }
+
+    /// Returns the positions sharing an edge or a corner with this [`Position`], in row-major
+    /// order.
+    ///
+    /// Like [`Position::neighbors4`], positions below 0 are left out.
+    pub fn neighbors8(&self) -> impl Iterator<Item = Position> + '_ {
+        (-1..=1)
+            .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+            .filter(|&offset| offset != (0, 0))
+            .filter_map(|(dx, dy)| self.offset(dx, dy))
+    }
+}
+
+/// A symmetry of a rectangular board other than the identity, mapping its cells onto themselves.
+///
+/// The mirrors and the half turn apply to boards of any size, while the diagonal reflections and
+/// the quarter turns swap the rows with the columns, so a board can only be invariant under them
+/// if it is square.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, Debug)]
+pub enum Symmetry {
+    /// Mirrors left to right.
+    MirrorX,
+    /// Mirrors top to bottom.
+    MirrorY,
+    /// Rotates by 180 degrees.
+    Rotate180,
+    /// Reflects across the diagonal from the top-left corner.
+    Transpose,
+    /// Reflects across the diagonal from the top-right corner.
+    AntiTranspose,
+    /// Rotates by 90 degrees clockwise.
+    Rotate90,
+    /// Rotates by 270 degrees clockwise.
+    Rotate270,
+}
+
+impl Symmetry {
+    /// Every [`Symmetry`].
+    pub const ALL: [Symmetry; 7] = [
+        Symmetry::MirrorX,
+        Symmetry::MirrorY,
+        Symmetry::Rotate180,
+        Symmetry::Transpose,
+        Symmetry::AntiTranspose,
+        Symmetry::Rotate90,
+        Symmetry::Rotate270,
+    ];
+
+    /// Checks if this [`Symmetry`] swaps the rows with the columns.
+    pub fn swaps_axes(&self) -> bool {
+        matches!(
+            self,
+            Symmetry::Transpose
+                | Symmetry::AntiTranspose
+                | Symmetry::Rotate90
+                | Symmetry::Rotate270
+        )
+    }
+
+    /// Maps a position within a `width` by `height` box to its image under this [`Symmetry`].
+    ///
+    /// The image lies within the box with `width` and `height` swapped if the symmetry
+    /// [swaps the axes](Symmetry::swaps_axes).
+    pub fn apply(&self, pos: &Position, width: usize, height: usize) -> Position {
+        let (x, y) = (pos.x(), pos.y());
+        let (flipped_x, flipped_y) = (width - 1 - x, height - 1 - y);
+        match self {
+            Symmetry::MirrorX => Position::new(flipped_x, y),
+            Symmetry::MirrorY => Position::new(x, flipped_y),
+            Symmetry::Rotate180 => Position::new(flipped_x, flipped_y),
+            Symmetry::Transpose => Position::new(y, x),
+            Symmetry::AntiTranspose => Position::new(flipped_y, flipped_x),
+            Symmetry::Rotate90 => Position::new(flipped_y, x),
+            Symmetry::Rotate270 => Position::new(y, flipped_x),
+        }
+    }
+}
+
+/// The state of a cell of a board, as far as it has been revealed.
+#[derive(Serialize, Deserialize, Clone, Copy, Hash, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum CellState {
+    /// Not revealed yet, so it may or may not be covered.
+    Unknown,
+    /// Revealed to be empty, so no rectangle covers it.
+    Empty,
+    /// Revealed to be covered by some rectangle.
+    Occupied,
+}
+
+/// Maps a cell of a grid mask, where `true` marks a blocked cell, to its state.
+impl From<bool> for CellState {
+    fn from(blocked: bool) -> CellState {
+        if blocked {
+            CellState::Empty
+        } else {
+            CellState::Unknown
+        }
+    }
+}
+
+/// A polyomino: a set of cells given by their offsets from the top-left corner of its bounding box.
+///
+/// The cells are normalized, so the topmost row and the leftmost column both have an offset of 0,
+/// and kept in row-major order without duplicates. Two shapes with the same cells are equal
+/// regardless of the order they were given in.
+#[derive(Clone, Hash, Eq, PartialEq, Debug)]
+pub struct Shape {
+    cells: Vec<Position>,
+    width: usize,
+    height: usize,
+    /// Whether the shape may be placed rotated by 90, 180 or 270 degrees.
+    rotatable: bool,
+    /// Whether the shape may be placed mirrored.
+    reflectable: bool,
+    /// The weights biasing where the shape is placed, if any. They are given in grid coordinates,
+    /// so every orientation of the shape shares them.
+    weights: Option<Weights>,
+}
+
+impl From<&Rectangle> for Shape {
+    fn from(rect: &Rectangle) -> Shape {
+        let cells = (0..rect.height())
+            .flat_map(|y| (0..rect.width()).map(move |x| Position::new(x, y)))
+            .collect();
+        Shape {
+            cells,
+            width: rect.width(),
+            height: rect.height(),
+            rotatable: rect.is_rotatable(),
+            reflectable: false,
+            weights: rect.weights().cloned(),
+        }
+    }
+}
+
+impl From<Rectangle> for Shape {
+    fn from(rect: Rectangle) -> Shape {
+        Shape::from(&rect)
+    }
+}
+
+impl Shape {
+    /// Creates a new rotatable, non-reflectable [`Shape`] from the offsets of its cells.
+    ///
+    /// Returns `None` if there are no cells.
+    pub fn new(cells: Vec<Position>) -> Option<Shape> {
+        if cells.is_empty() {
+            return None;
+        }
+        Some(Shape::normalized(cells, true, false))
+    }
+
+    /// Creates a [`Shape`] from non-empty cells, shifting them to the origin and sorting them.
+    fn normalized(mut cells: Vec<Position>, rotatable: bool, reflectable: bool) -> Shape {
+        let min_x = cells.iter().map(Position::x).min().unwrap_or(0);
+        let min_y = cells.iter().map(Position::y).min().unwrap_or(0);
+        for cell in &mut cells {
+            *cell = Position::new(cell.x() - min_x, cell.y() - min_y);
+        }
+        cells.sort_by_key(|cell| (cell.y(), cell.x()));
+        cells.dedup();
+
+        let width = cells.iter().map(|cell| cell.x() + 1).max().unwrap_or(0);
+        let height = cells.iter().map(|cell| cell.y() + 1).max().unwrap_or(0);
+        Shape {
+            cells,
+            width,
+            height,
+            rotatable,
+            reflectable,
+            weights: None,
+        }
+    }
+
+    /// Creates a [`Shape`] with the given cells and the placement rules of this one.
+    fn with_cells(&self, cells: Vec<Position>) -> Shape {
+        Shape {
+            weights: self.weights.clone(),
+            ..Shape::normalized(cells, self.rotatable, self.reflectable)
+        }
+    }
+
+    /// Returns this [`Shape`] with the given rotatability.
+    pub fn with_rotatable(mut self, rotatable: bool) -> Shape {
+        self.rotatable = rotatable;
+        self
+    }
+
+    /// Returns this [`Shape`] with the given reflectability.
+    pub fn with_reflectable(mut self, reflectable: bool) -> Shape {
+        self.reflectable = reflectable;
+        self
+    }
+
+    /// Returns this [`Shape`] placed according to the given [`Weights`].
+    pub fn with_weights(mut self, weights: Weights) -> Shape {
+        self.weights = Some(weights);
+        self
+    }
+
+    /// Returns the [`Weights`] biasing where this [`Shape`] is placed, if any.
+    pub fn weights(&self) -> Option<&Weights> {
+        self.weights.as_ref()
+    }
+
+    /// Returns the offsets of the cells of this [`Shape`], in row-major order.
+    pub fn cells(&self) -> &[Position] {
+        &self.cells
+    }
+
+    /// Returns the width of the bounding box of this [`Shape`].
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of the bounding box of this [`Shape`].
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the number of cells of this [`Shape`].
+    pub fn area(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Returns this [`Shape`] rotated by 90 degrees clockwise.
+    pub fn rotated(&self) -> Shape {
+        let cells = self
+            .cells
+            .iter()
+            .map(|cell| Position::new(self.height - 1 - cell.y(), cell.x()))
+            .collect();
+        self.with_cells(cells)
+    }
+
+    /// Returns this [`Shape`] mirrored left to right.
+    pub fn reflected(&self) -> Shape {
+        let cells = self
+            .cells
+            .iter()
+            .map(|cell| Position::new(self.width - 1 - cell.x(), cell.y()))
+            .collect();
+        self.with_cells(cells)
+    }
+
+    /// Returns this [`Shape`] transformed by a [`Symmetry`].
+    pub fn transformed(&self, symmetry: Symmetry) -> Shape {
+        let cells = self
+            .cells
+            .iter()
+            .map(|cell| symmetry.apply(cell, self.width, self.height))
+            .collect();
+        self.with_cells(cells)
+    }
+
+    /// Returns the distinct orientations this [`Shape`] may be placed in.
+    ///
+    /// The first orientation is always the shape as given, followed by its rotations clockwise if
+    /// it is rotatable, then by the mirrored shape and its rotations if it is reflectable.
+    /// Orientations with the same cells as an earlier one are skipped.
+    pub fn orientations(&self) -> Vec<Shape> {
+        let turns = if self.rotatable { 4 } else { 1 };
+        let mut sides = vec![self.clone()];
+        if self.reflectable {
+            sides.push(self.reflected());
+        }
+
+        let mut orientations: Vec<Shape> = vec![];
+        for side in sides {
+            let mut orientation = side;
+            for _ in 0..turns {
+                let next = orientation.rotated();
+                if orientations
+                    .iter()
+                    .all(|seen| seen.cells != orientation.cells)
+                {
+                    orientations.push(orientation);
+                }
+                orientation = next;
+            }
+        }
+        orientations
+    }
+}
+
+/// An error building a [`Grid<T>`] from existing data.
+#[derive(Debug, PartialEq)]
+pub enum GridError {
+    /// There are no rows, or the rows have no elements.
+    Empty,
+    /// The data does not have the stated number of rows and columns.
+    Mismatch {
+        /// The stated number of rows.
+        rows: usize,
+        /// The stated number of columns.
+        cols: usize,
+    },
+    /// A row has a different length from the first row.
+    Ragged {
+        /// The index of the row.
+        row: usize,
+        /// The length of the first row.
+        expected: usize,
+        /// The length of the row.
+        found: usize,
+    },
+}
+
+impl std::fmt::Display for GridError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GridError::Empty => write!(f, "grid must have at least one row and one column"),
+            GridError::Mismatch { rows, cols } => {
+                write!(f, "grid data must have {rows} rows of {cols} elements")
+            }
+            GridError::Ragged {
+                row,
+                expected,
+                found,
+            } => write!(f, "row {row} has {found} elements, expected {expected}"),
+        }
+    }
 }
 
+impl std::error::Error for GridError {}
+
 /// A two-dimensional grid of values.
-#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+///
+/// The elements are stored row by row in a single buffer. On the wire, a grid is an object with
+/// its `rows`, its `cols`, and its `data` as an array of rows.
+#[derive(PartialEq, Eq, Hash, Debug)]
 pub struct Grid<T> {
+    rows: usize,
+    cols: usize,
+    /// The elements in row-major order, with the element at `(x, y)` at `y * cols + x`.
+    data: Vec<T>,
+}
+
+/// The wire format of a [`Grid<T>`] before validation.
+#[derive(Deserialize)]
+struct GridRows<T> {
     rows: usize,
     cols: usize,
     data: Vec<Vec<T>>,
 }
 
+impl<T> TryFrom<GridRows<T>> for Grid<T> {
+    type Error = GridError;
+
+    fn try_from(grid: GridRows<T>) -> Result<Self, Self::Error> {
+        if grid.data.len() != grid.rows || grid.data.iter().any(|row| row.len() != grid.cols) {
+            return Err(GridError::Mismatch {
+                rows: grid.rows,
+                cols: grid.cols,
+            });
+        }
+        Ok(Grid {
+            rows: grid.rows,
+            cols: grid.cols,
+            data: grid.data.into_iter().flatten().collect(),
+        })
+    }
+}
+
+/// Validates rows like [`Grid::from_rows`] does.
+impl<T: Clone> TryFrom<Vec<Vec<T>>> for Grid<T> {
+    type Error = GridError;
+
+    fn try_from(data: Vec<Vec<T>>) -> Result<Self, Self::Error> {
+        Grid::from_rows(data)
+    }
+}
+
+impl<T: Serialize> Serialize for Grid<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let data: Vec<&[T]> = (0..self.rows).filter_map(|y| self.row(y)).collect();
+        let mut state = serializer.serialize_struct("Grid", 3)?;
+        state.serialize_field("rows", &self.rows)?;
+        state.serialize_field("cols", &self.cols)?;
+        state.serialize_field("data", &data)?;
+        state.end()
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Grid<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let grid = GridRows::deserialize(deserializer)?;
+        Grid::try_from(grid).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(feature = "schema")]
+impl<T: schemars::JsonSchema> schemars::JsonSchema for Grid<T> {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        format!("Grid_of_{}", T::schema_name()).into()
+    }
+
+    fn schema_id() -> std::borrow::Cow<'static, str> {
+        format!("{}::Grid<{}>", module_path!(), T::schema_id()).into()
+    }
+
+    fn json_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "description": "A grid of `rows` rows of `cols` values each, listed row by row in `data`.",
+            "type": "object",
+            "properties": {
+                "rows": {"type": "integer", "minimum": 0},
+                "cols": {"type": "integer", "minimum": 0},
+                "data": {
+                    "type": "array",
+                    "items": {"type": "array", "items": generator.subschema_for::<T>()},
+                },
+            },
+            "required": ["rows", "cols", "data"],
+        })
+    }
+}
+
+impl<T: Clone> Clone for Grid<T> {
+    fn clone(&self) -> Self {
+        Grid {
+            rows: self.rows,
+            cols: self.cols,
+            data: self.data.clone(),
+        }
+    }
+
+    /// Copies `source` into this [`Grid<T>`], reusing the existing allocations where possible.
+    fn clone_from(&mut self, source: &Self) {
+        self.rows = source.rows;
+        self.cols = source.cols;
+        self.data.clone_from(&source.data);
+    }
+}
+
 impl<T> Grid<T>
 where
     T: Clone,
 {
     /// Creates a new [`Grid<T>`].
+    ///
+    /// A zero dimension gives a grid without cells. Use [`Grid::try_new`] for dimensions that come
+    /// from outside, as such a grid has no position to index.
     pub fn new(rows: usize, cols: usize, value: T) -> Grid<T> {
         Grid {
             rows,
             cols,
-            data: vec![vec![value; cols]; rows],
+            data: vec![value; rows * cols],
+        }
+    }
+
+    /// Creates a new [`Grid<T>`] with at least one cell.
+    ///
+    /// Returns [`GridError::Empty`] if either dimension is zero.
+    pub fn try_new(rows: usize, cols: usize, value: T) -> Result<Grid<T>, GridError> {
+        if rows == 0 || cols == 0 {
+            return Err(GridError::Empty);
         }
+        Ok(Grid::new(rows, cols, value))
+    }
+
+    /// Creates a new [`Grid<T>`] from its rows, inferring the dimensions.
+    ///
+    /// Returns an error if there are no rows, the rows are empty, or the rows have unequal lengths.
+    pub fn from_rows(data: Vec<Vec<T>>) -> Result<Grid<T>, GridError> {
+        let cols = data.first().map_or(0, Vec::len);
+        if cols == 0 {
+            return Err(GridError::Empty);
+        }
+        if let Some((row, found)) = data
+            .iter()
+            .map(Vec::len)
+            .enumerate()
+            .find(|&(_, len)| len != cols)
+        {
+            return Err(GridError::Ragged {
+                row,
+                expected: cols,
+                found,
+            });
+        }
+        Ok(Grid {
+            rows: data.len(),
+            cols,
+            data: data.into_iter().flatten().collect(),
+        })
     }
 
     /// Returns the rows of this [`Grid<T>`].
@@ -91,89 +775,372 @@ where
     pub fn cols(&self) -> usize {
         self.cols
     }
+
+    /// Returns this [`Grid<T>`] with its rows and columns swapped.
+    pub fn transpose(&self) -> Grid<T> {
+        let data = (0..self.cols * self.rows)
+            .map(|i| self[&Position::new(i / self.rows, i % self.rows)].clone())
+            .collect();
+        Grid {
+            rows: self.cols,
+            cols: self.rows,
+            data,
+        }
+    }
+
+    /// Returns this [`Grid<T>`] rotated by 90 degrees clockwise.
+    pub fn rotate90(&self) -> Grid<T> {
+        let data = (0..self.cols * self.rows)
+            .map(|i| {
+                let (x, y) = (i % self.rows, i / self.rows);
+                self[&Position::new(y, self.rows - 1 - x)].clone()
+            })
+            .collect();
+        Grid {
+            rows: self.cols,
+            cols: self.rows,
+            data,
+        }
+    }
+
+    /// Sets every element of this [`Grid<T>`] to `value`.
+    pub fn fill(&mut self, value: T) {
+        self.data.fill(value);
+    }
+
+    /// Resizes this [`Grid<T>`] in place, anchored at the top-left corner.
+    ///
+    /// The cells within both the old and the new bounds keep their values, the cells added on the
+    /// right and at the bottom are set to `fill`, and the cells outside the new bounds are dropped.
+    pub fn resize(&mut self, new_rows: usize, new_cols: usize, fill: T) {
+        let mut data = Vec::with_capacity(new_rows * new_cols);
+        for y in 0..new_rows {
+            let kept = if y < self.rows {
+                let start = y * self.cols;
+                &self.data[start..start + self.cols.min(new_cols)]
+            } else {
+                &[]
+            };
+            data.extend_from_slice(kept);
+            data.resize((y + 1) * new_cols, fill.clone());
+        }
+        self.rows = new_rows;
+        self.cols = new_cols;
+        self.data = data;
+    }
+
+    /// Sets every element under a rectangle placed at the given position, as it lies without
+    /// rotating, to `value`.
+    ///
+    /// Returns `false` and leaves this [`Grid<T>`] unchanged if the rectangle extends past the edge,
+    /// so a region is written whole or not at all.
+    pub fn fill_region(&mut self, pos: &Position, rect: &Rectangle, value: T) -> bool {
+        let fits = pos.x() + rect.width() <= self.cols && pos.y() + rect.height() <= self.rows;
+        if !fits {
+            return false;
+        }
+        for y in pos.y()..pos.y() + rect.height() {
+            let start = y * self.cols + pos.x();
+            self.data[start..start + rect.width()].fill(value.clone());
+        }
+        true
+    }
 }
 
 impl Grid<f64> {
+    /// Returns the sum of the elements of this [`Grid<f64>`], 0 if it is empty.
+    pub fn sum(&self) -> f64 {
+        self.data.iter().sum()
+    }
+
+    /// Returns the largest absolute difference between the elements of this [`Grid<f64>`] and
+    /// another at the same positions, 0 if both are empty.
+    ///
+    /// Returns `None` if the grids are of different sizes.
+    pub fn max_abs_diff(&self, other: &Grid<f64>) -> Option<f64> {
+        if (self.rows, self.cols) != (other.rows, other.cols) {
+            return None;
+        }
+        Some(
+            self.data
+                .iter()
+                .zip(&other.data)
+                .map(|(a, b)| (a - b).abs())
+                .fold(0.0, f64::max),
+        )
+    }
+
     /// Convert the elements of this [`Grid<f64>`] to (value, color) pairs.
     pub fn to_value_color_pairs(&self, cmap: &ColorMap) -> Grid<(f64, Color)> {
-        let mut res = Grid::new(self.rows, self.cols, (0.0, Color(0, 0, 0)));
-        for j in 0..self.rows {
-            for i in 0..self.cols {
-                let pos = Position::new(i, j);
-                let elem = self[&pos];
-                let color = to_rgb(elem, cmap);
-                res[&pos] = (elem, color);
-            }
+        self.map(|&elem| (elem, to_rgb(elem, cmap)))
+    }
+
+    /// Convert the elements of this [`Grid<f64>`] to (value, color) pairs, coloring the range from
+    /// the smallest to the largest element with the full color map.
+    ///
+    /// The values are kept as they are. If every element is the same, they are colored like
+    /// [`Grid::to_value_color_pairs`] does.
+    pub fn to_normalized_color_pairs(&self, cmap: &ColorMap) -> Grid<(f64, Color)> {
+        let min = self.data.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = self.data.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        if max - min <= 0.0 {
+            return self.to_value_color_pairs(cmap);
+        }
+        self.map(|&elem| (elem, to_rgb((elem - min) / (max - min), cmap)))
+    }
+
+    /// Convert the elements of this [`Grid<f64>`] to (value, color) pairs, coloring the range from
+    /// 0 to the largest element with the full color map.
+    ///
+    /// The values are kept as they are. If no element is greater than 0, they are colored like
+    /// [`Grid::to_value_color_pairs`] does.
+    pub fn to_rescaled_color_pairs(&self, cmap: &ColorMap) -> Grid<(f64, Color)> {
+        let max = self.data.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        if max <= 0.0 {
+            return self.to_value_color_pairs(cmap);
         }
-        res
+        self.map(|&elem| (elem, to_rgb(elem / max, cmap)))
     }
 }
 
 impl std::ops::Div<f64> for Grid<f64> {
     type Output = Grid<f64>;
 
-    fn div(self, rhs: f64) -> Self::Output {
-        let mut new_data = self.data.clone();
-        for row in &mut new_data {
-            for elem in row {
-                *elem /= rhs;
-            }
+    fn div(mut self, rhs: f64) -> Self::Output {
+        self /= rhs;
+        self
+    }
+}
+
+impl std::ops::DivAssign<f64> for Grid<f64> {
+    fn div_assign(&mut self, rhs: f64) {
+        for elem in &mut self.data {
+            *elem /= rhs;
         }
+    }
+}
+
+impl<T> Grid<T> {
+    /// Returns the offset of `pos` in the buffer, or `None` if it is outside this [`Grid<T>`].
+    fn offset(&self, pos: &Position) -> Option<usize> {
+        (pos.x() < self.cols && pos.y() < self.rows).then(|| pos.y() * self.cols + pos.x())
+    }
+
+    /// Returns a reference to the element at `pos`, or `None` if it is outside this [`Grid<T>`].
+    pub fn get(&self, pos: &Position) -> Option<&T> {
+        self.offset(pos).map(|offset| &self.data[offset])
+    }
+
+    /// Returns a [`Grid<U>`] of the same size with `f` applied to each element.
+    pub fn map<U, F: Fn(&T) -> U>(&self, f: F) -> Grid<U> {
         Grid {
             rows: self.rows,
             cols: self.cols,
-            data: new_data,
+            data: self.data.iter().map(f).collect(),
         }
     }
+
+    /// Returns an iterator over the positions and elements of this [`Grid<T>`] in row-major order.
+    pub fn iter_positions(&self) -> impl Iterator<Item = (Position, &T)> {
+        let cols = self.cols;
+        self.data
+            .iter()
+            .enumerate()
+            .map(move |(i, elem)| (Position::new(i % cols, i / cols), elem))
+    }
+
+    /// Returns an iterator over the positions and mutable elements of this [`Grid<T>`] in row-major order.
+    pub fn iter_positions_mut(&mut self) -> impl Iterator<Item = (Position, &mut T)> {
+        let cols = self.cols;
+        self.data
+            .iter_mut()
+            .enumerate()
+            .map(move |(i, elem)| (Position::new(i % cols, i / cols), elem))
+    }
+
+    /// Returns a mutable reference to the element at `pos`, or `None` if it is outside this [`Grid<T>`].
+    pub fn get_mut(&mut self, pos: &Position) -> Option<&mut T> {
+        self.offset(pos).map(|offset| &mut self.data[offset])
+    }
+
+    /// Returns the elements of row `y` from left to right, or `None` if it is outside this
+    /// [`Grid<T>`].
+    pub fn row(&self, y: usize) -> Option<&[T]> {
+        (y < self.rows).then(|| &self.data[y * self.cols..(y + 1) * self.cols])
+    }
+
+    /// Returns an iterator over the elements of column `x` from top to bottom, or `None` if it is
+    /// outside this [`Grid<T>`].
+    ///
+    /// Columns are not contiguous in the buffer, so unlike [`Grid::row`] they are not a slice.
+    pub fn col(&self, x: usize) -> Option<impl Iterator<Item = &T>> {
+        let column = self.data.get(x..).unwrap_or_default();
+        (x < self.cols).then(|| column.iter().step_by(self.cols))
+    }
+
+    /// Returns the number of elements of this [`Grid<T>`] satisfying `pred`.
+    pub fn count<F: Fn(&T) -> bool>(&self, pred: F) -> usize {
+        self.data.iter().filter(|elem| pred(elem)).count()
+    }
 }
 
-impl std::ops::DivAssign<f64> for Grid<f64> {
-    fn div_assign(&mut self, rhs: f64) {
-        for row in &mut self.data {
-            for elem in row {
-                *elem /= rhs;
-            }
-        }
+impl<T> std::ops::Index<&Position> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, index: &Position) -> &Self::Output {
+        self.get(index).expect("position out of range of the grid")
     }
 }
 
-macro_rules! impl_index {
-    ($t:ty) => {
-        impl std::ops::Index<&Position> for Grid<$t> {
-            type Output = $t;
+impl<T> std::ops::IndexMut<&Position> for Grid<T> {
+    fn index_mut(&mut self, index: &Position) -> &mut Self::Output {
+        let offset = self
+            .offset(index)
+            .expect("position out of range of the grid");
+        &mut self.data[offset]
+    }
+}
 
-            fn index(&self, index: &Position) -> &Self::Output {
-                &self.data[index.y()][index.x()]
+impl<T> Grid<T> {
+    /// Writes the rows of this [`Grid<T>`] on separate lines, each cell formatted with `cell`.
+    fn fmt_rows(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        separator: &str,
+        cell: impl Fn(&mut std::fmt::Formatter<'_>, &T) -> std::fmt::Result,
+    ) -> std::fmt::Result {
+        for (pos, elem) in self.iter_positions() {
+            if pos.x() > 0 {
+                f.write_str(separator)?;
+            } else if pos.y() > 0 {
+                writeln!(f)?;
             }
+            cell(f, elem)?;
         }
+        Ok(())
+    }
+}
 
-        impl std::ops::IndexMut<&Position> for Grid<$t> {
-            fn index_mut(&mut self, index: &Position) -> &mut Self::Output {
-                &mut self.data[index.y()][index.x()]
-            }
+/// Renders a mask as rows of `#` for `true` and `.` for `false`.
+impl std::fmt::Display for Grid<bool> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_rows(f, "", |f, &elem| f.write_str(if elem { "#" } else { "." }))
+    }
+}
+
+/// Renders the numbers right-aligned to the widest one and separated by spaces.
+impl std::fmt::Display for Grid<usize> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let width = self.data.iter().map(|elem| elem.to_string().len()).max();
+        let width = width.unwrap_or(0);
+        self.fmt_rows(f, " ", |f, elem| write!(f, "{elem:>width$}"))
+    }
+}
+
+/// Renders the values with two decimals, separated by spaces.
+impl std::fmt::Display for Grid<f64> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_rows(f, " ", |f, elem| write!(f, "{elem:.2}"))
+    }
+}
+
+/// Parses a mask from rows of `#` for `true` and `.` for `false`, the inverse of its [`Display`].
+///
+/// Meant for boards written out in code, such as test fixtures. Use
+/// [`parse_mask`](crate::notation::parse_mask) to parse untrusted input.
+///
+/// # Panics
+///
+/// Panics if there are no rows, the rows are empty or have unequal lengths, or a cell is neither
+/// `#` nor `.`.
+///
+/// [`Display`]: std::fmt::Display
+impl From<&[&str]> for Grid<bool> {
+    fn from(rows: &[&str]) -> Grid<bool> {
+        let rows = rows
+            .iter()
+            .map(|row| {
+                row.chars()
+                    .map(|cell| match cell {
+                        '#' => true,
+                        '.' => false,
+                        _ => panic!("invalid mask cell {cell:?}"),
+                    })
+                    .collect()
+            })
+            .collect::<Vec<Vec<bool>>>();
+        Grid::try_from(rows).unwrap_or_else(|error| panic!("invalid mask: {error}"))
+    }
+}
+
+impl Grid<bool> {
+    /// Creates a grid mask with a fraction of its cells blocked at random, for test and demo
+    /// boards.
+    ///
+    /// # Arguments
+    ///
+    /// * `rows` - The number of rows.
+    /// * `cols` - The number of columns.
+    /// * `blocked_fraction` - The fraction of the cells to block, clamped to between 0 and 1.
+    /// * `seed` - The seed of the random number generator.
+    ///
+    /// # Returns
+    ///
+    /// A grid mask where exactly `blocked_fraction` of the cells, rounded to the nearest cell, are
+    /// `true`. The same arguments always give the same mask.
+    pub fn random_mask(rows: usize, cols: usize, blocked_fraction: f64, seed: u64) -> Grid<bool> {
+        let cells = rows * cols;
+        let blocked = (blocked_fraction.clamp(0.0, 1.0) * cells as f64).round() as usize;
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut mask = Grid::new(rows, cols, false);
+        for cell in rand::seq::index::sample(&mut rng, cells, blocked.min(cells)) {
+            mask.data[cell] = true;
         }
-    };
+        mask
+    }
 }
 
-impl_index!(bool);
-impl_index!(usize);
-impl_index!(f64);
-impl_index!((f64, Color));
+impl From<&Grid<bool>> for Grid<CellState> {
+    fn from(mask: &Grid<bool>) -> Grid<CellState> {
+        mask.map(|&blocked| CellState::from(blocked))
+    }
+}
+
+impl Grid<CellState> {
+    /// Returns the grid mask of the cells no rectangle may cover, those revealed to be empty.
+    pub fn to_mask(&self) -> Grid<bool> {
+        self.map(|&state| state == CellState::Empty)
+    }
+
+    /// Returns a grid where `true` marks a cell that must be covered, one revealed to be occupied.
+    pub fn to_required(&self) -> Grid<bool> {
+        self.map(|&state| state == CellState::Occupied)
+    }
+
+    /// Returns a grid where `true` marks a revealed cell, empty or occupied.
+    pub fn to_revealed(&self) -> Grid<bool> {
+        self.map(|&state| state != CellState::Unknown)
+    }
+}
 
 impl<T: PartialEq> Grid<T> {
-    /// Returns `true` if all elements in the specified rectangular area are the same as the given `value`.
-    pub fn all(&self, pos: &Position, rect: &Rectangle, value: &T) -> bool {
-        if pos.x() + rect.width() > self.cols || pos.y() + rect.height() > self.rows {
+    /// Returns `true` if all elements under the cells of a shape placed at the given position are
+    /// the same as the given `value`.
+    ///
+    /// Cells outside this [`Grid<T>`] never match, so a shape extending past the edge gives `false`.
+    pub fn all(&self, pos: &Position, shape: &Shape, value: &T) -> bool {
+        let fits = |start: usize, len: usize, end: usize| {
+            start.checked_add(len).is_some_and(|last| last <= end)
+        };
+        if !fits(pos.x(), shape.width(), self.cols) || !fits(pos.y(), shape.height(), self.rows) {
             return false;
         }
-        for y in pos.y()..pos.y() + rect.height() {
-            for x in pos.x()..pos.x() + rect.width() {
-                if &self.data[y][x] != value {
-                    return false;
-                }
-            }
-        }
-        true
+        // The cells are in row-major order, so each row of the shape reads one row slice.
+        shape.cells().chunk_by(|a, b| a.y() == b.y()).all(|cells| {
+            let start = (pos.y() + cells[0].y()) * self.cols + pos.x();
+            let row = &self.data[start..start + shape.width()];
+            cells.iter().all(|cell| row[cell.x()] == *value)
+        })
     }
 }
 
@@ -183,11 +1150,292 @@ mod tests {
 
     #[test]
     fn test_rectangle_new() {
-        let rect = Rectangle::new(3, 4);
+        let rect = Rectangle::new(3, 4).unwrap();
         assert_eq!(rect.width(), 3);
         assert_eq!(rect.height(), 4);
     }
 
+    #[test]
+    fn test_rectangle_new_zero_dimension() {
+        assert!(Rectangle::new(0, 4).is_none());
+        assert!(Rectangle::new(3, 0).is_none());
+        assert!(Rectangle::new(0, 0).is_none());
+    }
+
+    #[test]
+    fn test_rectangle_deserialize_zero_dimension() {
+        let rect: Result<Rectangle, _> = serde_json::from_str(r#"{"width": 0, "height": 2}"#);
+        assert!(rect.is_err());
+        let rect: Rectangle = serde_json::from_str(r#"{"width": 1, "height": 2}"#).unwrap();
+        assert_eq!(rect, Rectangle::new_unchecked(1, 2));
+    }
+
+    #[test]
+    fn test_rectangle_deserialize_rotatable() {
+        let rect: Rectangle = serde_json::from_str(r#"{"width": 1, "height": 2}"#).unwrap();
+        assert!(rect.is_rotatable());
+        let rect: Rectangle =
+            serde_json::from_str(r#"{"width": 1, "height": 2, "rotatable": false}"#).unwrap();
+        assert!(!rect.is_rotatable());
+        assert_eq!(rect, Rectangle::new_unchecked(1, 2).with_rotatable(false));
+    }
+
+    #[test]
+    fn test_rectangle_deserialize_weights() {
+        let rect: Rectangle = serde_json::from_str(
+            r#"{"width": 1, "height": 1, "weights": {"rows": 1, "cols": 2, "data": [[1.0, 3.0]]}}"#,
+        )
+        .unwrap();
+        let weights = Grid::from_rows(vec![vec![1.0, 3.0]]).unwrap();
+        assert_eq!(rect.weights().unwrap().grid(), &weights);
+        assert_eq!(
+            serde_json::to_value(&rect).unwrap()["weights"],
+            serde_json::to_value(&weights).unwrap()
+        );
+
+        let rect: Result<Rectangle, _> = serde_json::from_str(
+            r#"{"width": 1, "height": 1, "weights": {"rows": 1, "cols": 2, "data": [[0.0, 0.0]]}}"#,
+        );
+        assert!(rect.is_err());
+    }
+
+    #[test]
+    fn test_weights_new() {
+        assert!(Weights::new(Grid::from_rows(vec![vec![0.0, 0.5]]).unwrap()).is_some());
+        assert!(Weights::new(Grid::new(2, 2, 0.0)).is_none());
+        assert!(Weights::new(Grid::from_rows(vec![vec![1.0, -0.5]]).unwrap()).is_none());
+        assert!(Weights::new(Grid::from_rows(vec![vec![1.0, f64::NAN]]).unwrap()).is_none());
+        assert!(Weights::new(Grid::from_rows(vec![vec![1.0, f64::INFINITY]]).unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_weights_placement_weight() {
+        let weights = Weights::new(Grid::from_rows(vec![vec![1.0, 3.0, 8.0]]).unwrap()).unwrap();
+        let domino: Shape = (&Rectangle::new_unchecked(2, 1)).into();
+        assert_eq!(weights.placement_weight(&Position::new(0, 0), &domino), 2.0);
+        assert_eq!(weights.placement_weight(&Position::new(1, 0), &domino), 5.5);
+        // Cells outside the weight grid weigh 0.
+        assert_eq!(weights.placement_weight(&Position::new(2, 0), &domino), 4.0);
+        assert_eq!(weights.placement_weight(&Position::new(0, 1), &domino), 0.0);
+
+        let weighted = domino.clone().with_weights(weights.clone());
+        assert_eq!(weighted.rotated().weights(), Some(&weights));
+        assert_ne!(weighted, domino);
+    }
+
+    #[test]
+    fn test_rectangle_orientations() {
+        let rect = Rectangle::new_unchecked(3, 1);
+        assert_eq!(rect.transposed(), Rectangle::new_unchecked(1, 3));
+        assert_eq!(rect, Rectangle::new_unchecked(3, 1));
+
+        let orientations: Vec<Rectangle> = rect.orientations().collect();
+        assert_eq!(orientations, vec![rect.clone(), rect.transposed()]);
+
+        let fixed = rect.with_rotatable(false);
+        assert_eq!(
+            fixed.orientations().collect::<Vec<_>>(),
+            vec![fixed.clone()]
+        );
+
+        let square = Rectangle::new_unchecked(2, 2);
+        assert_eq!(square.orientations().count(), 1);
+        assert_eq!(Rectangle::new_unchecked(2, 3).orientations().count(), 2);
+
+        // The placement loop tries the orientations of the shape of a rectangle, which are as
+        // distinct, so a square is never tried twice.
+        assert_eq!(Shape::from(&square).orientations().len(), 1);
+        assert_eq!(
+            Shape::from(Rectangle::new_unchecked(2, 3))
+                .orientations()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_cell_states() {
+        let mut mask = Grid::new(1, 3, false);
+        mask[&Position::new(0, 0)] = true;
+        let mut states = Grid::from(&mask);
+        assert_eq!(states[&Position::new(0, 0)], CellState::Empty);
+        assert_eq!(states[&Position::new(1, 0)], CellState::Unknown);
+        assert_eq!(states.to_mask(), mask);
+        assert_eq!(states.to_required(), Grid::new(1, 3, false));
+
+        states[&Position::new(2, 0)] = CellState::Occupied;
+        assert_eq!(states.to_mask(), mask);
+        assert_eq!(states.to_required().to_string(), "..#");
+        assert_eq!(states.to_revealed().to_string(), "#.#");
+        assert_eq!(
+            serde_json::to_string(&CellState::Occupied).unwrap(),
+            r#""occupied""#
+        );
+    }
+
+    /// Creates an L-shaped tetromino: three cells in a column and one to the right of the bottom.
+    fn l_tetromino() -> Shape {
+        Shape::new(vec![
+            Position::new(0, 0),
+            Position::new(0, 1),
+            Position::new(0, 2),
+            Position::new(1, 2),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_shape_new() {
+        let shape = Shape::new(vec![
+            Position::new(3, 2),
+            Position::new(2, 2),
+            Position::new(2, 3),
+            Position::new(2, 2),
+        ])
+        .unwrap();
+        assert_eq!(
+            shape.cells(),
+            &[
+                Position::new(0, 0),
+                Position::new(1, 0),
+                Position::new(0, 1)
+            ]
+        );
+        assert_eq!((shape.width(), shape.height(), shape.area()), (2, 2, 3));
+        assert_eq!(Shape::new(vec![]), None);
+    }
+
+    #[test]
+    fn test_rectangle_area() {
+        let rect = Rectangle::new_unchecked(3, 2);
+        assert_eq!(rect.area(), 6);
+        assert_eq!(Shape::from(&rect).area(), rect.area());
+    }
+
+    #[test]
+    fn test_rectangle_fits_within() {
+        let rect = Rectangle::new_unchecked(3, 1);
+        assert!(rect.fits_within(1, 3));
+        assert!(rect.fits_within(3, 1));
+        assert!(!rect.with_rotatable(false).fits_within(3, 1));
+        assert!(!Rectangle::new_unchecked(usize::MAX, 1).fits_within(9, 9));
+    }
+
+    #[test]
+    fn test_shape_from_rectangle() {
+        let shape = Shape::from(&Rectangle::new_unchecked(2, 1));
+        assert_eq!(shape.cells(), &[Position::new(0, 0), Position::new(1, 0)]);
+        assert_eq!(
+            shape,
+            Shape::new(vec![Position::new(1, 0), Position::new(0, 0)]).unwrap()
+        );
+
+        let orientations = shape.orientations();
+        assert_eq!(orientations.len(), 2);
+        assert_eq!(
+            orientations[1],
+            Shape::from(&Rectangle::new_unchecked(1, 2))
+        );
+
+        let fixed = Shape::from(&Rectangle::new_unchecked(2, 1).with_rotatable(false));
+        assert_eq!(fixed.orientations().len(), 1);
+    }
+
+    #[test]
+    fn test_shape_rotated() {
+        let rotated = l_tetromino().rotated();
+        assert_eq!((rotated.width(), rotated.height()), (3, 2));
+        assert_eq!(
+            rotated.cells(),
+            &[
+                Position::new(0, 0),
+                Position::new(1, 0),
+                Position::new(2, 0),
+                Position::new(0, 1),
+            ]
+        );
+        assert_eq!(
+            l_tetromino().rotated().rotated().rotated().rotated(),
+            l_tetromino()
+        );
+    }
+
+    #[test]
+    fn test_symmetry_apply() {
+        // The top-left corner of a 3 by 2 box.
+        let pos = Position::new(0, 0);
+        assert_eq!(Symmetry::MirrorX.apply(&pos, 3, 2), Position::new(2, 0));
+        assert_eq!(Symmetry::MirrorY.apply(&pos, 3, 2), Position::new(0, 1));
+        assert_eq!(Symmetry::Rotate180.apply(&pos, 3, 2), Position::new(2, 1));
+        assert_eq!(Symmetry::Transpose.apply(&pos, 3, 2), Position::new(0, 0));
+        assert_eq!(
+            Symmetry::AntiTranspose.apply(&pos, 3, 2),
+            Position::new(1, 2)
+        );
+        assert_eq!(Symmetry::Rotate90.apply(&pos, 3, 2), Position::new(1, 0));
+        assert_eq!(Symmetry::Rotate270.apply(&pos, 3, 2), Position::new(0, 2));
+
+        let swapping: Vec<Symmetry> = Symmetry::ALL
+            .into_iter()
+            .filter(Symmetry::swaps_axes)
+            .collect();
+        assert_eq!(swapping.len(), 4);
+    }
+
+    #[test]
+    fn test_shape_transformed() {
+        let shape = l_tetromino();
+        assert_eq!(shape.transformed(Symmetry::Rotate90), shape.rotated());
+        assert_eq!(shape.transformed(Symmetry::MirrorX), shape.reflected());
+        assert_eq!(
+            shape.transformed(Symmetry::Rotate180),
+            shape.rotated().rotated()
+        );
+        assert_eq!(
+            shape.transformed(Symmetry::Transpose),
+            shape.rotated().reflected()
+        );
+    }
+
+    #[test]
+    fn test_shape_orientations() {
+        let shape = l_tetromino();
+        let orientations = shape.orientations();
+        assert_eq!(orientations.len(), 4);
+        assert_eq!(orientations[0], shape);
+
+        assert_eq!(shape.clone().with_rotatable(false).orientations().len(), 1);
+        assert_eq!(shape.clone().with_reflectable(true).orientations().len(), 8);
+        assert_eq!(
+            shape
+                .with_rotatable(false)
+                .with_reflectable(true)
+                .orientations()
+                .len(),
+            2
+        );
+
+        // A T tetromino is symmetric, so mirroring it adds no orientation.
+        let tee = Shape::new(vec![
+            Position::new(0, 0),
+            Position::new(1, 0),
+            Position::new(2, 0),
+            Position::new(1, 1),
+        ])
+        .unwrap()
+        .with_reflectable(true);
+        assert_eq!(tee.orientations().len(), 4);
+    }
+
+    #[test]
+    fn test_grid_all_shape() {
+        let mut grid: Grid<bool> = Grid::new(3, 3, false);
+        grid[&Position::new(1, 0)] = true;
+        assert!(grid.all(&Position::new(0, 0), &l_tetromino(), &false));
+        assert!(!grid.all(&Position::new(1, 0), &l_tetromino(), &false));
+        assert!(!grid.all(&Position::new(2, 0), &l_tetromino(), &false));
+    }
+
     #[test]
     fn test_position_new() {
         let pos = Position::new(5, 6);
@@ -195,32 +1443,400 @@ mod tests {
         assert_eq!(pos.y(), 6);
     }
 
+    #[test]
+    fn test_position_offset() {
+        let pos = Position::new(2, 3);
+        assert_eq!(pos.offset(1, -2), Some(Position::new(3, 1)));
+        assert_eq!(pos.offset(-2, -3), Some(Position::new(0, 0)));
+        assert_eq!(pos.offset(-3, 0), None);
+        assert_eq!(pos.offset(0, -4), None);
+        assert_eq!(Position::new(usize::MAX, 0).offset(1, 0), None);
+
+        // Nothing lies above or to the left of the origin.
+        let origin = Position::new(0, 0);
+        assert_eq!(origin.offset(-1, 0), None);
+        assert_eq!(origin.offset(0, -1), None);
+        assert_eq!(
+            origin.neighbors4().collect::<Vec<_>>(),
+            [Position::new(1, 0), Position::new(0, 1)]
+        );
+        assert_eq!(
+            origin.neighbors8().collect::<Vec<_>>(),
+            [
+                Position::new(1, 0),
+                Position::new(0, 1),
+                Position::new(1, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_position_neighbors() {
+        let pos = Position::new(1, 1);
+        assert_eq!(
+            pos.neighbors4().collect::<Vec<_>>(),
+            [
+                Position::new(1, 0),
+                Position::new(0, 1),
+                Position::new(2, 1),
+                Position::new(1, 2),
+            ]
+        );
+        let neighbors: Vec<Position> = pos.neighbors8().collect();
+        assert_eq!(neighbors.len(), 8);
+        assert_eq!(neighbors[0], Position::new(0, 0));
+        assert_eq!(neighbors[7], Position::new(2, 2));
+        assert!(!neighbors.contains(&pos));
+    }
+
     #[test]
     fn test_grid_new() {
         let grid: Grid<f64> = Grid::new(2, 3, 1.0);
         assert_eq!(grid.rows, 2);
         assert_eq!(grid.cols, 3);
-        for row in grid.data.iter() {
-            for &value in row.iter() {
-                assert_eq!(value, 1.0);
-            }
+        assert_eq!(grid.data.len(), 6);
+        for &value in grid.data.iter() {
+            assert_eq!(value, 1.0);
+        }
+    }
+
+    #[test]
+    fn test_grid_try_new() {
+        let grid = Grid::try_new(2, 3, 0).unwrap();
+        assert_eq!((grid.rows(), grid.cols()), (2, 3));
+
+        assert_eq!(Grid::try_new(0, 5, 0), Err(GridError::Empty));
+        assert_eq!(Grid::try_new(5, 0, 0), Err(GridError::Empty));
+        assert_eq!(Grid::try_new(0, 0, 0), Err(GridError::Empty));
+    }
+
+    #[test]
+    fn test_grid_from_rows() {
+        let grid = Grid::from_rows(vec![vec![1, 2, 3], vec![4, 5, 6]]).unwrap();
+        assert_eq!(grid.rows(), 2);
+        assert_eq!(grid.cols(), 3);
+        assert_eq!(grid[&Position::new(2, 1)], 6);
+
+        assert_eq!(Grid::<usize>::from_rows(vec![]), Err(GridError::Empty));
+        assert_eq!(
+            Grid::<usize>::from_rows(vec![vec![]]),
+            Err(GridError::Empty)
+        );
+        assert_eq!(
+            Grid::from_rows(vec![vec![1, 2], vec![3, 4], vec![5]]),
+            Err(GridError::Ragged {
+                row: 2,
+                expected: 2,
+                found: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_grid_fill() {
+        let mut grid: Grid<usize> = Grid::new(2, 3, 1);
+        grid.fill(7);
+        for &value in grid.data.iter() {
+            assert_eq!(value, 7);
+        }
+    }
+
+    #[test]
+    fn test_grid_resize() {
+        let mut grid = Grid::from_rows(vec![vec![1, 2, 3], vec![4, 5, 6]]).unwrap();
+        grid.resize(3, 4, 0);
+        assert_eq!(
+            grid,
+            Grid::from_rows(vec![vec![1, 2, 3, 0], vec![4, 5, 6, 0], vec![0, 0, 0, 0]]).unwrap()
+        );
+        grid.resize(2, 2, 9);
+        assert_eq!(grid, Grid::from_rows(vec![vec![1, 2], vec![4, 5]]).unwrap());
+
+        // Growing one way while shrinking the other keeps the overlap.
+        grid.resize(3, 1, 7);
+        assert_eq!(
+            grid,
+            Grid::from_rows(vec![vec![1], vec![4], vec![7]]).unwrap()
+        );
+        grid.resize(0, 3, 7);
+        assert_eq!(grid, Grid::new(0, 3, 7));
+        grid.resize(1, 2, 8);
+        assert_eq!(grid, Grid::new(1, 2, 8));
+
+        let mut states = Grid::new(1, 1, CellState::Occupied);
+        states.resize(1, 2, CellState::Unknown);
+        assert_eq!(states[&Position::new(0, 0)], CellState::Occupied);
+        assert_eq!(states[&Position::new(1, 0)], CellState::Unknown);
+    }
+
+    #[test]
+    fn test_grid_index() {
+        let mut grid: Grid<u32> = Grid::new(2, 3, 0);
+        grid[&Position::new(2, 1)] = 5;
+        assert_eq!(grid[&Position::new(2, 1)], 5);
+        assert_eq!(grid.data[5], 5);
+
+        let mut grid: Grid<Color> = Grid::new(1, 1, Color(0, 0, 0));
+        grid[&Position::new(0, 0)].1 = 255;
+        assert_eq!(grid[&Position::new(0, 0)], Color(0, 255, 0));
+    }
+
+    #[test]
+    fn test_grid_get() {
+        let mut grid: Grid<u32> = Grid::new(2, 3, 0);
+        assert_eq!(grid.get(&Position::new(2, 1)), Some(&0));
+        assert_eq!(grid.get(&Position::new(3, 1)), None);
+        assert_eq!(grid.get(&Position::new(2, 2)), None);
+
+        *grid.get_mut(&Position::new(1, 0)).unwrap() = 4;
+        assert_eq!(grid[&Position::new(1, 0)], 4);
+        assert_eq!(grid.get_mut(&Position::new(0, 2)), None);
+        assert_eq!(grid.get_mut(&Position::new(3, 0)), None);
+    }
+
+    #[test]
+    fn test_grid_map() {
+        let mut grid: Grid<usize> = Grid::new(2, 3, 1);
+        grid[&Position::new(2, 1)] = 42;
+
+        let mapped: Grid<String> = grid.map(|elem| elem.to_string());
+        assert_eq!(mapped.rows(), 2);
+        assert_eq!(mapped.cols(), 3);
+        assert_eq!(mapped[&Position::new(0, 0)], "1");
+        assert_eq!(mapped[&Position::new(2, 1)], "42");
+    }
+
+    #[test]
+    fn test_grid_iter_positions() {
+        let mut grid: Grid<usize> = Grid::new(2, 3, 0);
+        for (i, (_, elem)) in grid.iter_positions_mut().enumerate() {
+            *elem = i;
         }
+
+        let positions: Vec<(Position, usize)> = grid
+            .iter_positions()
+            .map(|(pos, &elem)| (pos, elem))
+            .collect();
+        assert_eq!(
+            positions,
+            vec![
+                (Position::new(0, 0), 0),
+                (Position::new(1, 0), 1),
+                (Position::new(2, 0), 2),
+                (Position::new(0, 1), 3),
+                (Position::new(1, 1), 4),
+                (Position::new(2, 1), 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_grid_row() {
+        let grid = Grid::from_rows(vec![vec![1, 2, 3], vec![4, 5, 6]]).unwrap();
+        assert_eq!(grid.row(0), Some(&[1, 2, 3][..]));
+        assert_eq!(grid.row(1), Some(&[4, 5, 6][..]));
+        assert_eq!(grid.row(2), None);
+    }
+
+    #[test]
+    fn test_grid_col() {
+        let grid = Grid::from_rows(vec![vec![1, 2, 3], vec![4, 5, 6]]).unwrap();
+        assert_eq!(grid.col(0).unwrap().collect::<Vec<_>>(), vec![&1, &4]);
+        assert_eq!(grid.col(2).unwrap().collect::<Vec<_>>(), vec![&3, &6]);
+        assert!(grid.col(3).is_none());
+
+        let empty: Grid<i32> = Grid::new(0, 2, 0);
+        assert_eq!(empty.col(1).unwrap().count(), 0);
+        assert_eq!(empty.row(0), None);
+    }
+
+    #[test]
+    fn test_grid_fill_region() {
+        let mut grid = Grid::new(3, 4, 0);
+        assert!(grid.fill_region(&Position::new(1, 1), &Rectangle::new_unchecked(3, 2), 7));
+        assert_eq!(grid.to_string(), "0 0 0 0\n0 7 7 7\n0 7 7 7");
+        assert!(grid.fill_region(&Position::new(0, 0), &Rectangle::new_unchecked(1, 3), 1));
+        assert_eq!(grid.to_string(), "1 0 0 0\n1 7 7 7\n1 7 7 7");
+    }
+
+    #[test]
+    fn test_grid_fill_region_out_of_bounds() {
+        let mut grid = Grid::new(2, 3, false);
+        // Regions past the right or bottom edge, or starting outside the grid, write nothing.
+        assert!(!grid.fill_region(&Position::new(2, 0), &Rectangle::new_unchecked(2, 1), true));
+        assert!(!grid.fill_region(&Position::new(0, 1), &Rectangle::new_unchecked(1, 2), true));
+        assert!(!grid.fill_region(&Position::new(5, 5), &Rectangle::new_unchecked(1, 1), true));
+        assert_eq!(grid, Grid::new(2, 3, false));
+
+        assert!(grid.fill_region(&Position::new(0, 0), &Rectangle::new_unchecked(3, 2), true));
+        assert_eq!(grid, Grid::new(2, 3, true));
+    }
+
+    #[test]
+    fn test_grid_count() {
+        let grid =
+            Grid::from_rows(vec![vec![true, false, true], vec![false, false, true]]).unwrap();
+        assert_eq!(grid.count(|&blocked| blocked), 3);
+        assert_eq!(grid.count(|&blocked| !blocked), 3);
+
+        let grid = Grid::from_rows(vec![vec![1, 5, 2], vec![7, 0, 9]]).unwrap();
+        assert_eq!(grid.count(|&elem| elem > 4), 3);
+        assert_eq!(grid.count(|&elem| elem > 9), 0);
+        assert_eq!(Grid::new(0, 0, 1).count(|_| true), 0);
+    }
+
+    #[test]
+    fn test_grid_sum() {
+        let grid = Grid::from_rows(vec![vec![0.25, 0.0, 1.0], vec![0.5, 0.75, 0.5]]).unwrap();
+        assert_eq!(grid.sum(), 3.0);
+        assert_eq!(Grid::new(2, 0, 1.0).sum(), 0.0);
+    }
+
+    #[test]
+    fn test_grid_max_abs_diff() {
+        let grid = Grid::from_rows(vec![vec![0.25, 0.0, 1.0], vec![0.5, 0.75, 0.5]]).unwrap();
+        let other = Grid::from_rows(vec![vec![0.5, 0.0, 0.875], vec![0.5, 0.25, 0.5]]).unwrap();
+        assert_eq!(grid.max_abs_diff(&other), Some(0.5));
+        assert_eq!(other.max_abs_diff(&grid), Some(0.5));
+        assert_eq!(grid.max_abs_diff(&grid), Some(0.0));
+        assert_eq!(
+            Grid::new(0, 0, 0.0).max_abs_diff(&Grid::new(0, 0, 0.0)),
+            Some(0.0)
+        );
+
+        assert_eq!(grid.max_abs_diff(&grid.transpose()), None);
+        assert_eq!(grid.max_abs_diff(&Grid::new(2, 4, 0.0)), None);
+    }
+
+    #[test]
+    fn test_grid_transpose() {
+        let grid = Grid::from_rows(vec![vec![1, 2, 3], vec![4, 5, 6]]).unwrap();
+        let transposed = grid.transpose();
+        assert_eq!(
+            transposed,
+            Grid::from_rows(vec![vec![1, 4], vec![2, 5], vec![3, 6]]).unwrap()
+        );
+        assert_eq!(transposed.transpose(), grid);
+    }
+
+    #[test]
+    fn test_grid_rotate90() {
+        let grid = Grid::from_rows(vec![vec![1, 2, 3], vec![4, 5, 6]]).unwrap();
+        let rotated = grid.rotate90();
+        assert_eq!(
+            rotated,
+            Grid::from_rows(vec![vec![4, 1], vec![5, 2], vec![6, 3]]).unwrap()
+        );
+        assert_eq!(rotated.rotate90().rotate90().rotate90(), grid);
+    }
+
+    #[test]
+    fn test_grid_display() {
+        let mask = Grid::from_rows(vec![vec![false, true, false], vec![true, false, false]]);
+        assert_eq!(mask.unwrap().to_string(), ".#.\n#..");
+
+        let placed = Grid::from_rows(vec![vec![0, 12, 12], vec![3, 0, 0]]);
+        assert_eq!(placed.unwrap().to_string(), " 0 12 12\n 3  0  0");
+
+        let probabilities = Grid::from_rows(vec![vec![0.0, 0.125], vec![1.0, 0.3333]]);
+        assert_eq!(probabilities.unwrap().to_string(), "0.00 0.12\n1.00 0.33");
+    }
+
+    #[test]
+    fn test_grid_try_from_rows() {
+        let mask = Grid::try_from(vec![vec![false, true], vec![true, true]]).unwrap();
+        assert_eq!(mask.count(|&blocked| blocked), 3);
+        assert_eq!(
+            Grid::try_from(vec![vec![false, true], vec![true]]),
+            Err(GridError::Ragged {
+                row: 1,
+                expected: 2,
+                found: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_grid_from_strs() {
+        let rows = ["..#.", "#...", "...#"];
+        let mask = Grid::from(&rows[..]);
+        assert_eq!((mask.rows(), mask.cols()), (3, 4));
+        assert!(mask[&Position::new(2, 0)]);
+        assert!(!mask[&Position::new(2, 1)]);
+        assert_eq!(mask.to_string(), rows.join("\n"));
+    }
+
+    #[test]
+    fn test_grid_random_mask() {
+        for (fraction, blocked) in [(0.0, 0), (0.4, 38), (0.5, 48), (1.0, 96), (2.0, 96)] {
+            let mask = Grid::random_mask(8, 12, fraction, 7);
+            assert_eq!((mask.rows(), mask.cols()), (8, 12));
+            assert_eq!(mask.count(|&cell| cell), blocked);
+        }
+        assert_eq!(Grid::random_mask(0, 3, 0.5, 7), Grid::new(0, 3, false));
+
+        // The same seed blocks the same cells.
+        let mask = Grid::random_mask(8, 12, 0.4, 7);
+        assert_eq!(Grid::random_mask(8, 12, 0.4, 7), mask);
+        assert_ne!(Grid::random_mask(8, 12, 0.4, 8), mask);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid mask")]
+    fn test_grid_from_strs_ragged() {
+        let _ = Grid::from(&["..", "."][..]);
+    }
+
+    #[test]
+    fn test_grid_serde() {
+        let mut grid: Grid<usize> = Grid::new(2, 3, 0);
+        grid[&Position::new(2, 0)] = 1;
+        grid[&Position::new(0, 1)] = 2;
+
+        let json = serde_json::to_string(&grid).unwrap();
+        assert_eq!(json, r#"{"rows":2,"cols":3,"data":[[0,0,1],[2,0,0]]}"#);
+        let parsed: Grid<usize> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, grid);
+
+        let parsed: Result<Grid<usize>, _> =
+            serde_json::from_str(r#"{"rows":2,"cols":3,"data":[[0,0,1],[2,0]]}"#);
+        assert!(parsed.is_err());
+        let parsed: Result<Grid<usize>, _> =
+            serde_json::from_str(r#"{"rows":3,"cols":3,"data":[[0,0,1],[2,0,0]]}"#);
+        assert!(parsed.is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_grid_index_out_of_range() {
+        let grid: Grid<u32> = Grid::new(2, 3, 0);
+        let _ = grid[&Position::new(3, 0)];
+    }
+
+    #[test]
+    fn test_grid_clone_from() {
+        let source: Grid<bool> = Grid::new(3, 2, true);
+        let mut grid: Grid<bool> = Grid::new(2, 2, false);
+        grid.clone_from(&source);
+        assert!(grid == source);
     }
 
     #[test]
     fn test_grid_all() {
         let grid: Grid<bool> = Grid::new(2, 2, true);
-        let rect = Rectangle::new(2, 2);
+        let rect = Rectangle::new_unchecked(2, 2);
         let pos = Position::new(0, 0);
-        assert!(grid.all(&pos, &rect, &true));
+        assert!(grid.all(&pos, &Shape::from(&rect), &true));
     }
 
     #[test]
     fn test_grid_all_out_of_bounds() {
         let grid: Grid<bool> = Grid::new(2, 2, true);
-        let rect = Rectangle::new(2, 2);
+        let rect = Rectangle::new_unchecked(2, 2);
         let pos = Position::new(1, 1);
-        assert!(!grid.all(&pos, &rect, &true));
+        assert!(!grid.all(&pos, &Shape::from(&rect), &true));
+        let pos = Position::new(usize::MAX, 0);
+        assert!(!grid.all(&pos, &Shape::from(&rect), &true));
     }
 
     #[test]
@@ -228,22 +1844,50 @@ mod tests {
         let grid: Grid<f64> = Grid::new(2, 2, 0.5);
         let cmap = ColorMap::Magma;
         let result = grid.to_value_color_pairs(&cmap);
-        for row in result.data.iter() {
-            for &(value, ref color) in row.iter() {
-                assert_eq!(value, 0.5);
-                assert_eq!(*color, to_rgb(0.5, &cmap));
-            }
+        for (value, color) in result.data.iter() {
+            assert_eq!(*value, 0.5);
+            assert_eq!(*color, to_rgb(0.5, &cmap));
         }
     }
 
+    #[test]
+    fn test_grid_to_normalized_color_pairs() {
+        let grid = Grid::from_rows(vec![vec![0.2, 0.4, 0.6]]).unwrap();
+        let pairs = grid.to_normalized_color_pairs(&ColorMap::Grayscale);
+        assert_eq!(pairs[&Position::new(0, 0)], (0.2, Color(0, 0, 0)));
+        assert_eq!(pairs[&Position::new(2, 0)], (0.6, Color(255, 255, 255)));
+
+        let flat = Grid::new(1, 2, 0.5);
+        assert_eq!(
+            flat.to_normalized_color_pairs(&ColorMap::Grayscale),
+            flat.to_value_color_pairs(&ColorMap::Grayscale)
+        );
+    }
+
+    #[test]
+    fn test_grid_to_rescaled_color_pairs() {
+        let grid = Grid::from_rows(vec![vec![0.0, 0.1, 0.4]]).unwrap();
+        let pairs = grid.to_rescaled_color_pairs(&ColorMap::Grayscale);
+        assert_eq!(pairs[&Position::new(0, 0)], (0.0, Color(0, 0, 0)));
+        assert_eq!(
+            pairs[&Position::new(1, 0)],
+            (0.1, to_rgb(0.25, &ColorMap::Grayscale))
+        );
+        assert_eq!(pairs[&Position::new(2, 0)], (0.4, Color(255, 255, 255)));
+
+        let zero = Grid::new(1, 2, 0.0);
+        assert_eq!(
+            zero.to_rescaled_color_pairs(&ColorMap::Grayscale),
+            zero.to_value_color_pairs(&ColorMap::Grayscale)
+        );
+    }
+
     #[test]
     fn test_grid_div() {
         let grid: Grid<f64> = Grid::new(2, 2, 4.0);
         let result = grid / 2.0;
-        for row in result.data.iter() {
-            for &value in row.iter() {
-                assert_eq!(value, 2.0);
-            }
+        for &value in result.data.iter() {
+            assert_eq!(value, 2.0);
         }
     }
 
@@ -251,10 +1895,8 @@ mod tests {
     fn test_grid_div_assign() {
         let mut grid: Grid<f64> = Grid::new(2, 2, 4.0);
         grid /= 2.0;
-        for row in grid.data.iter() {
-            for &value in row.iter() {
-                assert_eq!(value, 2.0);
-            }
+        for &value in grid.data.iter() {
+            assert_eq!(value, 2.0);
         }
     }
 }
@@ -0,0 +1,118 @@
+//! The entry point of the estimator in the browser, for boards small enough to estimate without
+//! the backend.
+//!
+//! Built for `wasm32-unknown-unknown` without the `server` feature. rayon cannot spawn threads
+//! there, so the simulations run on the calling thread.
+
+use crate::estimator::{
+    DEFAULT_NODE_BUDGET, Estimate, EstimateOptions, estimate_exact_with, estimate_with_options,
+    to_checked_shapes,
+};
+use crate::types::{Grid, Rectangle};
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/// The estimate of a board, as returned to JavaScript.
+#[derive(Serialize, Deserialize)]
+struct EstimateMessage {
+    /// The probability that each cell is covered by a rectangle.
+    probabilities: Grid<f64>,
+    /// The standard error of each probability.
+    std_error: Grid<f64>,
+    /// The most likely rectangle to cover each cell and its probability.
+    occupants: Grid<(usize, f64)>,
+    /// The fraction of simulations in which every rectangle was placed.
+    feasibility: f64,
+    /// The number of simulations run, or 0 for an exact estimate.
+    simulations_run: usize,
+}
+
+impl From<Estimate> for EstimateMessage {
+    fn from(estimate: Estimate) -> EstimateMessage {
+        EstimateMessage {
+            probabilities: estimate.probabilities,
+            std_error: estimate.std_error,
+            occupants: estimate.occupants,
+            feasibility: estimate.feasibility,
+            simulations_run: estimate.stats.simulations_run,
+        }
+    }
+}
+
+/// Estimates the probabilities of a board given as JSON.
+///
+/// The layouts are enumerated exactly when there are few enough of them, and sampled with the
+/// default options otherwise.
+///
+/// # Arguments
+///
+/// * `mask_json` - A [`Grid<bool>`] mask as JSON, where `true` marks a blocked cell.
+/// * `rects_json` - A JSON array of the [`Rectangle`]s to be placed.
+///
+/// # Returns
+///
+/// The probabilities, standard errors, occupants and feasibility as JSON, or a description of the
+/// argument that could not be parsed or of the [`Error`](crate::error::Error) the board has.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub fn estimate(mask_json: &str, rects_json: &str) -> Result<String, String> {
+    let mask: Grid<bool> =
+        serde_json::from_str(mask_json).map_err(|error| format!("invalid mask: {error}"))?;
+    let rectangles: Vec<Rectangle> =
+        serde_json::from_str(rects_json).map_err(|error| format!("invalid rectangles: {error}"))?;
+
+    let options = EstimateOptions::default();
+    let shapes =
+        to_checked_shapes(&mask, &rectangles, &options).map_err(|error| error.to_string())?;
+    let estimate = estimate_exact_with(&mask, &rectangles, None, DEFAULT_NODE_BUDGET)
+        .unwrap_or_else(|| estimate_with_options(&mask, &shapes, &options));
+    serde_json::to_string(&EstimateMessage::from(estimate)).map_err(|error| error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Position;
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn test_estimate_round_trip() {
+        let mask = serde_json::to_string(&Grid::new(3, 3, false)).unwrap();
+        let rectangles = r#"[{"width": 3, "height": 3}]"#;
+
+        let message: EstimateMessage =
+            serde_json::from_str(&estimate(&mask, rectangles).unwrap()).unwrap();
+        assert_eq!(message.feasibility, 1.0);
+        assert_eq!(message.simulations_run, 0);
+        assert_eq!(message.probabilities, Grid::new(3, 3, 1.0));
+        assert_eq!(message.occupants[&Position::new(2, 2)], (1, 1.0));
+    }
+
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn test_estimate_invalid() {
+        let mask = serde_json::to_string(&Grid::new(3, 3, false)).unwrap();
+        assert!(
+            estimate("[[false]]", "[]")
+                .unwrap_err()
+                .starts_with("invalid mask")
+        );
+        assert!(
+            estimate(&mask, r#"[{"width": 0, "height": 1}]"#)
+                .unwrap_err()
+                .starts_with("invalid rectangles")
+        );
+        assert_eq!(
+            estimate(&mask, r#"[{"width": 4, "height": 1}]"#).unwrap_err(),
+            "rectangle 0 does not fit within the grid"
+        );
+        let weighted =
+            r#"[{"width": 1, "height": 1, "weights": {"rows": 1, "cols": 1, "data": [[1.0]]}}]"#;
+        assert_eq!(
+            estimate(&mask, weighted).unwrap_err(),
+            "the weights of rectangle 0 must have the same size as the mask, 3x3"
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// The board of `tests/fixtures/board.txt`: a 3x1 rectangle that only fits the free top row.
+const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/board.txt");
+
+/// Runs the CLI with the given arguments and stdin.
+fn run(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_estimate-cli"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_estimate_cli_text() {
+    let output = run(&[FIXTURE, "--simulations", "100", "--seed", "1"], "");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "1.00 1.00 1.00\n0.00 0.00 0.00\n"
+    );
+}
+
+#[test]
+fn test_estimate_cli_json_stdin() {
+    let board = std::fs::read_to_string(FIXTURE).unwrap();
+    let output = run(
+        &["--json", "--cmap", "grayscale", "--simulations", "100"],
+        &board,
+    );
+    assert!(output.status.success());
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        json["probabilities"]["data"],
+        serde_json::json!([[1.0, 1.0, 1.0], [0.0, 0.0, 0.0]])
+    );
+    assert_eq!(json["colors"]["data"][0][0], "#ffffff");
+    assert_eq!(json["colors"]["data"][1][0], "#000000");
+    assert_eq!(json["feasibility"], 1.0);
+}
+
+#[test]
+fn test_estimate_cli_invalid() {
+    let output = run(&["--simulations", "0"], "");
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = run(&[], "..x\n2x1\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("invalid mask cell")
+    );
+
+    let output = run(&[], "..\n3x1\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("rectangle 0 does not fit within the grid")
+    );
+}